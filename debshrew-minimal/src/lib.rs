@@ -77,6 +77,7 @@ impl debshrew_runtime::transform::DebTransform for MinimalTransform {
                     block_height: height,
                     block_hash: format!("{:02x}", block_hash_byte),
                     transaction_id: None,
+                    inverse: false,
                 },
                 payload: CdcPayload {
                     operation: CdcOperation::Create,
@@ -130,6 +131,7 @@ impl debshrew_runtime::transform::DebTransform for MinimalTransform {
                             block_height: height, // Target height for rollback
                             block_hash: format!("{:02x}", block_hash_byte),
                             transaction_id: None,
+                            inverse: false,
                         },
                         payload: CdcPayload {
                             operation: CdcOperation::Delete,
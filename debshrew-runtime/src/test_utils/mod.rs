@@ -96,6 +96,7 @@ mod tests {
                     block_height: 123,
                     block_hash: "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
                     transaction_id: None,
+                    inverse: false,
                 },
                 payload: CdcPayload {
                     operation: CdcOperation::Create,
@@ -121,6 +122,7 @@ mod tests {
                     block_height: 123,
                     block_hash: "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
                     transaction_id: None,
+                    inverse: false,
                 },
                 payload: CdcPayload {
                     operation: CdcOperation::Delete,
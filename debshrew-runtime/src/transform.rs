@@ -125,6 +125,7 @@ mod tests {
                 block_height: 123,
                 block_hash: "000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d".to_string(),
                 transaction_id: None,
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,
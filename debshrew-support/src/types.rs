@@ -27,6 +27,10 @@ pub struct CdcHeader {
     /// Transaction ID where the change occurred (if applicable)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transaction_id: Option<String>,
+    
+    /// Whether the message reverts an earlier message because of a reorg
+    #[serde(default)]
+    pub inverse: bool,
 }
 
 /// CDC message payload
@@ -81,6 +85,79 @@ pub enum CdcOperation {
     Delete,
 }
 
+/// CDC control record
+///
+/// Marks block and reorg boundaries in a CDC stream, so consumers can tell
+/// which messages belong to which block and which ones revert a reorg.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CdcControl {
+    /// The messages of a block follow
+    BeginBlock {
+        /// Block height
+        height: u32,
+        
+        /// Block hash
+        hash: String,
+        
+        /// Number of CDC messages in the block
+        message_count: u32,
+    },
+    
+    /// All messages of a block have been sent
+    EndBlock {
+        /// Block height
+        height: u32,
+        
+        /// Block hash
+        hash: String,
+        
+        /// Number of CDC messages in the block
+        message_count: u32,
+    },
+    
+    /// The inverse messages of a reorg follow
+    ReorgBegin {
+        /// The common ancestor the stream is rolled back to
+        fork_height: u32,
+        
+        /// Number of blocks rolled back
+        depth: u32,
+    },
+    
+    /// All inverse messages of a reorg have been sent
+    ReorgEnd {
+        /// The common ancestor the stream was rolled back to
+        fork_height: u32,
+        
+        /// Number of blocks rolled back
+        depth: u32,
+    },
+}
+
+/// CDC control message
+///
+/// A control record, with the same source and timestamp metadata as a CDC
+/// message header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CdcControlMessage {
+    /// Source of the control message (e.g., "debshrew")
+    pub source: String,
+    
+    /// Timestamp when the control message was generated (milliseconds since UNIX epoch)
+    pub timestamp: u64,
+    
+    /// The control record
+    #[serde(flatten)]
+    pub control: CdcControl,
+}
+
+/// The Kafka key of control messages
+///
+/// Control messages share a topic with CDC messages, so consumers use this
+/// key to tell them apart.
+pub const CONTROL_MESSAGE_KEY: &str = "__debshrew_control";
+
 /// Transform state
 ///
 /// Represents the state of a transform module, which is a key-value store
@@ -260,6 +337,7 @@ mod tests {
                 block_height: 123456,
                 block_hash: "000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d".to_string(),
                 transaction_id: Some("tx123".to_string()),
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,
@@ -277,6 +355,34 @@ mod tests {
         let deserialized: CdcMessage = serde_json::from_str(&json).unwrap();
         
         assert_eq!(deserialized, message);
+        
+        // Messages serialized before the inverse flag existed are not inverse
+        let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+        legacy["header"].as_object_mut().unwrap().remove("inverse");
+        let deserialized: CdcMessage = serde_json::from_value(legacy).unwrap();
+        assert!(!deserialized.header.inverse);
+    }
+    
+    #[test]
+    fn test_cdc_control_message_serialization() {
+        let message = CdcControlMessage {
+            source: "debshrew".to_string(),
+            timestamp: 1672531200000,
+            control: CdcControl::BeginBlock {
+                height: 123456,
+                hash: "00".to_string(),
+                message_count: 3,
+            },
+        };
+        
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "BEGIN_BLOCK");
+        assert_eq!(json["height"], 123456);
+        assert_eq!(json["message_count"], 3);
+        assert_eq!(serde_json::from_value::<CdcControlMessage>(json).unwrap(), message);
+        
+        let json = serde_json::to_value(CdcControl::ReorgEnd { fork_height: 100, depth: 2 }).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "REORG_END", "fork_height": 100, "depth": 2 }));
     }
     
     #[test]
@@ -4,7 +4,7 @@
 //! It consumes messages from a Kafka topic and logs them to stdout.
 
 use clap::{Parser, Subcommand};
-use debshrew_support::{CdcMessage, CdcOperation};
+use debshrew_support::{CdcControl, CdcControlMessage, CdcMessage, CdcOperation, CONTROL_MESSAGE_KEY};
 use env_logger::Env;
 use log::{error, info, warn};
 use rdkafka::config::ClientConfig;
@@ -37,6 +37,24 @@ struct KafkaConfig {
     session_timeout_ms: u64,
 }
 
+/// Describe a control record for the log
+fn describe_control(control: &CdcControl) -> String {
+    match control {
+        CdcControl::BeginBlock { height, hash, message_count } => {
+            format!("BEGIN_BLOCK {} {} ({} messages)", height, hash, message_count)
+        }
+        CdcControl::EndBlock { height, hash, message_count } => {
+            format!("END_BLOCK {} {} ({} messages)", height, hash, message_count)
+        }
+        CdcControl::ReorgBegin { fork_height, depth } => {
+            format!("REORG_BEGIN to {} ({} blocks)", fork_height, depth)
+        }
+        CdcControl::ReorgEnd { fork_height, depth } => {
+            format!("REORG_END to {} ({} blocks)", fork_height, depth)
+        }
+    }
+}
+
 fn default_auto_offset_reset() -> String {
     "earliest".to_string()
 }
@@ -204,34 +222,51 @@ async fn main() -> anyhow::Result<()> {
                                 
                                 // Get the payload
                                 if let Some(payload) = msg.payload() {
-                                    // Parse the payload as a CDC message
-                                    match serde_json::from_slice::<CdcMessage>(payload) {
-                                        Ok(cdc_message) => {
-                                            // Log the message
-                                            let operation_str = match cdc_message.payload.operation {
-                                                CdcOperation::Create => "CREATE",
-                                                CdcOperation::Update => "UPDATE",
-                                                CdcOperation::Delete => "DELETE",
-                                            };
-                                            
-                                            info!("Received CDC message: {} {} {} (block: {})",
-                                                operation_str,
-                                                cdc_message.payload.table,
-                                                cdc_message.payload.key,
-                                                cdc_message.header.block_height);
-                                            
-                                            // Print the full message if requested
-                                            if pretty {
-                                                match serde_json::to_string_pretty(&cdc_message) {
-                                                    Ok(json) => println!("{}", json),
-                                                    Err(e) => warn!("Failed to serialize CDC message: {}", e),
+                                    // Block and reorg boundaries are sent under a reserved key
+                                    if msg.key() == Some(CONTROL_MESSAGE_KEY.as_bytes()) {
+                                        match serde_json::from_slice::<CdcControlMessage>(payload) {
+                                            Ok(control_message) => {
+                                                info!("Received control message: {}", describe_control(&control_message.control));
+                                                
+                                                if pretty {
+                                                    match serde_json::to_string_pretty(&control_message) {
+                                                        Ok(json) => println!("{}", json),
+                                                        Err(e) => warn!("Failed to serialize control message: {}", e),
+                                                    }
                                                 }
                                             }
+                                            Err(e) => warn!("Failed to parse control message: {}", e),
                                         }
-                                        Err(e) => {
-                                            warn!("Failed to parse CDC message: {}", e);
-                                            if let Ok(text) = std::str::from_utf8(payload) {
-                                                warn!("Raw payload: {}", text);
+                                    } else {
+                                        // Parse the payload as a CDC message
+                                        match serde_json::from_slice::<CdcMessage>(payload) {
+                                            Ok(cdc_message) => {
+                                                // Log the message
+                                                let operation_str = match cdc_message.payload.operation {
+                                                    CdcOperation::Create => "CREATE",
+                                                    CdcOperation::Update => "UPDATE",
+                                                    CdcOperation::Delete => "DELETE",
+                                                };
+                                                
+                                                info!("Received CDC message: {} {} {} (block: {})",
+                                                    operation_str,
+                                                    cdc_message.payload.table,
+                                                    cdc_message.payload.key,
+                                                    cdc_message.header.block_height);
+                                                
+                                                // Print the full message if requested
+                                                if pretty {
+                                                    match serde_json::to_string_pretty(&cdc_message) {
+                                                        Ok(json) => println!("{}", json),
+                                                        Err(e) => warn!("Failed to serialize CDC message: {}", e),
+                                                    }
+                                                }
+                                            }
+                                            Err(e) => {
+                                                warn!("Failed to parse CDC message: {}", e);
+                                                if let Ok(text) = std::str::from_utf8(payload) {
+                                                    warn!("Raw payload: {}", text);
+                                                }
                                             }
                                         }
                                     }
@@ -67,6 +67,12 @@ impl MemoryMetashrewAdapter {
         state.height = height;
     }
     
+    /// Get the current block height
+    pub fn current_height(&self) -> u32 {
+        let state = self.state.lock().unwrap();
+        state.height
+    }
+    
     /// Set a block hash for a given height
     pub fn set_block_hash(&self, height: u32, hash: Vec<u8>) {
         let mut state = self.state.lock().unwrap();
@@ -294,6 +300,7 @@ impl Default for MemoryAdapterBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::traits::ViewCall;
     
     #[tokio::test]
     async fn test_memory_adapter_basic_operations() {
@@ -317,6 +324,20 @@ mod tests {
         
         // Test missing view result
         assert!(adapter.call_view("missing_view", &[], None).await.is_err());
+        
+        // Test the default batch implementations
+        adapter.set_block_hash(122, vec![0]);
+        let hashes = BlockProviderLike::get_block_hashes(&adapter, 122..=123).await.unwrap();
+        assert_eq!(hashes, vec![vec![0], vec![1, 2, 3, 4]]);
+        assert!(BlockProviderLike::get_block_hashes(&adapter, 121..=123).await.is_err());
+        
+        let calls = vec![
+            ViewCall::new("test_view", &[5, 6], Some(123)),
+            ViewCall::new("missing_view", &[], None),
+        ];
+        let results = ViewProviderLike::call_views(&adapter, &calls).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &vec![7, 8, 9]);
+        assert!(results[1].is_err());
     }
     
     #[tokio::test]
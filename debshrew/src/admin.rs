@@ -0,0 +1,149 @@
+//! Operator control of a running synchronizer
+//!
+//! This module provides the handle used to pause, resume and rewind a running
+//! [`BlockSynchronizer`](crate::BlockSynchronizer) from another task, such as
+//! the admin endpoints of the embedded HTTP server. Commands take effect
+//! between blocks.
+
+use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Handle for controlling a running synchronizer
+///
+/// The handle can be cloned and used from other tasks. Every command is
+/// logged when it is requested, and again when the synchronizer applies it.
+#[derive(Debug, Clone, Default)]
+pub struct AdminHandle {
+    /// Whether processing is paused
+    paused: Arc<AtomicBool>,
+
+    /// The height a rewind was requested to, if one is pending
+    rewind: Arc<Mutex<Option<u32>>>,
+
+    /// Wakes the synchronizer when a command is issued
+    notify: Arc<Notify>,
+}
+
+impl AdminHandle {
+    /// Create a new admin handle
+    ///
+    /// # Returns
+    ///
+    /// A new admin handle
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pause processing after the current block
+    pub fn pause(&self) {
+        info!("Admin: pause requested");
+        self.paused.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+
+    /// Resume processing
+    pub fn resume(&self) {
+        info!("Admin: resume requested");
+        self.paused.store(false, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+
+    /// Check whether processing is paused
+    ///
+    /// # Returns
+    ///
+    /// True if processing is paused, false otherwise
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Request a rewind
+    ///
+    /// The synchronizer sends inverse CDC messages for every block above the
+    /// height, rolls back to it and processes forward again. A later request
+    /// replaces a pending one.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height to rewind to
+    pub fn request_rewind(&self, height: u32) {
+        info!("Admin: rewind to height {} requested", height);
+        *self.lock_rewind() = Some(height);
+        self.notify.notify_one();
+    }
+
+    /// Get the pending rewind
+    ///
+    /// # Returns
+    ///
+    /// The height a rewind was requested to, or None if no rewind is pending
+    pub fn pending_rewind(&self) -> Option<u32> {
+        *self.lock_rewind()
+    }
+
+    /// Clear a pending rewind once it has been handled
+    ///
+    /// A rewind requested to another height in the meantime stays pending.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The height of the handled rewind
+    pub fn clear_rewind(&self, height: u32) {
+        let mut rewind = self.lock_rewind();
+        if *rewind == Some(height) {
+            *rewind = None;
+        }
+    }
+
+    /// Check whether a command requires the synchronizer to stop processing blocks
+    ///
+    /// # Returns
+    ///
+    /// True if processing is paused or a rewind is pending
+    pub fn interrupts(&self) -> bool {
+        self.is_paused() || self.pending_rewind().is_some()
+    }
+
+    /// Wait until a command is issued
+    pub async fn changed(&self) {
+        self.notify.notified().await;
+    }
+
+    /// Lock the pending rewind, recovering from a poisoned lock
+    fn lock_rewind(&self) -> std::sync::MutexGuard<'_, Option<u32>> {
+        match self.rewind.lock() {
+            Ok(rewind) => rewind,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_handle_commands_are_shared() {
+        let handle = AdminHandle::new();
+        let synchronizer = handle.clone();
+        assert!(!synchronizer.interrupts());
+
+        handle.pause();
+        assert!(synchronizer.is_paused());
+        handle.resume();
+        assert!(!synchronizer.is_paused());
+
+        handle.request_rewind(100);
+        assert!(synchronizer.interrupts());
+        assert_eq!(synchronizer.pending_rewind(), Some(100));
+
+        // A newer request survives clearing the one that was handled
+        handle.request_rewind(90);
+        synchronizer.clear_rewind(100);
+        assert_eq!(synchronizer.pending_rewind(), Some(90));
+        synchronizer.clear_rewind(90);
+        assert_eq!(synchronizer.pending_rewind(), None);
+    }
+}
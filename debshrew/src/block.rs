@@ -63,6 +63,10 @@ impl BlockCache {
     
     /// Add a block to the cache
     ///
+    /// Cached blocks at the same or a higher height are replaced, so a block
+    /// that is processed again, for example when retrying after a sink
+    /// failure, never appears twice.
+    ///
     /// # Arguments
     ///
     /// * `metadata` - The block metadata
@@ -87,6 +91,11 @@ impl BlockCache {
             cdc_messages: transform_result.cdc_messages,
         };
         
+        // Drop any blocks this one replaces
+        while self.blocks.back().is_some_and(|block| block.metadata.height >= cached_block.metadata.height) {
+            self.blocks.pop_back();
+        }
+        
         // Add the block to the cache
         self.blocks.push_back(cached_block);
         
@@ -331,6 +340,7 @@ mod tests {
                 block_height: height,
                 block_hash: hash.to_string(),
                 transaction_id: None,
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,
@@ -447,6 +457,26 @@ mod tests {
         assert!(cache.is_empty());
     }
 
+    #[test]
+    fn test_add_block_replaces_same_or_higher_heights() {
+        let mut cache = BlockCache::new(5).unwrap();
+        
+        for height in 1..=4 {
+            let (metadata, result) = create_test_block(height, &format!("hash{}", height));
+            cache.add_block(metadata, result).unwrap();
+        }
+        
+        // Processing block 3 again replaces blocks 3 and 4
+        let (metadata, result) = create_test_block(3, "retry3");
+        cache.add_block(metadata, result).unwrap();
+        
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.highest_height(), Some(3));
+        assert_eq!(cache.get_block_hash(3), Some("retry3".to_string()));
+        assert!(cache.get_block_at_height(4).is_none());
+        assert_eq!(cache.get_cdc_messages_range(1, 3).len(), 3);
+    }
+
     #[test]
     fn test_find_common_ancestor() {
         // Create a block cache
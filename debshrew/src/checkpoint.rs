@@ -0,0 +1,158 @@
+//! Checkpoint persistence
+//!
+//! This module defines the checkpoint, which records the last block whose CDC
+//! messages were fully emitted, and the store used to persist it so that a
+//! restarted pipeline can resume right after that block.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Checkpoint
+///
+/// A checkpoint identifies the last block whose CDC messages reached the sink.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The block height
+    pub height: u32,
+
+    /// The block hash
+    pub hash: String,
+
+    /// The time the checkpoint was written, in milliseconds since the epoch
+    pub timestamp: u64,
+}
+
+/// File-backed checkpoint store
+///
+/// Checkpoints are written as JSON to a temporary file next to the target and
+/// then renamed over it, so a crash never leaves a partially written checkpoint.
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    /// The path of the checkpoint file
+    path: PathBuf,
+}
+
+impl CheckpointStore {
+    /// Create a new checkpoint store
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the checkpoint file
+    ///
+    /// # Returns
+    ///
+    /// A new checkpoint store
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Get the path of the checkpoint file
+    ///
+    /// # Returns
+    ///
+    /// The path of the checkpoint file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the checkpoint
+    ///
+    /// # Returns
+    ///
+    /// The stored checkpoint, or None if no checkpoint has been written yet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint file cannot be read or parsed
+    pub fn load(&self) -> Result<Option<Checkpoint>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| Error::File(format!("Failed to read checkpoint {}: {}", self.path.display(), e)))?;
+
+        let checkpoint = serde_json::from_str(&contents)
+            .map_err(|e| Error::File(format!("Failed to parse checkpoint {}: {}", self.path.display(), e)))?;
+
+        Ok(Some(checkpoint))
+    }
+
+    /// Save a checkpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - The checkpoint to save
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the checkpoint was saved successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint file cannot be written
+    pub fn save(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let contents = serde_json::to_string_pretty(checkpoint)?;
+
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        fs::write(&temp_path, contents)
+            .map_err(|e| Error::File(format!("Failed to write checkpoint {}: {}", temp_path.display(), e)))?;
+
+        fs::rename(&temp_path, &self.path)
+            .map_err(|e| Error::File(format!("Failed to replace checkpoint {}: {}", self.path.display(), e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_checkpoint_store() {
+        let dir = tempdir().unwrap();
+        let store = CheckpointStore::new(dir.path().join("checkpoint.json"));
+
+        // Nothing stored yet
+        assert_eq!(store.load().unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            height: 100,
+            hash: "abcd".to_string(),
+            timestamp: 1234,
+        };
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load().unwrap(), Some(checkpoint));
+
+        // A later checkpoint replaces the earlier one
+        let checkpoint = Checkpoint {
+            height: 101,
+            hash: "ef01".to_string(),
+            timestamp: 1235,
+        };
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load().unwrap(), Some(checkpoint));
+
+        // No temporary file is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_checkpoint_store_invalid_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("checkpoint.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let store = CheckpointStore::new(&path);
+        assert!(store.load().is_err());
+    }
+}
@@ -4,17 +4,28 @@
 
 use crate::error::{Error, Result};
 use crate::config::MetashrewConfig;
-use crate::traits::{BlockProviderLike, ViewProviderLike, MetashrewClientLike};
+use crate::error_policy::RetryPolicy;
+use crate::traits::{BlockProviderLike, ViewProviderLike, MetashrewClientLike, ViewCall};
 use async_trait::async_trait;
 use log;
-use reqwest::{Client, ClientBuilder};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, ClientBuilder, Identity, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
 use url::Url;
 
 /// Maximum length for logged response bodies (in characters)
 const MAX_RESPONSE_LOG_LENGTH: usize = 1000;
 
+/// Maximum number of requests sent in one JSON-RPC batch
+const MAX_BATCH_SIZE: usize = 500;
+
 /// Truncate a response string for logging purposes
 fn truncate_response_for_logging(response: &str) -> String {
     if response.len() <= MAX_RESPONSE_LOG_LENGTH {
@@ -28,7 +39,10 @@ fn truncate_response_for_logging(response: &str) -> String {
 
 /// Metashrew client trait
 ///
-/// This trait defines the interface for communicating with metashrew.
+/// This trait defines the interface for communicating with metashrew over a
+/// URL. The block synchronizer and the runtime are generic over
+/// [`MetashrewClientLike`]; wrap an implementation of this trait in a
+/// [`MetashrewClientAdapter`] to use it with them.
 #[async_trait]
 pub trait MetashrewClient: Send + Sync {
     /// Get the current block height
@@ -74,6 +88,53 @@ pub trait MetashrewClient: Send + Sync {
     /// Returns an error if the request fails
     async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>>;
     
+    /// Get the block hashes for a range of heights
+    ///
+    /// The default implementation requests one height at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `heights` - The block heights
+    ///
+    /// # Returns
+    ///
+    /// The block hashes, in height order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hash of any of the heights cannot be fetched
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        let mut hashes = Vec::new();
+        for height in heights {
+            hashes.push(self.get_block_hash(height).await?);
+        }
+        Ok(hashes)
+    }
+    
+    /// Call several view functions
+    ///
+    /// The default implementation makes one call at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `calls` - The view calls
+    ///
+    /// # Returns
+    ///
+    /// The result of each call, in the order of the calls
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the calls cannot be sent at all; failures of
+    /// individual calls are returned in their results
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        let mut results = Vec::with_capacity(calls.len());
+        for call in calls {
+            results.push(self.call_view(&call.view_name, &call.params, call.height).await);
+        }
+        Ok(results)
+    }
+    
     /// Get the URL of the metashrew service
     ///
     /// # Returns
@@ -119,7 +180,6 @@ struct JsonRpcResponse<T> {
     error: Option<JsonRpcError>,
     
     /// Request ID
-    #[allow(dead_code)]
     id: u32,
 }
 
@@ -137,7 +197,163 @@ struct JsonRpcError {
     data: Option<serde_json::Value>,
 }
 
+/// Credentials sent with every request
+#[derive(Clone)]
+enum Credentials {
+    /// HTTP basic authentication
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    
+    /// Bearer token authentication
+    Bearer(String),
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never log the secrets themselves
+        match self {
+            Credentials::Basic { username, .. } => f.debug_struct("Basic").field("username", username).finish_non_exhaustive(),
+            Credentials::Bearer(_) => f.write_str("Bearer"),
+        }
+    }
+}
+
+/// TLS settings applied to every HTTP client built for metashrew
+#[derive(Clone, Default)]
+struct TlsSettings {
+    /// CA certificates trusted in addition to the system roots
+    root_certificates: Vec<Certificate>,
+    
+    /// The client certificate for mutual TLS
+    identity: Option<Identity>,
+}
+
+impl fmt::Debug for TlsSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsSettings")
+            .field("root_certificates", &self.root_certificates.len())
+            .field("identity", &self.identity.is_some())
+            .finish()
+    }
+}
+
+impl TlsSettings {
+    /// Load the TLS settings from a configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The metashrew configuration
+    ///
+    /// # Returns
+    ///
+    /// The TLS settings
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a certificate or key cannot be read or parsed
+    fn from_config(config: &MetashrewConfig) -> Result<Self> {
+        let read = |path: &str| {
+            std::fs::read(path)
+                .map_err(|e| Error::Configuration(format!("Failed to read {}: {}", path, e)))
+        };
+        
+        let root_certificates = match &config.ca_cert_path {
+            Some(path) => Certificate::from_pem_bundle(&read(path)?)
+                .map_err(|e| Error::Configuration(format!("Invalid CA bundle {}: {}", path, e)))?,
+            None => Vec::new(),
+        };
+        
+        let identity = match (&config.client_cert_path, &config.client_key_path) {
+            (Some(cert_path), Some(key_path)) => Some(
+                Identity::from_pkcs8_pem(&read(cert_path)?, &read(key_path)?)
+                    .map_err(|e| Error::Configuration(format!("Invalid client certificate {}: {}", cert_path, e)))?
+            ),
+            (None, None) => None,
+            _ => return Err(Error::Configuration(
+                "client_cert_path and client_key_path must be set together".to_string()
+            )),
+        };
+        
+        Ok(Self {
+            root_certificates,
+            identity,
+        })
+    }
+}
+
+/// Failure of a single request attempt
+#[derive(Debug)]
+enum RequestFailure {
+    /// A failure that may not happen again, such as a connection error,
+    /// timeout or server error
+    Transient(Error),
+    
+    /// Metashrew has not indexed the requested block yet
+    NotIndexed(Error),
+    
+    /// A failure that happens the same way on every attempt
+    Permanent(Error),
+}
+
+impl RequestFailure {
+    /// Classify an error sending a request
+    fn from_send_error(error: reqwest::Error) -> Self {
+        let transient = error.is_connect() || error.is_timeout() || error.is_request();
+        let error = Error::MetashrewClient(format!("Failed to send request: {}", error));
+        if transient {
+            RequestFailure::Transient(error)
+        } else {
+            RequestFailure::Permanent(error)
+        }
+    }
+    
+    /// Classify a JSON-RPC error
+    fn from_rpc_error(error: &JsonRpcError) -> Self {
+        let code = error.code.unwrap_or(-1);
+        let not_indexed = crate::tip::is_not_available_message(&error.message);
+        let error = Error::MetashrewClient(format!("JSON-RPC error: {} (code: {})", error.message, code));
+        if not_indexed {
+            RequestFailure::NotIndexed(error)
+        } else {
+            RequestFailure::Permanent(error)
+        }
+    }
+    
+    /// Check whether the failure is worth retrying
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The JSON-RPC method that failed
+    fn is_retryable(&self, method: &str) -> bool {
+        match self {
+            RequestFailure::Transient(_) => true,
+            RequestFailure::NotIndexed(_) => method != "metashrew_getblockhash",
+            RequestFailure::Permanent(_) => false,
+        }
+    }
+    
+    /// Get the error of the failure
+    fn error(&self) -> &Error {
+        match self {
+            RequestFailure::Transient(error) | RequestFailure::NotIndexed(error) | RequestFailure::Permanent(error) => error,
+        }
+    }
+    
+    /// Convert the failure into its error
+    fn into_error(self) -> Error {
+        match self {
+            RequestFailure::Transient(error) | RequestFailure::NotIndexed(error) | RequestFailure::Permanent(error) => error,
+        }
+    }
+}
+
 /// JSON-RPC client for metashrew
+///
+/// Clones share the connection pool, the request ID counter and the limit on
+/// concurrent requests, so a single client can serve the synchronizer and the
+/// runtime's view calls.
 #[derive(Debug, Clone)]
 pub struct JsonRpcClient {
     /// The HTTP client
@@ -147,7 +363,22 @@ pub struct JsonRpcClient {
     url: Url,
     
     /// The request ID counter
-    request_id: u32,
+    request_id: Arc<AtomicU32>,
+    
+    /// Limits the number of requests in flight, if configured
+    request_limit: Option<Arc<Semaphore>>,
+    
+    /// Additional headers sent with every request
+    headers: HeaderMap,
+    
+    /// The credentials sent with every request, if any
+    credentials: Option<Credentials>,
+    
+    /// The TLS settings, kept for the blocking client
+    tls: TlsSettings,
+    
+    /// The retry policy for transient failures
+    retry_policy: RetryPolicy,
 }
 
 /// Synchronous version of MetashrewClient trait
@@ -184,11 +415,17 @@ impl JsonRpcClient {
             .map_err(|e| Error::MetashrewClient(format!("Invalid URL: {}", e)))?;
         
         let client = Client::new();
+        let retry_policy = MetashrewConfig::new(url.as_str()).retry_policy();
         
         Ok(Self {
             client,
             url,
-            request_id: 0,
+            request_id: Arc::new(AtomicU32::new(0)),
+            request_limit: None,
+            headers: HeaderMap::new(),
+            credentials: None,
+            tls: TlsSettings::default(),
+            retry_policy,
         })
     }
     
@@ -203,6 +440,9 @@ impl JsonRpcClient {
     
     /// Create a new JSON-RPC client from a configuration
     ///
+    /// The client sends the configured credentials and headers with every
+    /// request, and trusts the configured CA bundle and client certificate.
+    ///
     /// # Arguments
     ///
     /// * `config` - The metashrew configuration
@@ -213,26 +453,43 @@ impl JsonRpcClient {
     ///
     /// # Errors
     ///
-    /// Returns an error if the configuration is invalid
+    /// Returns an error if the configuration is invalid, or the bearer token,
+    /// certificates or key cannot be loaded
     pub fn from_config(config: &MetashrewConfig) -> Result<Self> {
         let url = Url::parse(&config.url)
             .map_err(|e| Error::MetashrewClient(format!("Invalid URL: {}", e)))?;
         
-        let client_builder = ClientBuilder::new()
+        // Collect the additional headers
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::Configuration(format!("Invalid header name {}: {}", name, e)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| Error::Configuration(format!("Invalid value for header {}: {}", name, e)))?;
+            headers.insert(name, value);
+        }
+        
+        // Resolve the credentials, preferring a bearer token
+        let credentials = match (config.resolve_bearer_token()?, &config.username) {
+            (Some(token), _) => Some(Credentials::Bearer(token)),
+            (None, Some(username)) => Some(Credentials::Basic {
+                username: username.clone(),
+                password: config.password.clone(),
+            }),
+            (None, None) => None,
+        };
+        
+        let tls = TlsSettings::from_config(config)?;
+        
+        let mut client_builder = ClientBuilder::new()
             .timeout(Duration::from_secs(config.timeout))
             .connect_timeout(Duration::from_secs(config.timeout));
         
-        // Add authentication if provided
-        if let (Some(_username), Some(_password)) = (&config.username, &config.password) {
-            // Create a client with basic auth
-            let client = client_builder.build()
-                .map_err(|e| Error::MetashrewClient(format!("Failed to build HTTP client: {}", e)))?;
-            
-            return Ok(Self {
-                client,
-                url,
-                request_id: 0,
-            });
+        for certificate in &tls.root_certificates {
+            client_builder = client_builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(identity) = &tls.identity {
+            client_builder = client_builder.identity(identity.clone());
         }
         
         let client = client_builder.build()
@@ -241,22 +498,78 @@ impl JsonRpcClient {
         Ok(Self {
             client,
             url,
-            request_id: 0,
+            request_id: Arc::new(AtomicU32::new(0)),
+            request_limit: config.max_concurrent_requests.map(|limit| Arc::new(Semaphore::new(limit))),
+            headers,
+            credentials,
+            tls,
+            retry_policy: config.retry_policy(),
         })
     }
     
+    /// Check the HTTP status of a response
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the request succeeded
+    ///
+    /// # Errors
+    ///
+    /// Returns a permanent authentication failure if metashrew rejected the
+    /// credentials, a transient failure for server errors and rate limiting,
+    /// and a permanent failure for any other unsuccessful status
+    fn check_status(&self, status: StatusCode) -> std::result::Result<(), RequestFailure> {
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return Err(RequestFailure::Permanent(Error::Authentication(format!(
+                "Metashrew at {} rejected the request: {}", self.url, status
+            ))));
+        }
+        
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(RequestFailure::Transient(Error::MetashrewClient(format!("HTTP error: {}", status))));
+        }
+        
+        if !status.is_success() {
+            return Err(RequestFailure::Permanent(Error::MetashrewClient(format!("HTTP error: {}", status))));
+        }
+        
+        Ok(())
+    }
+    
     /// Get the next request ID
     ///
     /// # Returns
     ///
     /// The next request ID
-    fn next_request_id(&mut self) -> u32 {
-        let id = self.request_id;
-        self.request_id = self.request_id.wrapping_add(1);
-        id
+    fn next_request_id(&self) -> u32 {
+        self.request_id.fetch_add(1, Ordering::Relaxed)
     }
     
-    /// Send a JSON-RPC request
+    /// Wait until another request may be sent
+    ///
+    /// # Returns
+    ///
+    /// The permit to hold while the request is in flight, or None if requests
+    /// are not limited
+    async fn acquire_request_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.request_limit {
+            // The semaphore is never closed, so acquiring cannot fail
+            Some(limit) => limit.acquire().await.ok(),
+            None => None,
+        }
+    }
+    
+    /// Send a JSON-RPC request, retrying transient failures
+    ///
+    /// Connection errors, timeouts, server errors and blocks metashrew has
+    /// not indexed yet are retried with exponential backoff and jitter, up to
+    /// the configured number of retries. Block hash requests are not retried
+    /// when the block is not indexed yet, since the tip tracker probes for
+    /// exactly that answer.
     ///
     /// # Arguments
     ///
@@ -269,8 +582,237 @@ impl JsonRpcClient {
     ///
     /// # Errors
     ///
-    /// Returns an error if the request fails
-    async fn send_request<T, R>(&mut self, method: &str, params: T) -> Result<R>
+    /// Returns an error if the request fails permanently or runs out of retries
+    async fn send_request<T, R>(&self, method: &str, params: T) -> Result<R>
+    where
+        T: Serialize,
+        R: for<'de> Deserialize<'de>,
+    {
+        let mut failures = 0;
+        loop {
+            let failure = match self.send_request_once(method, &params).await {
+                Ok(result) => return Ok(result),
+                Err(failure) => failure,
+            };
+            
+            failures += 1;
+            if !failure.is_retryable(method) || !self.retry_policy.should_retry(failures) {
+                return Err(failure.into_error());
+            }
+            
+            let delay = self.retry_policy.backoff_with_jitter(failures);
+            self.log_retry(method, failure.error(), failures, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    /// Send a JSON-RPC request synchronously, retrying transient failures
+    ///
+    /// Failures are retried the same way as [`send_request`](Self::send_request).
+    fn send_request_sync<T, R>(&self, method: &str, params: T) -> Result<R>
+    where
+        T: Serialize,
+        R: for<'de> Deserialize<'de>,
+    {
+        let mut failures = 0;
+        loop {
+            let failure = match self.send_request_sync_once(method, &params) {
+                Ok(result) => return Ok(result),
+                Err(failure) => failure,
+            };
+            
+            failures += 1;
+            if !failure.is_retryable(method) || !self.retry_policy.should_retry(failures) {
+                return Err(failure.into_error());
+            }
+            
+            let delay = self.retry_policy.backoff_with_jitter(failures);
+            self.log_retry(method, failure.error(), failures, delay);
+            std::thread::sleep(delay);
+        }
+    }
+    
+    /// Send a batch of JSON-RPC requests for the same method, retrying transient failures
+    ///
+    /// The requests are sent as JSON-RPC batches of up to [`MAX_BATCH_SIZE`]
+    /// requests. A batch is retried as a whole when it fails the same way a
+    /// single request would; errors returned for individual requests are not
+    /// retried and are returned in their results.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name
+    /// * `params` - The parameters of each request
+    ///
+    /// # Returns
+    ///
+    /// The result of each request, in the order of the parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a batch fails permanently or runs out of retries
+    async fn send_batch<T, R>(&self, method: &str, params: &[T]) -> Result<Vec<Result<R>>>
+    where
+        T: Serialize,
+        R: for<'de> Deserialize<'de>,
+    {
+        let mut results = Vec::with_capacity(params.len());
+        
+        for chunk in params.chunks(MAX_BATCH_SIZE) {
+            let mut failures = 0;
+            loop {
+                let failure = match self.send_batch_once(method, chunk).await {
+                    Ok(chunk_results) => {
+                        results.extend(chunk_results);
+                        break;
+                    }
+                    Err(failure) => failure,
+                };
+                
+                failures += 1;
+                if !matches!(failure, RequestFailure::Transient(_)) || !self.retry_policy.should_retry(failures) {
+                    return Err(failure.into_error());
+                }
+                
+                let delay = self.retry_policy.backoff_with_jitter(failures);
+                self.log_retry(method, failure.error(), failures, delay);
+                tokio::time::sleep(delay).await;
+            }
+        }
+        
+        Ok(results)
+    }
+    
+    /// Send a single attempt of a JSON-RPC batch
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name
+    /// * `params` - The parameters of each request
+    ///
+    /// # Returns
+    ///
+    /// The result of each request, in the order of the parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns the failure of the batch as a whole, classified by whether it is
+    /// worth retrying
+    async fn send_batch_once<T, R>(&self, method: &str, params: &[T]) -> std::result::Result<Vec<Result<R>>, RequestFailure>
+    where
+        T: Serialize,
+        R: for<'de> Deserialize<'de>,
+    {
+        let requests: Vec<JsonRpcRequest<&T>> = params
+            .iter()
+            .map(|params| JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params,
+                id: self.next_request_id(),
+            })
+            .collect();
+        
+        log::debug!("Sending JSONRPC batch of {} {} requests to {}", requests.len(), method, self.url);
+        
+        let request_json = serde_json::to_string(&requests)
+            .map_err(|e| RequestFailure::Permanent(Error::MetashrewClient(format!("Failed to serialize batch: {}", e))))?;
+        
+        let permit = self.acquire_request_permit().await;
+        let started = Instant::now();
+        
+        let response = self.post(request_json)
+            .send()
+            .await
+            .map_err(RequestFailure::from_send_error)?;
+        
+        self.check_status(response.status())?;
+        
+        let response_text = response.text().await
+            .map_err(|e| RequestFailure::Transient(Error::MetashrewClient(format!("Failed to get response text: {}", e))))?;
+        drop(permit);
+        
+        if let (Some(first), Some(last)) = (requests.first(), requests.last()) {
+            log::debug!("JSONRPC batch requests {}-{} ({}) answered in {:?}", first.id, last.id, method, started.elapsed());
+        }
+        log::debug!("Received raw batch response: \n{}", truncate_response_for_logging(&response_text));
+        
+        let responses: Vec<JsonRpcResponse<R>> = serde_json::from_str(&response_text)
+            .map_err(|e| RequestFailure::Permanent(Error::MetashrewClient(format!(
+                "Failed to parse batch response as JSON: {}\nRaw response: {}", e, truncate_response_for_logging(&response_text)
+            ))))?;
+        
+        // Responses may come back in any order, so match them up by ID
+        let mut responses: HashMap<u32, JsonRpcResponse<R>> = responses
+            .into_iter()
+            .map(|response| (response.id, response))
+            .collect();
+        
+        Ok(requests
+            .iter()
+            .map(|request| {
+                let response = responses.remove(&request.id).ok_or_else(|| {
+                    Error::MetashrewClient(format!("No response for request {} in batch", request.id))
+                })?;
+                if let Some(error) = response.error {
+                    return Err(RequestFailure::from_rpc_error(&error).into_error());
+                }
+                response.result
+                    .ok_or_else(|| Error::MetashrewClient("No result in response".to_string()))
+            })
+            .collect())
+    }
+    
+    /// Build a POST request to metashrew with the configured headers and credentials
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The JSON request body
+    ///
+    /// # Returns
+    ///
+    /// The request builder
+    fn post(&self, body: String) -> reqwest::RequestBuilder {
+        let request_builder = self.client.post(self.url.clone())
+            .headers(self.headers.clone())
+            .header("Content-Type", "application/json")
+            .body(body);
+        
+        match &self.credentials {
+            Some(Credentials::Basic { username, password }) => request_builder.basic_auth(username, password.as_ref()),
+            Some(Credentials::Bearer(token)) => request_builder.bearer_auth(token),
+            None => request_builder,
+        }
+    }
+    
+    /// Log a retry
+    fn log_retry(&self, method: &str, error: &Error, failures: u32, delay: Duration) {
+        log::warn!(
+            "Retrying {} request to {} in {:?} (retry {} of {}): {}",
+            method,
+            self.url,
+            delay,
+            failures,
+            self.retry_policy.max_retries.unwrap_or(u32::MAX),
+            error
+        );
+    }
+    
+    /// Send a single attempt of a JSON-RPC request
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name
+    /// * `params` - The parameters
+    ///
+    /// # Returns
+    ///
+    /// The JSON-RPC response
+    ///
+    /// # Errors
+    ///
+    /// Returns the failure, classified by whether it is worth retrying
+    async fn send_request_once<T, R>(&self, method: &str, params: &T) -> std::result::Result<R, RequestFailure>
     where
         T: Serialize,
         R: for<'de> Deserialize<'de>,
@@ -287,41 +829,40 @@ impl JsonRpcClient {
         
         // Manually serialize the request to JSON
         let request_json = serde_json::to_string(&request)
-            .map_err(|e| Error::MetashrewClient(format!("Failed to serialize request: {}", e)))?;
+            .map_err(|e| RequestFailure::Permanent(Error::MetashrewClient(format!("Failed to serialize request: {}", e))))?;
         
-        // Send the request with explicit Content-Type header
-        let response = self.client.post(self.url.clone())
-            .header("Content-Type", "application/json")
-            .body(request_json)
+        let permit = self.acquire_request_permit().await;
+        let started = Instant::now();
+        
+        let response = self.post(request_json)
             .send()
             .await
-            .map_err(|e| Error::MetashrewClient(format!("Failed to send request: {}", e)))?;
+            .map_err(RequestFailure::from_send_error)?;
         
-        let status = response.status();
-        if !status.is_success() {
-            return Err(Error::MetashrewClient(format!("HTTP error: {}", status)));
-        }
+        self.check_status(response.status())?;
         
         // Get the raw response text for debugging
         let response_text = response.text().await
-            .map_err(|e| Error::MetashrewClient(format!("Failed to get response text: {}", e)))?;
+            .map_err(|e| RequestFailure::Transient(Error::MetashrewClient(format!("Failed to get response text: {}", e))))?;
+        drop(permit);
         
+        log::debug!("JSONRPC request {} ({}) answered in {:?}", request.id, method, started.elapsed());
         log::debug!("Received raw response: \n{}", truncate_response_for_logging(&response_text));
         
         // Parse the response as JSON
         let json_response: JsonRpcResponse<R> = serde_json::from_str(&response_text)
-            .map_err(|e| Error::MetashrewClient(format!("Failed to parse response as JSON: {}\nRaw response: {}", e, response_text)))?;
+            .map_err(|e| RequestFailure::Permanent(Error::MetashrewClient(format!("Failed to parse response as JSON: {}\nRaw response: {}", e, response_text))))?;
         
         if let Some(error) = json_response.error {
-            return Err(Error::MetashrewClient(format!("JSON-RPC error: {} (code: {})", error.message, error.code.unwrap_or_else(|| -1))));
+            return Err(RequestFailure::from_rpc_error(&error));
         }
         
         json_response.result
-            .ok_or_else(|| Error::MetashrewClient("No result in response".to_string()))
+            .ok_or_else(|| RequestFailure::Permanent(Error::MetashrewClient("No result in response".to_string())))
     }
     
-    /// Send a JSON-RPC request synchronously
-    fn send_request_sync<T, R>(&mut self, method: &str, params: T) -> Result<R>
+    /// Send a single attempt of a JSON-RPC request synchronously
+    fn send_request_sync_once<T, R>(&self, method: &str, params: &T) -> std::result::Result<R, RequestFailure>
     where
         T: Serialize,
         R: for<'de> Deserialize<'de>,
@@ -338,47 +879,66 @@ impl JsonRpcClient {
         
         // Manually serialize the request to JSON
         let request_json = serde_json::to_string(&request)
-            .map_err(|e| Error::MetashrewClient(format!("Failed to serialize request: {}", e)))?;
+            .map_err(|e| RequestFailure::Permanent(Error::MetashrewClient(format!("Failed to serialize request: {}", e))))?;
+        
+        // Build a blocking client with the same TLS settings
+        let mut client_builder = reqwest::blocking::Client::builder();
+        for certificate in &self.tls.root_certificates {
+            client_builder = client_builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(identity) = &self.tls.identity {
+            client_builder = client_builder.identity(identity.clone());
+        }
+        let client = client_builder.build()
+            .map_err(|e| RequestFailure::Permanent(Error::MetashrewClient(format!("Failed to build HTTP client: {}", e))))?;
         
         // Send the request with explicit Content-Type header using blocking client
-        let client = reqwest::blocking::Client::new();
-        let response = client.post(self.url.clone())
+        let mut request_builder = client.post(self.url.clone())
+            .headers(self.headers.clone())
             .header("Content-Type", "application/json")
-            .body(request_json)
+            .body(request_json);
+        
+        request_builder = match &self.credentials {
+            Some(Credentials::Basic { username, password }) => request_builder.basic_auth(username, password.as_ref()),
+            Some(Credentials::Bearer(token)) => request_builder.bearer_auth(token),
+            None => request_builder,
+        };
+        
+        let permit = futures::executor::block_on(self.acquire_request_permit());
+        let started = Instant::now();
+        
+        let response = request_builder
             .send()
-            .map_err(|e| Error::MetashrewClient(format!("Failed to send request: {}", e)))?;
+            .map_err(RequestFailure::from_send_error)?;
         
-        let status = response.status();
-        if !status.is_success() {
-            return Err(Error::MetashrewClient(format!("HTTP error: {}", status)));
-        }
+        self.check_status(response.status())?;
         
         // Get the raw response text for debugging
         let response_text = response.text()
-            .map_err(|e| Error::MetashrewClient(format!("Failed to get response text: {}", e)))?;
+            .map_err(|e| RequestFailure::Transient(Error::MetashrewClient(format!("Failed to get response text: {}", e))))?;
+        drop(permit);
         
+        log::debug!("JSONRPC request {} ({}) answered in {:?}", request.id, method, started.elapsed());
         log::debug!("Received raw response: \n{}", truncate_response_for_logging(&response_text));
         
         // Parse the response as JSON
         let json_response: JsonRpcResponse<R> = serde_json::from_str(&response_text)
-            .map_err(|e| Error::MetashrewClient(format!("Failed to parse response as JSON: {}\nRaw response: {}", e, response_text)))?;
+            .map_err(|e| RequestFailure::Permanent(Error::MetashrewClient(format!("Failed to parse response as JSON: {}\nRaw response: {}", e, response_text))))?;
         
         if let Some(error) = json_response.error {
-            return Err(Error::MetashrewClient(format!("JSON-RPC error: {} (code: {})", error.message, error.code.unwrap_or_else(|| -1))));
+            return Err(RequestFailure::from_rpc_error(&error));
         }
         
         json_response.result
-            .ok_or_else(|| Error::MetashrewClient("No result in response".to_string()))
+            .ok_or_else(|| RequestFailure::Permanent(Error::MetashrewClient("No result in response".to_string())))
     }
 }
 
 /// Implement the synchronous version of MetashrewClient for JsonRpcClient
 impl SyncMetashrewClient for JsonRpcClient {
     fn get_height(&self) -> Result<u32> {
-        let mut client = self.clone();
-        
         // For get_height, we're sending an empty array as params
-        let height_str: String = client.send_request_sync("metashrew_height", Vec::<String>::new())?;
+        let height_str: String = self.send_request_sync("metashrew_height", Vec::<String>::new())?;
         
         // Parse the string as a u32
         let height = height_str.parse::<u32>()
@@ -389,13 +949,11 @@ impl SyncMetashrewClient for JsonRpcClient {
     }
     
     fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
-        let mut client = self.clone();
-        
         // For get_block_hash, we're sending a vec with the height
         let params = serde_json::json!([height]);
         log::debug!("Sending get_block_hash with params: {}", serde_json::to_string(&params).unwrap_or_default());
         
-        let hash: String = client.send_request_sync("metashrew_getblockhash", params)?;
+        let hash: String = self.send_request_sync("metashrew_getblockhash", params)?;
         
         log::debug!("Got block hash (hex): {}", hash);
         
@@ -416,8 +974,6 @@ impl SyncMetashrewClient for JsonRpcClient {
     }
     
     fn call_view(&self, view_name: &str, params: &[u8]) -> Result<Vec<u8>> {
-        let mut client = self.clone();
-        
         // Convert params to hex string
         let params_hex = hex::encode(params);
         
@@ -432,7 +988,7 @@ impl SyncMetashrewClient for JsonRpcClient {
         log::debug!("View params JSON: {}", serde_json::to_string_pretty(&view_params).unwrap_or_default());
         
         // Call the view function
-        let result: String = client.send_request_sync("metashrew_view", view_params)?;
+        let result: String = self.send_request_sync("metashrew_view", view_params)?;
         
         // Pretty print and log the result (truncated)
         log::info!("JSONRPC result from metashrew_view '{}': ", view_name);
@@ -477,14 +1033,12 @@ impl SyncMetashrewClient for JsonRpcClient {
 }
 
 #[async_trait]
-impl MetashrewClient for JsonRpcClient {
+impl BlockProviderLike for JsonRpcClient {
     async fn get_height(&self) -> Result<u32> {
-        let mut client = self.clone();
-        
         // For get_height, we're sending an empty array as params
         // The Metashrew service expects [] not null
         // The result comes back as a string, so we need to parse it
-        let height_str: String = client.send_request("metashrew_height", Vec::<String>::new()).await?;
+        let height_str: String = self.send_request("metashrew_height", Vec::<String>::new()).await?;
         
         // Parse the string as a u32
         let height = height_str.parse::<u32>()
@@ -495,14 +1049,12 @@ impl MetashrewClient for JsonRpcClient {
     }
     
     async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
-        let mut client = self.clone();
-        
         // For get_block_hash, we're sending a vec with the height
         // Let's use serde_json::Value to ensure proper JSON formatting
         let params = serde_json::json!([height]);
         log::debug!("Sending get_block_hash with params: {}", serde_json::to_string(&params).unwrap_or_default());
         
-        let hash: String = client.send_request("metashrew_getblockhash", params).await?;
+        let hash: String = self.send_request("metashrew_getblockhash", params).await?;
         
         log::debug!("Got block hash (hex): {}", hash);
         
@@ -522,9 +1074,21 @@ impl MetashrewClient for JsonRpcClient {
         Ok(hash_bytes)
     }
     
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        // One batch request for the whole range
+        let params: Vec<serde_json::Value> = heights.map(|height| serde_json::json!([height])).collect();
+        let hashes: Vec<Result<String>> = self.send_batch("metashrew_getblockhash", &params).await?;
+        
+        hashes
+            .into_iter()
+            .map(|hash| decode_hex(&hash?, "block hash"))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ViewProviderLike for JsonRpcClient {
     async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
-        let mut client = self.clone();
-        
         // Convert params to hex string
         let params_hex = hex::encode(params);
         
@@ -543,7 +1107,7 @@ impl MetashrewClient for JsonRpcClient {
         log::debug!("View params JSON: {}", serde_json::to_string_pretty(&view_params).unwrap_or_default());
         
         // Call the view function
-        let result: String = client.send_request("metashrew_view", view_params).await?;
+        let result: String = self.send_request("metashrew_view", view_params).await?;
         
         // Pretty print and log the result (truncated)
         log::info!("JSONRPC result from metashrew_view '{}': ", view_name);
@@ -582,29 +1146,72 @@ impl MetashrewClient for JsonRpcClient {
         Ok(result_bytes)
     }
     
-    fn get_url(&self) -> &Url {
-        &self.url
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        let params: Vec<serde_json::Value> = calls
+            .iter()
+            .map(|call| {
+                let params_hex = hex::encode(&call.params);
+                match call.height {
+                    Some(h) => serde_json::json!([call.view_name, params_hex, h]),
+                    None => serde_json::json!([call.view_name, params_hex, "latest"]),
+                }
+            })
+            .collect();
+        let results: Vec<Result<String>> = self.send_batch("metashrew_view", &params).await?;
+        
+        Ok(results
+            .into_iter()
+            .map(|result| decode_hex(&result?, "view result"))
+            .collect())
     }
 }
 
 #[async_trait]
-impl BlockProviderLike for JsonRpcClient {
+impl MetashrewClient for JsonRpcClient {
     async fn get_height(&self) -> Result<u32> {
-        <Self as MetashrewClient>::get_height(self).await
+        <Self as BlockProviderLike>::get_height(self).await
     }
     
     async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
-        <Self as MetashrewClient>::get_block_hash(self, height).await
+        <Self as BlockProviderLike>::get_block_hash(self, height).await
     }
-}
-
-#[async_trait]
-impl ViewProviderLike for JsonRpcClient {
+    
     async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
-        <Self as MetashrewClient>::call_view(self, view_name, params, height).await
+        <Self as ViewProviderLike>::call_view(self, view_name, params, height).await
+    }
+    
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        <Self as BlockProviderLike>::get_block_hashes(self, heights).await
+    }
+    
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        <Self as ViewProviderLike>::call_views(self, calls).await
+    }
+    
+    fn get_url(&self) -> &Url {
+        &self.url
     }
 }
 
+/// Decode a hex string returned by metashrew
+///
+/// # Arguments
+///
+/// * `value` - The hex string, with or without a `0x` prefix
+/// * `what` - What the value is, for the error message
+///
+/// # Returns
+///
+/// The decoded bytes
+///
+/// # Errors
+///
+/// Returns an error if the value is not valid hex
+fn decode_hex(value: &str, what: &str) -> Result<Vec<u8>> {
+    hex::decode(value.strip_prefix("0x").unwrap_or(value))
+        .map_err(|e| Error::MetashrewClient(format!("Failed to decode {}: {}", what, e)))
+}
+
 #[async_trait]
 impl MetashrewClientLike for JsonRpcClient {
     fn get_identifier(&self) -> String {
@@ -613,7 +1220,7 @@ impl MetashrewClientLike for JsonRpcClient {
     
     async fn is_healthy(&self) -> bool {
         // Try to get the current height as a health check
-        <Self as MetashrewClient>::get_height(self).await.is_ok()
+        <Self as BlockProviderLike>::get_height(self).await.is_ok()
     }
 }
 
@@ -722,7 +1329,7 @@ impl MockMetashrewClient {
 }
 
 #[async_trait]
-impl MetashrewClient for MockMetashrewClient {
+impl BlockProviderLike for MockMetashrewClient {
     async fn get_height(&self) -> Result<u32> {
         Ok(self.height)
     }
@@ -739,7 +1346,10 @@ impl MetashrewClient for MockMetashrewClient {
         
         Ok(hash.clone())
     }
-    
+}
+
+#[async_trait]
+impl ViewProviderLike for MockMetashrewClient {
     async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
         for (name, p, h, result) in &self.view_results {
             if name == view_name && p == params && h == &height {
@@ -772,44 +1382,131 @@ impl MetashrewClient for MockMetashrewClient {
         
         Err(Error::MetashrewClient(format!("View result not found for {}", view_name)))
     }
+}
+
+#[async_trait]
+impl MetashrewClientLike for MockMetashrewClient {
+    fn get_identifier(&self) -> String {
+        format!("mock-{}", self.url)
+    }
     
-    fn get_url(&self) -> &Url {
-        &self.url
+    async fn is_healthy(&self) -> bool {
+        true // Mock client is always healthy
     }
 }
 
 #[async_trait]
-impl BlockProviderLike for MockMetashrewClient {
+impl MetashrewClient for MockMetashrewClient {
     async fn get_height(&self) -> Result<u32> {
-        <Self as MetashrewClient>::get_height(self).await
+        <Self as BlockProviderLike>::get_height(self).await
     }
     
     async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
-        <Self as MetashrewClient>::get_block_hash(self, height).await
+        <Self as BlockProviderLike>::get_block_hash(self, height).await
+    }
+    
+    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+        <Self as ViewProviderLike>::call_view(self, view_name, params, height).await
+    }
+    
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        <Self as BlockProviderLike>::get_block_hashes(self, heights).await
+    }
+    
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        <Self as ViewProviderLike>::call_views(self, calls).await
+    }
+    
+    fn get_url(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl Default for MockMetashrewClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adapter exposing a [`MetashrewClient`] through [`MetashrewClientLike`]
+///
+/// The client's URL is used as its identifier.
+pub struct MetashrewClientAdapter<C: MetashrewClient> {
+    /// The wrapped client
+    client: C,
+}
+
+impl<C: MetashrewClient> MetashrewClientAdapter<C> {
+    /// Create a new adapter
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client to wrap
+    ///
+    /// # Returns
+    ///
+    /// A new adapter
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+
+    /// Get the wrapped client
+    ///
+    /// # Returns
+    ///
+    /// A reference to the wrapped client
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Unwrap the adapter
+    ///
+    /// # Returns
+    ///
+    /// The wrapped client
+    pub fn into_inner(self) -> C {
+        self.client
+    }
+}
+
+impl<C: MetashrewClient> std::fmt::Debug for MetashrewClientAdapter<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetashrewClientAdapter")
+            .field("url", &self.client.get_url().as_str())
+            .finish()
     }
 }
 
 #[async_trait]
-impl ViewProviderLike for MockMetashrewClient {
-    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
-        <Self as MetashrewClient>::call_view(self, view_name, params, height).await
+impl<C: MetashrewClient> BlockProviderLike for MetashrewClientAdapter<C> {
+    async fn get_height(&self) -> Result<u32> {
+        self.client.get_height().await
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+        self.client.get_block_hash(height).await
+    }
+
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        self.client.get_block_hashes(heights).await
     }
 }
 
 #[async_trait]
-impl MetashrewClientLike for MockMetashrewClient {
-    fn get_identifier(&self) -> String {
-        format!("mock-{}", self.url)
+impl<C: MetashrewClient> ViewProviderLike for MetashrewClientAdapter<C> {
+    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+        self.client.call_view(view_name, params, height).await
     }
-    
-    async fn is_healthy(&self) -> bool {
-        true // Mock client is always healthy
+
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        self.client.call_views(calls).await
     }
 }
 
-impl Default for MockMetashrewClient {
-    fn default() -> Self {
-        Self::new()
+#[async_trait]
+impl<C: MetashrewClient> MetashrewClientLike for MetashrewClientAdapter<C> {
+    fn get_identifier(&self) -> String {
+        self.client.get_url().to_string()
     }
 }
 
@@ -818,7 +1515,7 @@ mod tests {
     use super::*;
     use tokio::runtime::Runtime;
     use wiremock::{Mock, MockServer, ResponseTemplate};
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_partial_json, header, method, path};
     use serde_json::json;
 
     #[test]
@@ -871,7 +1568,329 @@ mod tests {
         let client = JsonRpcClient::new(&mock_server.uri()).unwrap();
         
         // Test get_height
-        let height = <JsonRpcClient as MetashrewClient>::get_height(&client).await.unwrap();
+        let height = BlockProviderLike::get_height(&client).await.unwrap();
         assert_eq!(height, 123);
     }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_authentication() {
+        let mock_server = MockServer::start().await;
+        
+        // Only requests carrying the expected credentials and headers succeed
+        Mock::given(method("POST"))
+            .and(header("Authorization", "Basic dXNlcjpwYXNz"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(json!({"jsonrpc": "2.0", "result": "7", "id": 0})))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header("Authorization", "Bearer secret-token"))
+            .and(header("X-Api-Key", "key"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(json!({"jsonrpc": "2.0", "result": "8", "id": 0})))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+        
+        let mut config = MetashrewConfig::new(&mock_server.uri());
+        config.username = Some("user".to_string());
+        config.password = Some("pass".to_string());
+        let client = JsonRpcClient::from_config(&config).unwrap();
+        assert_eq!(BlockProviderLike::get_height(&client).await.unwrap(), 7);
+        
+        let mut config = MetashrewConfig::new(&mock_server.uri());
+        config.bearer_token = Some("secret-token".to_string());
+        config.headers.insert("X-Api-Key".to_string(), "key".to_string());
+        let client = JsonRpcClient::from_config(&config).unwrap();
+        assert_eq!(BlockProviderLike::get_height(&client).await.unwrap(), 8);
+        
+        // Rejected credentials surface as an authentication error
+        config.bearer_token = Some("wrong-token".to_string());
+        let client = JsonRpcClient::from_config(&config).unwrap();
+        let error = BlockProviderLike::get_height(&client).await.unwrap_err();
+        assert!(matches!(error, Error::Authentication(_)));
+        assert!(!error.is_transient());
+        
+        // Secrets stay out of debug output
+        assert!(!format!("{:?}", client).contains("wrong-token"));
+        
+        config.ca_cert_path = Some("/nonexistent/ca.pem".to_string());
+        assert!(matches!(JsonRpcClient::from_config(&config), Err(Error::Configuration(_))));
+    }
+
+    fn fast_retry_config(url: &str, max_retries: u32) -> MetashrewConfig {
+        MetashrewConfig {
+            max_retries,
+            retry_delay: 1,
+            max_retry_delay: 10,
+            ..MetashrewConfig::new(url)
+        }
+    }
+
+    async fn received(mock_server: &MockServer) -> usize {
+        mock_server.received_requests().await.unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_retries_transient_failures() {
+        let mock_server = MockServer::start().await;
+        
+        // Two server errors, then success
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(json!({"jsonrpc": "2.0", "result": "123", "id": 0})))
+            .mount(&mock_server)
+            .await;
+        
+        let client = JsonRpcClient::from_config(&fast_retry_config(&mock_server.uri(), 3)).unwrap();
+        assert_eq!(BlockProviderLike::get_height(&client).await.unwrap(), 123);
+        assert_eq!(received(&mock_server).await, 3);
+        
+        // Retries run out after max_retries
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let client = JsonRpcClient::from_config(&fast_retry_config(&mock_server.uri(), 2)).unwrap();
+        assert!(BlockProviderLike::get_height(&client).await.is_err());
+        assert_eq!(received(&mock_server).await, 3);
+        
+        // Connection errors are retried as well
+        let client = JsonRpcClient::from_config(&fast_retry_config("http://127.0.0.1:1", 2)).unwrap();
+        let error = BlockProviderLike::get_height(&client).await.unwrap_err();
+        assert!(error.to_string().contains("Failed to send request"));
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_does_not_retry_permanent_failures() {
+        let mock_server = MockServer::start().await;
+        
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&mock_server)
+            .await;
+        let client = JsonRpcClient::from_config(&fast_retry_config(&mock_server.uri(), 3)).unwrap();
+        assert!(BlockProviderLike::get_height(&client).await.is_err());
+        assert_eq!(received(&mock_server).await, 1);
+        
+        // An unindexed block is an answer for block hash requests, but worth
+        // retrying for view calls
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32000, "message": "block not yet indexed"},
+                "id": 0
+            })))
+            .mount(&mock_server)
+            .await;
+        
+        let error = BlockProviderLike::get_block_hash(&client, 10).await.unwrap_err();
+        assert!(crate::tip::is_block_not_available(&error));
+        assert_eq!(received(&mock_server).await, 1);
+        
+        assert!(ViewProviderLike::call_view(&client, "test_view", &[], Some(10)).await.is_err());
+        assert_eq!(received(&mock_server).await, 5);
+        
+        // Other JSON-RPC errors are permanent
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({"method": "metashrew_view"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32601, "message": "unknown view"},
+                "id": 0
+            })))
+            .mount(&mock_server)
+            .await;
+        assert!(ViewProviderLike::call_view(&client, "test_view", &[], None).await.is_err());
+        assert_eq!(received(&mock_server).await, 1);
+        
+        // So is a server error that does not say the block is missing
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32000, "message": "database is corrupt"},
+                "id": 0
+            })))
+            .mount(&mock_server)
+            .await;
+        let error = BlockProviderLike::get_block_hash(&client, 10).await.unwrap_err();
+        assert!(!crate::tip::is_block_not_available(&error));
+        assert!(ViewProviderLike::call_view(&client, "test_view", &[], Some(10)).await.is_err());
+        assert_eq!(received(&mock_server).await, 2);
+    }
+
+    #[test]
+    fn test_sync_json_rpc_client_retries_transient_failures() {
+        let rt = Runtime::new().unwrap();
+        let mock_server = rt.block_on(async {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .respond_with(ResponseTemplate::new(502))
+                .up_to_n_times(1)
+                .mount(&mock_server)
+                .await;
+            Mock::given(method("POST"))
+                .respond_with(ResponseTemplate::new(200)
+                    .set_body_json(json!({"jsonrpc": "2.0", "result": "42", "id": 0})))
+                .mount(&mock_server)
+                .await;
+            mock_server
+        });
+        
+        let client = JsonRpcClient::from_config(&fast_retry_config(&mock_server.uri(), 3)).unwrap();
+        assert_eq!(<JsonRpcClient as SyncMetashrewClient>::get_height(&client).unwrap(), 42);
+        assert_eq!(rt.block_on(received(&mock_server)), 2);
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_batches() {
+        let mock_server = MockServer::start().await;
+        
+        // Responses to a batch may come back in any order
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!([{"method": "metashrew_getblockhash", "params": [5]}])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"jsonrpc": "2.0", "result": "0x07", "id": 2},
+                {"jsonrpc": "2.0", "result": "05", "id": 0},
+                {"jsonrpc": "2.0", "result": "06", "id": 1}
+            ])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!([{"method": "metashrew_view"}])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"jsonrpc": "2.0", "result": "0x0102", "id": 3},
+                {"jsonrpc": "2.0", "error": {"code": -32601, "message": "unknown view"}, "id": 4}
+            ])))
+            .mount(&mock_server)
+            .await;
+        
+        let client = JsonRpcClient::new(&mock_server.uri()).unwrap();
+        let hashes = <JsonRpcClient as MetashrewClient>::get_block_hashes(&client, 5..=7).await.unwrap();
+        assert_eq!(hashes, vec![vec![5], vec![6], vec![7]]);
+        assert_eq!(received(&mock_server).await, 1);
+        
+        let calls = vec![
+            ViewCall::new("balance", &[1], Some(7)),
+            ViewCall::new("missing", &[], None),
+        ];
+        let results = <JsonRpcClient as MetashrewClient>::call_views(&client, &calls).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &vec![1, 2]);
+        assert!(results[1].as_ref().unwrap_err().to_string().contains("unknown view"));
+        assert_eq!(received(&mock_server).await, 2);
+        
+        // An empty batch is not sent at all
+        assert!(<JsonRpcClient as MetashrewClient>::call_views(&client, &[]).await.unwrap().is_empty());
+        assert_eq!(received(&mock_server).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_shares_request_ids() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(json!({"jsonrpc": "2.0", "result": "1", "id": 0})))
+            .mount(&mock_server)
+            .await;
+        
+        // IDs keep counting across calls and clones
+        let client = JsonRpcClient::new(&mock_server.uri()).unwrap();
+        let clone = client.clone();
+        BlockProviderLike::get_height(&client).await.unwrap();
+        BlockProviderLike::get_height(&clone).await.unwrap();
+        BlockProviderLike::get_height(&client).await.unwrap();
+        
+        let ids: Vec<u64> = mock_server.received_requests().await.unwrap()
+            .iter()
+            .map(|request| request.body_json::<serde_json::Value>().unwrap()["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_limits_concurrent_requests() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(json!({"jsonrpc": "2.0", "result": "1", "id": 0}))
+                .set_delay(Duration::from_millis(100)))
+            .mount(&mock_server)
+            .await;
+        
+        let config = MetashrewConfig {
+            max_concurrent_requests: Some(1),
+            ..MetashrewConfig::new(&mock_server.uri())
+        };
+        let client = JsonRpcClient::from_config(&config).unwrap();
+        
+        // With one request at a time, three requests take at least three round trips
+        let started = Instant::now();
+        let heights = futures::future::join_all((0..3).map(|_| BlockProviderLike::get_height(&client))).await;
+        assert!(heights.iter().all(|height| height.is_ok()));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    /// A client implementing only the required methods of [`MetashrewClient`]
+    struct MinimalClient {
+        url: Url,
+    }
+
+    #[async_trait]
+    impl MetashrewClient for MinimalClient {
+        async fn get_height(&self) -> Result<u32> {
+            Ok(3)
+        }
+
+        async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+            Ok(vec![height as u8])
+        }
+
+        async fn call_view(&self, view_name: &str, params: &[u8], _height: Option<u32>) -> Result<Vec<u8>> {
+            match view_name {
+                "echo" => Ok(params.to_vec()),
+                _ => Err(Error::MetashrewClient(format!("unknown view {}", view_name))),
+            }
+        }
+
+        fn get_url(&self) -> &Url {
+            &self.url
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metashrew_client_adapter() {
+        let mut client = MockMetashrewClient::new();
+        client.set_height(10);
+        client.set_block_hash(10, vec![0xaa]);
+        client.set_view_result("test_view", &[1], Some(10), vec![2]);
+
+        let adapter = MetashrewClientAdapter::new(client);
+        assert_eq!(adapter.get_identifier(), "http://localhost:18888/");
+        assert_eq!(BlockProviderLike::get_height(&adapter).await.unwrap(), 10);
+        assert_eq!(BlockProviderLike::get_block_hash(&adapter, 10).await.unwrap(), vec![0xaa]);
+        assert_eq!(ViewProviderLike::call_view(&adapter, "test_view", &[1], Some(10)).await.unwrap(), vec![2]);
+        assert!(adapter.is_healthy().await);
+        assert_eq!(adapter.into_inner().height, 10);
+
+        // The batch methods fall back to one request at a time
+        let adapter = MetashrewClientAdapter::new(MinimalClient { url: Url::parse("http://metashrew:8080").unwrap() });
+        assert_eq!(adapter.get_identifier(), "http://metashrew:8080/");
+        assert_eq!(BlockProviderLike::get_block_hashes(&adapter, 1..=3).await.unwrap(), vec![vec![1], vec![2], vec![3]]);
+        let calls = vec![ViewCall::new("echo", &[7], None), ViewCall::new("missing", &[], None)];
+        let results = ViewProviderLike::call_views(&adapter, &calls).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &vec![7]);
+        assert!(results[1].is_err());
+    }
 }
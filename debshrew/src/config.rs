@@ -3,10 +3,13 @@
 //! This module defines the configuration types used throughout the debshrew project.
 
 use crate::error::{Error, Result};
+use crate::error_policy::{DeadLetterQueue, RetryPolicy, DEFAULT_MAX_RETRIES};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::time::Duration;
 
 /// Configuration for the debshrew service
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +31,77 @@ pub struct Config {
     #[serde(default)]
     pub start_height: Option<u32>,
     
+    /// Last block height to emit before exiting (optional)
+    #[serde(default)]
+    pub end_height: Option<u32>,
+    
+    /// Path of the checkpoint file used to resume after a restart (optional)
+    #[serde(default)]
+    pub checkpoint_path: Option<String>,
+    
+    /// Interval between tip checks in milliseconds
+    #[serde(default = "default_polling_interval")]
+    pub polling_interval: u64,
+    
+    /// URL of a newline-delimited JSON stream of tip events (optional)
+    ///
+    /// When set, the synchronizer wakes as soon as metashrew announces a new
+    /// tip, and falls back to polling while the stream is unavailable.
+    #[serde(default)]
+    pub tip_stream_url: Option<String>,
+    
+    /// Number of upcoming blocks to prefetch during catch-up
+    #[serde(default = "default_prefetch_depth")]
+    pub prefetch_depth: usize,
+    
+    /// Whether to prefetch the view calls recorded for the previous block
+    #[serde(default)]
+    pub prefetch_views: bool,
+    
+    /// Number of confirmations a block needs before its CDC messages are emitted
+    /// (0 emits every block immediately)
+    #[serde(default)]
+    pub finality_depth: u32,
+    
+    /// Separate sink for the finalized stream (optional)
+    ///
+    /// When set, the main sink receives a tentative stream with inverse messages
+    /// on reorg, and this sink receives blocks once they pass the finality depth.
+    #[serde(default)]
+    pub finalized_sink: Option<SinkConfig>,
+    
+    /// Whether to mark block and reorg boundaries with control messages
+    #[serde(default)]
+    pub control_messages: bool,
+    
+    /// Address of the embedded HTTP server serving health, readiness and status (optional)
+    #[serde(default)]
+    pub http_listen_address: Option<String>,
+    
+    /// Whether the HTTP server exposes the admin endpoints for pausing, resuming and rewinding
+    #[serde(default)]
+    pub admin_api: bool,
+    
+    /// Token admin requests must send as `Authorization: Bearer <token>` (optional)
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    
+    /// Environment variable to read the admin token from (optional)
+    #[serde(default)]
+    pub admin_token_env: Option<String>,
+    
+    /// Name of the pipeline, added as the `pipeline` label to every metric
+    #[serde(default = "default_pipeline_name")]
+    pub pipeline_name: String,
+    
+    /// Path the HTTP server serves Prometheus metrics on
+    #[serde(default = "default_metrics_path")]
+    pub metrics_path: String,
+    
+    /// How failures are handled
+    #[serde(default)]
+    pub error_policy: ErrorPolicyConfig,
+    
     /// Log level
     #[serde(default = "default_log_level")]
     pub log_level: String,
@@ -38,12 +112,59 @@ fn default_cache_size() -> u32 {
     6
 }
 
+/// Default polling interval
+fn default_polling_interval() -> u64 {
+    1000
+}
+
+/// Default prefetch depth
+fn default_prefetch_depth() -> usize {
+    8
+}
+
+/// Default pipeline name
+fn default_pipeline_name() -> String {
+    "debshrew".to_string()
+}
+
+/// Default metrics path
+fn default_metrics_path() -> String {
+    "/metrics".to_string()
+}
+
 /// Default log level
 fn default_log_level() -> String {
     "info".to_string()
 }
 
 impl Config {
+    /// Resolve the admin token from whichever source is configured
+    ///
+    /// # Returns
+    ///
+    /// The admin token, or None if no token is configured
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable is not set or is empty
+    pub fn resolve_admin_token(&self) -> Result<Option<String>> {
+        if let Some(token) = &self.admin_token {
+            return Ok(Some(token.clone()));
+        }
+        
+        if let Some(var) = &self.admin_token_env {
+            let token = std::env::var(var)
+                .map_err(|e| Error::Configuration(format!("Failed to read admin token from ${}: {}", var, e)))?;
+            let token = token.trim();
+            if token.is_empty() {
+                return Err(Error::Configuration(format!("Admin token in ${} is empty", var)));
+            }
+            return Ok(Some(token.to_string()));
+        }
+        
+        Ok(None)
+    }
+    
     /// Load configuration from a file
     ///
     /// # Arguments
@@ -106,6 +227,84 @@ impl Config {
             return Err(Error::Configuration("Cache size must be greater than 0".to_string()));
         }
         
+        // Validate the block range
+        if let (Some(start_height), Some(end_height)) = (self.start_height, self.end_height) {
+            if end_height < start_height {
+                return Err(Error::Configuration("End height must not be lower than the start height".to_string()));
+            }
+        }
+        
+        // Validate polling interval
+        if self.polling_interval == 0 {
+            return Err(Error::Configuration("Polling interval must be greater than 0".to_string()));
+        }
+        
+        // Validate the tip stream URL
+        if let Some(tip_stream_url) = &self.tip_stream_url {
+            url::Url::parse(tip_stream_url)
+                .map_err(|e| Error::Configuration(format!("Invalid tip stream URL: {}", e)))?;
+        }
+        
+        // Validate prefetch depth
+        if self.prefetch_depth == 0 {
+            return Err(Error::Configuration("Prefetch depth must be greater than 0".to_string()));
+        }
+        
+        // Blocks waiting for finality are held in the block cache
+        if self.finality_depth > 0 && self.cache_size <= self.finality_depth {
+            return Err(Error::Configuration("Cache size must be greater than the finality depth".to_string()));
+        }
+        
+        // Validate the finalized sink configuration
+        if let Some(finalized_sink) = &self.finalized_sink {
+            finalized_sink.validate()?;
+            
+            if self.finality_depth == 0 {
+                return Err(Error::Configuration("A finalized sink requires a finality depth greater than 0".to_string()));
+            }
+        }
+        
+        // Validate the HTTP listen address
+        if let Some(http_listen_address) = &self.http_listen_address {
+            http_listen_address.parse::<std::net::SocketAddr>()
+                .map_err(|e| Error::Configuration(format!("Invalid HTTP listen address: {}", e)))?;
+        }
+        
+        // The admin endpoints are served by the HTTP server
+        if self.admin_api && self.http_listen_address.is_none() {
+            return Err(Error::Configuration("The admin API requires an HTTP listen address".to_string()));
+        }
+        
+        // The admin endpoints are only served to callers presenting the admin token
+        match (&self.admin_token, &self.admin_token_env) {
+            (Some(_), Some(_)) => {
+                return Err(Error::Configuration("Only one of admin_token and admin_token_env can be set".to_string()));
+            }
+            (None, None) if self.admin_api => {
+                return Err(Error::Configuration("The admin API requires admin_token or admin_token_env".to_string()));
+            }
+            (Some(token), None) if token.is_empty() => {
+                return Err(Error::Configuration("Admin token cannot be empty".to_string()));
+            }
+            _ => {}
+        }
+        
+        // Validate the error policy
+        self.error_policy.validate()?;
+        
+        // Validate the pipeline name
+        if self.pipeline_name.is_empty() {
+            return Err(Error::Configuration("Pipeline name cannot be empty".to_string()));
+        }
+        
+        // Validate the metrics path, which must not shadow another endpoint
+        if !self.metrics_path.starts_with('/') {
+            return Err(Error::Configuration("Metrics path must start with '/'".to_string()));
+        }
+        if ["/healthz", "/readyz", "/status"].contains(&self.metrics_path.as_str()) || self.metrics_path.starts_with("/admin/") {
+            return Err(Error::Configuration(format!("Metrics path {} is already used by another endpoint", self.metrics_path)));
+        }
+        
         Ok(())
     }
 }
@@ -124,6 +323,34 @@ pub struct MetashrewConfig {
     #[serde(default)]
     pub password: Option<String>,
     
+    /// Bearer token sent in the Authorization header (optional)
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    
+    /// Environment variable to read the bearer token from (optional)
+    #[serde(default)]
+    pub bearer_token_env: Option<String>,
+    
+    /// File to read the bearer token from (optional)
+    #[serde(default)]
+    pub bearer_token_file: Option<String>,
+    
+    /// Additional HTTP headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    
+    /// PEM bundle of CA certificates to trust in addition to the system roots (optional)
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    
+    /// PEM client certificate for mutual TLS (optional)
+    #[serde(default)]
+    pub client_cert_path: Option<String>,
+    
+    /// PEM PKCS#8 private key of the client certificate (optional)
+    #[serde(default)]
+    pub client_key_path: Option<String>,
+    
     /// Connection timeout in seconds
     #[serde(default = "default_timeout")]
     pub timeout: u64,
@@ -132,9 +359,43 @@ pub struct MetashrewConfig {
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
     
-    /// Retry delay in milliseconds
+    /// Retry delay in milliseconds, doubled after every failed attempt
     #[serde(default = "default_retry_delay")]
     pub retry_delay: u64,
+    
+    /// Maximum retry delay in milliseconds
+    #[serde(default = "default_max_retry_delay")]
+    pub max_retry_delay: u64,
+    
+    /// Maximum number of requests in flight to each metashrew endpoint (optional)
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+    
+    /// Additional metashrew URLs, used according to the endpoint strategy
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    
+    /// How multiple endpoints are used
+    #[serde(default)]
+    pub strategy: EndpointStrategy,
+    
+    /// Number of endpoints that must agree on a block hash with the quorum strategy
+    /// (defaults to a majority of the endpoints, and must be a majority so two
+    /// hashes cannot both reach it)
+    #[serde(default)]
+    pub quorum: Option<usize>,
+}
+
+/// Strategy for using multiple metashrew endpoints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointStrategy {
+    /// Use the first healthy endpoint that has indexed the requested block
+    #[default]
+    Failover,
+    
+    /// Only accept a block hash once a quorum of endpoints agree on it
+    Quorum,
 }
 
 /// Default timeout
@@ -152,22 +413,181 @@ fn default_retry_delay() -> u64 {
     1000
 }
 
+/// Default maximum retry delay
+fn default_max_retry_delay() -> u64 {
+    30_000
+}
+
 impl MetashrewConfig {
+    /// Create a configuration for a single metashrew URL with default settings
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The metashrew URL
+    ///
+    /// # Returns
+    ///
+    /// A new metashrew configuration
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            username: None,
+            password: None,
+            bearer_token: None,
+            bearer_token_env: None,
+            bearer_token_file: None,
+            headers: BTreeMap::new(),
+            ca_cert_path: None,
+            client_cert_path: None,
+            client_key_path: None,
+            timeout: default_timeout(),
+            max_retries: default_max_retries(),
+            retry_delay: default_retry_delay(),
+            max_retry_delay: default_max_retry_delay(),
+            max_concurrent_requests: None,
+            endpoints: Vec::new(),
+            strategy: EndpointStrategy::default(),
+            quorum: None,
+        }
+    }
+    
+    /// Get every configured metashrew URL
+    ///
+    /// # Returns
+    ///
+    /// The primary URL followed by the additional endpoints
+    pub fn urls(&self) -> Vec<&str> {
+        std::iter::once(self.url.as_str())
+            .chain(self.endpoints.iter().map(|url| url.as_str()))
+            .collect()
+    }
+    
+    /// Get the number of endpoints that must agree on a block hash
+    ///
+    /// # Returns
+    ///
+    /// The configured quorum, or a majority of the endpoints
+    pub fn quorum_size(&self) -> usize {
+        self.quorum.unwrap_or(self.urls().len() / 2 + 1)
+    }
+    
+    /// Get the retry policy for failed metashrew requests
+    ///
+    /// # Returns
+    ///
+    /// The retry policy
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(self.retry_delay),
+            max_backoff: Duration::from_millis(self.max_retry_delay),
+            max_retries: Some(self.max_retries),
+        }
+    }
+    
+    /// Resolve the bearer token from whichever source is configured
+    ///
+    /// # Returns
+    ///
+    /// The bearer token, or None if no token is configured
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable is not set or the file cannot be read
+    pub fn resolve_bearer_token(&self) -> Result<Option<String>> {
+        if let Some(token) = &self.bearer_token {
+            return Ok(Some(token.clone()));
+        }
+        
+        if let Some(var) = &self.bearer_token_env {
+            let token = std::env::var(var)
+                .map_err(|e| Error::Configuration(format!("Failed to read bearer token from ${}: {}", var, e)))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+        
+        if let Some(path) = &self.bearer_token_file {
+            let token = std::fs::read_to_string(path)
+                .map_err(|e| Error::Configuration(format!("Failed to read bearer token file {}: {}", path, e)))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+        
+        Ok(None)
+    }
+    
     /// Validate the metashrew configuration
     ///
     /// # Returns
     ///
     /// Ok(()) if the configuration is valid, an error otherwise
     pub fn validate(&self) -> Result<()> {
-        // Validate URL
-        url::Url::parse(&self.url)
-            .map_err(|e| Error::Configuration(format!("Invalid metashrew URL: {}", e)))?;
+        // Validate URLs
+        for url in self.urls() {
+            url::Url::parse(url)
+                .map_err(|e| Error::Configuration(format!("Invalid metashrew URL {}: {}", url, e)))?;
+        }
         
         // Validate timeout
         if self.timeout == 0 {
             return Err(Error::Configuration("Timeout must be greater than 0".to_string()));
         }
         
+        // Validate retries
+        if self.max_retry_delay < self.retry_delay {
+            return Err(Error::Configuration("max_retry_delay must not be less than retry_delay".to_string()));
+        }
+        
+        // Validate the request limit
+        if self.max_concurrent_requests == Some(0) {
+            return Err(Error::Configuration("max_concurrent_requests must be greater than 0".to_string()));
+        }
+        
+        // Validate the quorum
+        if self.strategy == EndpointStrategy::Quorum {
+            let endpoints = self.urls().len();
+            if endpoints < 2 {
+                return Err(Error::Configuration("The quorum strategy requires at least two metashrew endpoints".to_string()));
+            }
+            if self.quorum_size() <= endpoints / 2 || self.quorum_size() > endpoints {
+                return Err(Error::Configuration(format!(
+                    "Quorum must be a majority of the metashrew endpoints, between {} and {}",
+                    endpoints / 2 + 1, endpoints
+                )));
+            }
+        } else if self.quorum.is_some() {
+            return Err(Error::Configuration("A quorum requires the quorum strategy".to_string()));
+        }
+        
+        // Validate authentication
+        let token_sources = [&self.bearer_token, &self.bearer_token_env, &self.bearer_token_file]
+            .iter()
+            .filter(|source| source.is_some())
+            .count();
+        if token_sources > 1 {
+            return Err(Error::Configuration(
+                "Only one of bearer_token, bearer_token_env and bearer_token_file can be set".to_string()
+            ));
+        }
+        if token_sources > 0 && self.username.is_some() {
+            return Err(Error::Configuration("A bearer token cannot be combined with a username".to_string()));
+        }
+        if self.password.is_some() && self.username.is_none() {
+            return Err(Error::Configuration("A password requires a username".to_string()));
+        }
+        
+        // Validate headers
+        for (name, value) in &self.headers {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::Configuration(format!("Invalid header name {}: {}", name, e)))?;
+            reqwest::header::HeaderValue::from_str(value)
+                .map_err(|e| Error::Configuration(format!("Invalid value for header {}: {}", name, e)))?;
+        }
+        
+        // Validate TLS
+        if self.client_cert_path.is_some() != self.client_key_path.is_some() {
+            return Err(Error::Configuration(
+                "client_cert_path and client_key_path must be set together".to_string()
+            ));
+        }
+        
         Ok(())
     }
 }
@@ -200,6 +620,117 @@ impl TransformConfig {
     }
 }
 
+/// Action taken when the transform fails on a block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransformFailureAction {
+    /// Stop the pipeline and report the failing block
+    #[default]
+    Halt,
+    
+    /// Record the block in the dead-letter file and continue without its CDC messages
+    DeadLetter,
+}
+
+/// Configuration for the error policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorPolicyConfig {
+    /// Action taken when the transform fails on a block
+    #[serde(default)]
+    pub on_transform_failure: TransformFailureAction,
+    
+    /// Path of the dead-letter file (required for the dead_letter action)
+    #[serde(default)]
+    pub dead_letter_path: Option<String>,
+    
+    /// Delay before the first retry of a transient error, in milliseconds
+    #[serde(default = "default_initial_backoff")]
+    pub initial_backoff: u64,
+    
+    /// Maximum delay between retries, in milliseconds
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: u64,
+    
+    /// Maximum number of consecutive retries before halting (None retries forever)
+    #[serde(default = "default_policy_max_retries")]
+    pub max_retries: Option<u32>,
+}
+
+/// Default initial backoff
+fn default_initial_backoff() -> u64 {
+    1000
+}
+
+/// Default maximum backoff
+fn default_max_backoff() -> u64 {
+    60_000
+}
+
+/// Default maximum number of consecutive retries at the same block
+fn default_policy_max_retries() -> Option<u32> {
+    Some(DEFAULT_MAX_RETRIES)
+}
+
+impl Default for ErrorPolicyConfig {
+    fn default() -> Self {
+        Self {
+            on_transform_failure: TransformFailureAction::default(),
+            dead_letter_path: None,
+            initial_backoff: default_initial_backoff(),
+            max_backoff: default_max_backoff(),
+            max_retries: default_policy_max_retries(),
+        }
+    }
+}
+
+impl ErrorPolicyConfig {
+    /// Validate the error policy configuration
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the configuration is valid, an error otherwise
+    pub fn validate(&self) -> Result<()> {
+        if self.on_transform_failure == TransformFailureAction::DeadLetter && self.dead_letter_path.is_none() {
+            return Err(Error::Configuration("The dead_letter action requires a dead-letter path".to_string()));
+        }
+        
+        if self.initial_backoff == 0 {
+            return Err(Error::Configuration("Initial backoff must be greater than 0".to_string()));
+        }
+        
+        if self.max_backoff < self.initial_backoff {
+            return Err(Error::Configuration("Maximum backoff must not be lower than the initial backoff".to_string()));
+        }
+        
+        Ok(())
+    }
+    
+    /// Get the retry policy for transient errors
+    ///
+    /// # Returns
+    ///
+    /// The retry policy
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(self.initial_backoff),
+            max_backoff: Duration::from_millis(self.max_backoff),
+            max_retries: self.max_retries,
+        }
+    }
+    
+    /// Get the dead-letter queue for transform failures
+    ///
+    /// # Returns
+    ///
+    /// The dead-letter queue, or None if transform failures halt the pipeline
+    pub fn dead_letter_queue(&self) -> Option<DeadLetterQueue> {
+        match (self.on_transform_failure, &self.dead_letter_path) {
+            (TransformFailureAction::DeadLetter, Some(path)) => Some(DeadLetterQueue::new(path)),
+            _ => None,
+        }
+    }
+}
+
 /// Configuration for the CDC sink
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -241,6 +772,22 @@ pub enum SinkConfig {
         /// Flush interval in milliseconds (optional)
         #[serde(default = "default_flush_interval")]
         flush_interval: u64,
+        
+        /// Maximum number of pooled connections (optional)
+        #[serde(default = "default_pool_size")]
+        pool_size: usize,
+        
+        /// Path to a PEM file with a CA certificate to trust, which enables TLS (optional)
+        #[serde(default)]
+        ca_cert_path: Option<String>,
+        
+        /// Column holding the message key (optional)
+        #[serde(default = "default_key_column")]
+        key_column: String,
+        
+        /// Key column per table, overriding `key_column` (optional)
+        #[serde(default)]
+        key_columns: BTreeMap<String, String>,
     },
     
     /// File sink configuration
@@ -275,6 +822,16 @@ fn default_flush_interval() -> u64 {
     1000
 }
 
+/// Default connection pool size
+fn default_pool_size() -> usize {
+    crate::sink::DEFAULT_POSTGRES_POOL_SIZE
+}
+
+/// Default key column
+fn default_key_column() -> String {
+    crate::sink::DEFAULT_POSTGRES_KEY_COLUMN.to_string()
+}
+
 /// Default schema
 fn default_schema() -> String {
     "public".to_string()
@@ -314,16 +871,39 @@ impl SinkConfig {
                     return Err(Error::Configuration("Batch size must be greater than 0".to_string()));
                 }
             }
-            SinkConfig::Postgres { connection_string, batch_size, .. } => {
+            SinkConfig::Postgres { connection_string, batch_size, pool_size, ca_cert_path, key_column, key_columns, .. } => {
                 // Validate connection string
                 if connection_string.is_empty() {
                     return Err(Error::Configuration("Connection string cannot be empty".to_string()));
                 }
+                if let Err(e) = connection_string.parse::<tokio_postgres::Config>() {
+                    return Err(Error::Configuration(format!("Invalid PostgreSQL connection string: {}", e)));
+                }
                 
                 // Validate batch size
                 if *batch_size == 0 {
                     return Err(Error::Configuration("Batch size must be greater than 0".to_string()));
                 }
+                
+                // Validate pool size
+                if *pool_size == 0 {
+                    return Err(Error::Configuration("Pool size must be greater than 0".to_string()));
+                }
+                
+                // Validate CA certificate
+                if let Some(path) = ca_cert_path {
+                    if !Path::new(path).exists() {
+                        return Err(Error::Configuration(format!("CA certificate not found: {}", path)));
+                    }
+                }
+                
+                // Validate key columns
+                if key_column.is_empty() {
+                    return Err(Error::Configuration("Key column cannot be empty".to_string()));
+                }
+                if let Some(table) = key_columns.iter().find(|(_, column)| column.is_empty()).map(|(table, _)| table) {
+                    return Err(Error::Configuration(format!("Key column for table {} cannot be empty", table)));
+                }
             }
             SinkConfig::File { path, .. } => {
                 // Validate path
@@ -375,6 +955,8 @@ mod tests {
         let config = Config::from_str(config_str).unwrap();
         
         assert_eq!(config.metashrew.url, "http://localhost:8080");
+        assert_eq!(config.metashrew.urls(), vec!["http://localhost:8080"]);
+        assert_eq!(config.metashrew.strategy, EndpointStrategy::Failover);
         assert_eq!(config.transform.path, "transform.wasm");
         
         match config.sink {
@@ -386,9 +968,202 @@ mod tests {
         }
         
         assert_eq!(config.cache_size, 6);
+        assert!(config.end_height.is_none());
+        assert!(config.checkpoint_path.is_none());
+        assert_eq!(config.polling_interval, 1000);
+        assert!(config.tip_stream_url.is_none());
+        assert_eq!(config.prefetch_depth, 8);
+        assert!(!config.prefetch_views);
+        assert_eq!(config.finality_depth, 0);
+        assert!(config.finalized_sink.is_none());
+        assert!(!config.control_messages);
+        assert!(config.http_listen_address.is_none());
+        assert!(!config.admin_api);
+        assert_eq!(config.pipeline_name, "debshrew");
+        assert_eq!(config.metrics_path, "/metrics");
+        assert_eq!(config.error_policy.on_transform_failure, TransformFailureAction::Halt);
+        assert!(config.error_policy.dead_letter_queue().is_none());
+        assert_eq!(config.error_policy.retry_policy(), RetryPolicy::default());
         assert_eq!(config.log_level, "info");
     }
 
+    #[test]
+    fn test_config_finalized_sink() {
+        let config_str = r#"
+        {
+            "metashrew": {
+                "url": "http://localhost:8080"
+            },
+            "transform": {
+                "path": "transform.wasm"
+            },
+            "sink": {
+                "type": "console"
+            },
+            "finality_depth": 3,
+            "finalized_sink": {
+                "type": "kafka",
+                "bootstrap_servers": "localhost:9092",
+                "topic": "cdc-finalized"
+            }
+        }
+        "#;
+        
+        let config = Config::from_str(config_str).unwrap();
+        
+        assert_eq!(config.finality_depth, 3);
+        match config.finalized_sink {
+            Some(SinkConfig::Kafka { topic, .. }) => assert_eq!(topic, "cdc-finalized"),
+            _ => panic!("Expected Kafka finalized sink"),
+        }
+    }
+
+    #[test]
+    fn test_config_admin_api_requires_http_server() {
+        let mut config = Config::from_str(r#"
+        {
+            "metashrew": {
+                "url": "http://localhost:8080"
+            },
+            "transform": {
+                "path": "transform.wasm"
+            },
+            "sink": {
+                "type": "console"
+            },
+            "admin_api": true
+        }
+        "#).unwrap();
+        let dir = tempdir().unwrap();
+        let transform_path = dir.path().join("transform.wasm");
+        std::fs::write(&transform_path, b"").unwrap();
+        config.transform.path = transform_path.to_string_lossy().to_string();
+        
+        assert!(config.admin_api);
+        assert!(config.validate().is_err());
+        
+        // The admin API also requires a token
+        config.http_listen_address = Some("127.0.0.1:9090".to_string());
+        assert!(config.validate().is_err());
+        
+        config.admin_token = Some(String::new());
+        assert!(config.validate().is_err());
+        
+        config.admin_token = Some("secret".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!(config.resolve_admin_token().unwrap(), Some("secret".to_string()));
+        
+        config.admin_token_env = Some("DEBSHREW_TEST_ADMIN_TOKEN".to_string());
+        assert!(config.validate().is_err());
+        
+        config.admin_token = None;
+        assert!(config.validate().is_ok());
+        assert!(config.resolve_admin_token().is_err());
+        
+        // The metrics path cannot shadow an admin endpoint
+        config.metrics_path = "/admin/pause".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_metashrew_endpoints_config() {
+        let mut config: MetashrewConfig = serde_json::from_str(r#"
+        {
+            "url": "http://indexer-a:8080",
+            "endpoints": ["http://indexer-b:8080", "http://indexer-c:8080"],
+            "strategy": "quorum"
+        }
+        "#).unwrap();
+        
+        assert!(config.validate().is_ok());
+        assert_eq!(config.urls().len(), 3);
+        assert_eq!(config.quorum_size(), 2);
+        
+        config.quorum = Some(4);
+        assert!(config.validate().is_err());
+        
+        // A minority quorum would let two hashes both reach it
+        config.quorum = Some(1);
+        assert!(config.validate().is_err());
+        config.quorum = Some(3);
+        assert!(config.validate().is_ok());
+        config.endpoints.push("http://indexer-d:8080".to_string());
+        config.quorum = Some(2);
+        assert!(config.validate().is_err());
+        
+        // A quorum needs several endpoints to agree
+        config.endpoints.clear();
+        config.quorum = None;
+        assert!(config.validate().is_err());
+        
+        config.strategy = EndpointStrategy::Failover;
+        config.endpoints = vec!["not a url".to_string()];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_metashrew_auth_config() {
+        let dir = tempdir().unwrap();
+        let token_path = dir.path().join("token");
+        std::fs::write(&token_path, "file-token\n").unwrap();
+        
+        let mut config = MetashrewConfig::new("https://indexer:8080");
+        config.bearer_token_file = Some(token_path.to_string_lossy().to_string());
+        config.headers.insert("X-Api-Key".to_string(), "key".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!(config.resolve_bearer_token().unwrap(), Some("file-token".to_string()));
+        
+        // Only one token source and no basic auth alongside a token
+        config.bearer_token = Some("inline-token".to_string());
+        assert!(config.validate().is_err());
+        config.bearer_token_file = None;
+        config.username = Some("user".to_string());
+        assert!(config.validate().is_err());
+        config.bearer_token = None;
+        assert!(config.validate().is_ok());
+        
+        config.headers.insert("Bad Header".to_string(), "value".to_string());
+        assert!(config.validate().is_err());
+        config.headers.clear();
+        
+        // A client certificate needs its key
+        config.client_cert_path = Some("client.pem".to_string());
+        assert!(config.validate().is_err());
+        config.client_key_path = Some("client-key.pem".to_string());
+        assert!(config.validate().is_ok());
+        
+        config.bearer_token_env = Some("DEBSHREW_TEST_UNSET_TOKEN".to_string());
+        assert!(config.resolve_bearer_token().is_err());
+        config.bearer_token_env = None;
+        
+        config.max_concurrent_requests = Some(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_error_policy_config() {
+        let policy: ErrorPolicyConfig = serde_json::from_str(r#"
+        {
+            "on_transform_failure": "dead_letter",
+            "dead_letter_path": "dead-letters.jsonl",
+            "initial_backoff": 500,
+            "max_retries": 5
+        }
+        "#).unwrap();
+        
+        assert!(policy.validate().is_ok());
+        assert_eq!(policy.dead_letter_queue().unwrap().path(), Path::new("dead-letters.jsonl"));
+        assert_eq!(policy.retry_policy().initial_backoff, Duration::from_millis(500));
+        assert_eq!(policy.retry_policy().max_retries, Some(5));
+        
+        // Dead-lettering needs somewhere to write to
+        let policy = ErrorPolicyConfig {
+            on_transform_failure: TransformFailureAction::DeadLetter,
+            ..ErrorPolicyConfig::default()
+        };
+        assert!(policy.validate().is_err());
+    }
+
     #[test]
     fn test_config_from_file() {
         // Create a temporary directory
@@ -460,6 +1235,10 @@ mod tests {
             schema: "public".to_string(),
             batch_size: 100,
             flush_interval: 1000,
+            pool_size: 4,
+            ca_cert_path: None,
+            key_column: "id".to_string(),
+            key_columns: BTreeMap::new(),
         };
         
         assert!(postgres_sink.validate().is_ok());
@@ -470,10 +1249,36 @@ mod tests {
             schema: "public".to_string(),
             batch_size: 100,
             flush_interval: 1000,
+            pool_size: 4,
+            ca_cert_path: None,
+            key_column: "id".to_string(),
+            key_columns: BTreeMap::new(),
         };
         
         assert!(invalid_postgres_sink.validate().is_err());
         
+        // Test PostgreSQL sink pool and TLS options
+        let postgres_sink: SinkConfig = serde_json::from_str(
+            r#"{"type": "postgres", "connection_string": "host=localhost sslmode=require"}"#
+        ).unwrap();
+        assert!(matches!(
+            &postgres_sink,
+            SinkConfig::Postgres { pool_size: 4, ca_cert_path: None, key_column, key_columns, .. }
+                if key_column == "id" && key_columns.is_empty()
+        ));
+        assert!(postgres_sink.validate().is_ok());
+        
+        for invalid in [
+            r#"{"type": "postgres", "connection_string": "host=localhost sslmode=bogus"}"#,
+            r#"{"type": "postgres", "connection_string": "host=localhost", "pool_size": 0}"#,
+            r#"{"type": "postgres", "connection_string": "host=localhost", "ca_cert_path": "/nonexistent/ca.pem"}"#,
+            r#"{"type": "postgres", "connection_string": "host=localhost", "key_column": ""}"#,
+            r#"{"type": "postgres", "connection_string": "host=localhost", "key_columns": {"balances": ""}}"#,
+        ] {
+            let sink: SinkConfig = serde_json::from_str(invalid).unwrap();
+            assert!(sink.validate().is_err(), "{} should be invalid", invalid);
+        }
+        
         // Test File sink
         let file_sink = SinkConfig::File {
             path: "output.json".to_string(),
@@ -0,0 +1,717 @@
+//! Multiple metashrew endpoints
+//!
+//! This module provides a client that spreads requests over redundant metashrew
+//! indexers, so the pipeline keeps running when one of them lags or restarts.
+//! With the failover strategy, every request goes to the first healthy endpoint
+//! that has indexed the requested block. With the quorum strategy, a block hash
+//! is only accepted once enough endpoints agree on it.
+//!
+//! Every height request polls all endpoints, which keeps the health and lag of
+//! each endpoint current as the synchronizer polls for new blocks.
+
+use crate::client::JsonRpcClient;
+use crate::config::{EndpointStrategy, MetashrewConfig};
+use crate::error::{Error, Result};
+use crate::metrics;
+use crate::tip::is_block_not_available;
+use crate::traits::{BlockProviderLike, MetashrewClientLike, ViewCall, ViewProviderLike};
+use async_trait::async_trait;
+use futures::future::join_all;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// State of a metashrew endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    /// The endpoint identifier
+    pub identifier: String,
+
+    /// Whether the endpoint answered the last request made to it
+    pub healthy: bool,
+
+    /// The height the endpoint last reported as indexed, if known
+    pub height: Option<u32>,
+
+    /// The number of blocks the endpoint is behind the highest endpoint, if known
+    pub lag: Option<u32>,
+}
+
+/// What is known about an endpoint
+#[derive(Debug, Clone, Copy)]
+struct EndpointState {
+    /// Whether the endpoint answered the last request made to it
+    healthy: bool,
+
+    /// The height the endpoint last reported as indexed
+    height: Option<u32>,
+}
+
+/// A metashrew endpoint
+struct Endpoint {
+    /// The client for the endpoint
+    client: Arc<dyn MetashrewClientLike>,
+
+    /// The endpoint identifier
+    identifier: String,
+
+    /// What is known about the endpoint
+    state: Mutex<EndpointState>,
+}
+
+impl Endpoint {
+    /// Lock the endpoint state, recovering from a poisoned lock
+    fn lock_state(&self) -> MutexGuard<'_, EndpointState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Get what is known about the endpoint
+    fn state(&self) -> EndpointState {
+        *self.lock_state()
+    }
+
+    /// Record the outcome of a request to the endpoint
+    ///
+    /// A block the endpoint has not indexed yet does not make it unhealthy.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error the request failed with, or None if it succeeded
+    fn record_result(&self, error: Option<&Error>) {
+        let healthy = error.is_none_or(is_block_not_available);
+        let mut state = self.lock_state();
+
+        if state.healthy && !healthy {
+            warn!("Metashrew endpoint {} is unhealthy: {}", self.identifier, error.map(|e| e.to_string()).unwrap_or_default());
+        } else if !state.healthy && healthy {
+            info!("Metashrew endpoint {} recovered", self.identifier);
+        }
+        state.healthy = healthy;
+    }
+}
+
+/// Metashrew client backed by several endpoints
+///
+/// View calls always use failover, since views are deterministic for a block
+/// and cross-checking them would multiply the load on the indexers.
+pub struct MultiEndpointClient {
+    /// The endpoints, in order of preference
+    endpoints: Vec<Endpoint>,
+
+    /// How the endpoints are used
+    strategy: EndpointStrategy,
+
+    /// Number of endpoints that must agree with the quorum strategy
+    quorum: usize,
+}
+
+impl std::fmt::Debug for MultiEndpointClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiEndpointClient")
+            .field("endpoints", &self.endpoints.iter().map(|endpoint| &endpoint.identifier).collect::<Vec<_>>())
+            .field("strategy", &self.strategy)
+            .field("quorum", &self.quorum)
+            .finish()
+    }
+}
+
+impl MultiEndpointClient {
+    /// Create a new multi-endpoint client
+    ///
+    /// # Arguments
+    ///
+    /// * `clients` - The clients for each endpoint, in order of preference
+    /// * `strategy` - How the endpoints are used
+    /// * `quorum` - The number of endpoints that must agree with the quorum strategy
+    ///
+    /// # Returns
+    ///
+    /// A new multi-endpoint client
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no endpoints, or the quorum is not a
+    /// majority of the endpoints
+    pub fn new(clients: Vec<Arc<dyn MetashrewClientLike>>, strategy: EndpointStrategy, quorum: usize) -> Result<Self> {
+        if clients.is_empty() {
+            return Err(Error::Configuration("At least one metashrew endpoint is required".to_string()));
+        }
+        if strategy == EndpointStrategy::Quorum && (quorum <= clients.len() / 2 || quorum > clients.len()) {
+            return Err(Error::Configuration(format!(
+                "Quorum must be a majority of the metashrew endpoints, between {} and {}",
+                clients.len() / 2 + 1, clients.len()
+            )));
+        }
+
+        let endpoints = clients
+            .into_iter()
+            .map(|client| Endpoint {
+                identifier: client.get_identifier(),
+                client,
+                state: Mutex::new(EndpointState { healthy: true, height: None }),
+            })
+            .collect();
+
+        Ok(Self { endpoints, strategy, quorum })
+    }
+
+    /// Create a multi-endpoint client from a configuration
+    ///
+    /// Every endpoint uses a JSON-RPC client with the configuration's settings.
+    /// When there are several endpoints, a failed request moves on to the next
+    /// endpoint instead of being retried against the same one.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The metashrew configuration
+    ///
+    /// # Returns
+    ///
+    /// A new multi-endpoint client
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an endpoint URL is invalid or the quorum is not a
+    /// majority of the endpoints
+    pub fn from_config(config: &MetashrewConfig) -> Result<Self> {
+        // With several endpoints, failing over takes the place of retrying
+        let several = config.urls().len() > 1;
+        let clients = config
+            .urls()
+            .into_iter()
+            .map(|url| {
+                let client = JsonRpcClient::from_config(&MetashrewConfig {
+                    url: url.to_string(),
+                    max_retries: if several { 0 } else { config.max_retries },
+                    ..config.clone()
+                })?;
+                Ok(Arc::new(client) as Arc<dyn MetashrewClientLike>)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Self::new(clients, config.strategy, config.quorum_size())
+    }
+
+    /// Get the state of every endpoint
+    ///
+    /// # Returns
+    ///
+    /// The state of every endpoint, in order of preference
+    pub fn endpoint_status(&self) -> Vec<EndpointStatus> {
+        let states: Vec<EndpointState> = self.endpoints.iter().map(Endpoint::state).collect();
+        let highest = states.iter().filter_map(|state| state.height).max();
+
+        self.endpoints
+            .iter()
+            .zip(states)
+            .map(|(endpoint, state)| EndpointStatus {
+                identifier: endpoint.identifier.clone(),
+                healthy: state.healthy,
+                height: state.height,
+                lag: state.height.zip(highest).map(|(height, highest)| highest.saturating_sub(height)),
+            })
+            .collect()
+    }
+
+    /// Get the number of endpoints that must answer a request
+    fn required(&self) -> usize {
+        match self.strategy {
+            EndpointStrategy::Failover => 1,
+            EndpointStrategy::Quorum => self.quorum,
+        }
+    }
+
+    /// Poll the height of every endpoint
+    ///
+    /// # Returns
+    ///
+    /// The result of the height request to each endpoint
+    async fn refresh_heights(&self) -> Vec<Result<u32>> {
+        let results = join_all(self.endpoints.iter().map(|endpoint| endpoint.client.get_height())).await;
+
+        for (endpoint, result) in self.endpoints.iter().zip(&results) {
+            endpoint.record_result(result.as_ref().err());
+            if let Ok(height) = result {
+                endpoint.lock_state().height = Some(*height);
+            }
+        }
+        for status in self.endpoint_status() {
+            metrics::record_endpoint(&status.identifier, status.healthy, status.height, status.lag);
+        }
+
+        results
+    }
+
+    /// Order the endpoints for a request
+    ///
+    /// Healthy endpoints that have indexed the height come first, then other
+    /// healthy endpoints and finally unhealthy ones, each in order of preference.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height the request is for, if any
+    ///
+    /// # Returns
+    ///
+    /// The endpoints in the order they should be tried
+    fn candidates(&self, height: Option<u32>) -> Vec<&Endpoint> {
+        let mut candidates: Vec<(u8, &Endpoint)> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let state = endpoint.state();
+                let indexed = match (height, state.height) {
+                    (Some(height), Some(indexed_height)) => indexed_height >= height,
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                };
+                let rank = match (state.healthy, indexed) {
+                    (true, true) => 0,
+                    (true, false) => 1,
+                    (false, _) => 2,
+                };
+                (rank, endpoint)
+            })
+            .collect();
+
+        candidates.sort_by_key(|(rank, _)| *rank);
+        candidates.into_iter().map(|(_, endpoint)| endpoint).collect()
+    }
+
+    /// Send a request to the first endpoint that answers it
+    ///
+    /// Unhealthy endpoints are only tried once they report being healthy again.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height the request is for, if any
+    /// * `request` - The request to send to an endpoint
+    ///
+    /// # Returns
+    ///
+    /// The answer of the first endpoint that succeeded
+    ///
+    /// # Errors
+    ///
+    /// Returns the last endpoint's error if no endpoint answered
+    async fn failover<T, F, Fut>(&self, height: Option<u32>, request: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn MetashrewClientLike>) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T>> + Send,
+        T: Send,
+    {
+        let mut last_error = None;
+
+        for endpoint in self.candidates(height) {
+            if !endpoint.state().healthy && !endpoint.client.is_healthy().await {
+                continue;
+            }
+
+            match request(endpoint.client.clone()).await {
+                Ok(value) => {
+                    endpoint.record_result(None);
+                    return Ok(value);
+                }
+                Err(e) => {
+                    debug!("Metashrew endpoint {} failed, trying the next one: {}", endpoint.identifier, e);
+                    endpoint.record_result(Some(&e));
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| Error::MetashrewClient("No metashrew endpoint is healthy".to_string())))
+    }
+
+    /// Get a block hash that a quorum of endpoints agree on
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height
+    ///
+    /// # Returns
+    ///
+    /// The block hash
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoints disagree, or a block not found error
+    /// if too few endpoints serve the block yet
+    async fn quorum_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+        let endpoints: Vec<&Endpoint> = self.endpoints.iter().filter(|endpoint| endpoint.state().healthy).collect();
+        let results = join_all(endpoints.iter().map(|endpoint| endpoint.client.get_block_hash(height))).await;
+
+        let mut votes: HashMap<Vec<u8>, Vec<&str>> = HashMap::new();
+        for (endpoint, result) in endpoints.iter().zip(results) {
+            endpoint.record_result(result.as_ref().err());
+            if let Ok(hash) = result {
+                votes.entry(hash).or_default().push(&endpoint.identifier);
+            }
+        }
+
+        self.count_votes(height, votes)
+    }
+
+    /// Get the block hashes of a range of heights that a quorum of endpoints agree on
+    ///
+    /// Every endpoint is sent a single batch request, for the heights it has
+    /// indexed, and the hashes are voted on per height.
+    ///
+    /// # Arguments
+    ///
+    /// * `heights` - The block heights
+    ///
+    /// # Returns
+    ///
+    /// The block hashes, in height order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoints disagree on any of the heights, or a
+    /// block not found error if too few endpoints serve one of them yet
+    async fn quorum_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        let (start, end) = (*heights.start(), *heights.end());
+
+        // A lagging endpoint still votes on the blocks below its tip
+        let requests: Vec<(&Endpoint, RangeInclusive<u32>)> = self
+            .endpoints
+            .iter()
+            .filter_map(|endpoint| {
+                let state = endpoint.state();
+                let last = state.height.map_or(end, |indexed| indexed.min(end));
+                (state.healthy && last >= start).then_some((endpoint, start..=last))
+            })
+            .collect();
+        let results = join_all(requests.iter().map(|(endpoint, range)| endpoint.client.get_block_hashes(range.clone()))).await;
+
+        let mut votes: Vec<HashMap<Vec<u8>, Vec<&str>>> = heights.clone().map(|_| HashMap::new()).collect();
+        for ((endpoint, _), result) in requests.iter().zip(results) {
+            endpoint.record_result(result.as_ref().err());
+            for (votes, hash) in votes.iter_mut().zip(result.into_iter().flatten()) {
+                votes.entry(hash).or_default().push(&endpoint.identifier);
+            }
+        }
+
+        heights.zip(votes).map(|(height, votes)| self.count_votes(height, votes)).collect()
+    }
+
+    /// Pick the block hash a quorum of endpoints voted for
+    ///
+    /// The quorum is a majority of the endpoints, so at most one hash can reach it.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height
+    /// * `votes` - The endpoints that returned each hash
+    ///
+    /// # Returns
+    ///
+    /// The block hash
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoints disagree, or a block not found error
+    /// if too few endpoints serve the block yet
+    fn count_votes(&self, height: u32, votes: HashMap<Vec<u8>, Vec<&str>>) -> Result<Vec<u8>> {
+        if let Some((hash, agreeing)) = votes.iter().find(|(_, agreeing)| agreeing.len() >= self.quorum) {
+            if votes.len() > 1 {
+                warn!("Metashrew endpoints disagree on the block hash at height {}; using {} from {}",
+                      height, hex::encode(hash), agreeing.join(", "));
+            }
+            return Ok(hash.clone());
+        }
+
+        if votes.is_empty() {
+            return Err(Error::MetashrewClient(format!(
+                "Block hash not found for height {} on any metashrew endpoint", height
+            )));
+        }
+
+        let most = votes.values().map(Vec::len).max().unwrap_or(0);
+        if votes.len() > 1 {
+            return Err(Error::MetashrewClient(format!(
+                "Metashrew endpoints disagree on the block hash at height {}: at most {} of the required {} agree",
+                height, most, self.quorum
+            )));
+        }
+
+        Err(Error::MetashrewClient(format!(
+            "Block hash not found for height {} on enough metashrew endpoints: {} of the required {} serve it",
+            height, most, self.quorum
+        )))
+    }
+}
+
+#[async_trait]
+impl BlockProviderLike for MultiEndpointClient {
+    async fn get_height(&self) -> Result<u32> {
+        let results = self.refresh_heights().await;
+
+        // The highest height that enough endpoints have indexed
+        let mut heights: Vec<u32> = results.iter().filter_map(|result| result.as_ref().ok().copied()).collect();
+        heights.sort_unstable_by(|a, b| b.cmp(a));
+        if let Some(height) = heights.get(self.required() - 1) {
+            return Ok(*height);
+        }
+
+        let error = results.into_iter().find_map(|result| result.err()).map(|e| e.to_string()).unwrap_or_default();
+        Err(Error::MetashrewClient(format!(
+            "{} of {} metashrew endpoints answered, {} required: {}",
+            heights.len(), self.endpoints.len(), self.required(), error
+        )))
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+        match self.strategy {
+            EndpointStrategy::Failover => {
+                self.failover(Some(height), move |client| async move { client.get_block_hash(height).await })
+                    .await
+            }
+            EndpointStrategy::Quorum => self.quorum_block_hash(height).await,
+        }
+    }
+
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        match self.strategy {
+            EndpointStrategy::Failover => {
+                self.failover(Some(*heights.end()), move |client| {
+                    let heights = heights.clone();
+                    async move { client.get_block_hashes(heights).await }
+                })
+                .await
+            }
+            EndpointStrategy::Quorum => self.quorum_block_hashes(heights).await,
+        }
+    }
+}
+
+#[async_trait]
+impl ViewProviderLike for MultiEndpointClient {
+    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+        self.failover(height, move |client| async move { client.call_view(view_name, params, height).await })
+            .await
+    }
+
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        let height = calls.iter().filter_map(|call| call.height).max();
+        self.failover(height, move |client| async move { client.call_views(calls).await })
+            .await
+    }
+}
+
+#[async_trait]
+impl MetashrewClientLike for MultiEndpointClient {
+    fn get_identifier(&self) -> String {
+        self.endpoints.iter().map(|endpoint| endpoint.identifier.as_str()).collect::<Vec<_>>().join(", ")
+    }
+
+    async fn is_healthy(&self) -> bool {
+        let healthy = join_all(self.endpoints.iter().map(|endpoint| endpoint.client.is_healthy())).await;
+        healthy.into_iter().filter(|healthy| *healthy).count() >= self.required()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::MemoryMetashrewAdapter;
+
+    /// An endpoint that cannot be reached
+    #[derive(Debug)]
+    struct UnreachableEndpoint;
+
+    #[async_trait]
+    impl BlockProviderLike for UnreachableEndpoint {
+        async fn get_height(&self) -> Result<u32> {
+            Err(Error::MetashrewClient("Failed to send request: connection refused".to_string()))
+        }
+
+        async fn get_block_hash(&self, _height: u32) -> Result<Vec<u8>> {
+            Err(Error::MetashrewClient("Failed to send request: connection refused".to_string()))
+        }
+    }
+
+    #[async_trait]
+    impl ViewProviderLike for UnreachableEndpoint {
+        async fn call_view(&self, _view_name: &str, _params: &[u8], _height: Option<u32>) -> Result<Vec<u8>> {
+            Err(Error::MetashrewClient("Failed to send request: connection refused".to_string()))
+        }
+    }
+
+    #[async_trait]
+    impl MetashrewClientLike for UnreachableEndpoint {
+        fn get_identifier(&self) -> String {
+            "unreachable".to_string()
+        }
+
+        async fn is_healthy(&self) -> bool {
+            false
+        }
+    }
+
+    /// An endpoint that records the block hash requests it receives
+    #[derive(Debug)]
+    struct RecordingEndpoint {
+        inner: MemoryMetashrewAdapter,
+        requests: Mutex<Vec<RangeInclusive<u32>>>,
+    }
+
+    impl RecordingEndpoint {
+        fn new(inner: MemoryMetashrewAdapter) -> Arc<Self> {
+            Arc::new(Self { inner, requests: Mutex::new(Vec::new()) })
+        }
+
+        fn requests(&self) -> Vec<RangeInclusive<u32>> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl BlockProviderLike for RecordingEndpoint {
+        async fn get_height(&self) -> Result<u32> {
+            self.inner.get_height().await
+        }
+
+        async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+            self.requests.lock().unwrap().push(height..=height);
+            self.inner.get_block_hash(height).await
+        }
+
+        async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+            self.requests.lock().unwrap().push(heights.clone());
+            self.inner.get_block_hashes(heights).await
+        }
+    }
+
+    #[async_trait]
+    impl ViewProviderLike for RecordingEndpoint {
+        async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+            self.inner.call_view(view_name, params, height).await
+        }
+    }
+
+    #[async_trait]
+    impl MetashrewClientLike for RecordingEndpoint {
+        fn get_identifier(&self) -> String {
+            self.inner.get_identifier()
+        }
+    }
+
+    fn indexer(name: &str, height: u32) -> MemoryMetashrewAdapter {
+        let adapter = MemoryMetashrewAdapter::with_identifier(name);
+        adapter.set_height(height);
+        for h in 0..=height {
+            adapter.set_block_hash(h, vec![h as u8; 32]);
+        }
+        adapter
+    }
+
+    fn client(endpoints: &[Arc<dyn MetashrewClientLike>], strategy: EndpointStrategy, quorum: usize) -> MultiEndpointClient {
+        MultiEndpointClient::new(endpoints.to_vec(), strategy, quorum).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failover_skips_unreachable_and_lagging_endpoints() {
+        let lagging = indexer("lagging", 10);
+        let leading = indexer("leading", 12);
+        leading.set_view_result("balances", &[1], Some(12), vec![2]);
+
+        let endpoints: Vec<Arc<dyn MetashrewClientLike>> =
+            vec![Arc::new(UnreachableEndpoint), Arc::new(lagging), Arc::new(leading)];
+        let client = client(&endpoints, EndpointStrategy::Failover, 1);
+
+        assert_eq!(client.get_height().await.unwrap(), 12);
+        let status = client.endpoint_status();
+        assert!(!status[0].healthy);
+        assert_eq!((status[1].height, status[1].lag), (Some(10), Some(2)));
+        assert_eq!((status[2].height, status[2].lag), (Some(12), Some(0)));
+
+        // Blocks and views are served by an endpoint that has indexed them
+        assert_eq!(client.get_block_hash(12).await.unwrap(), vec![12; 32]);
+        assert_eq!(client.get_block_hash(5).await.unwrap(), vec![5; 32]);
+        assert_eq!(client.call_view("balances", &[1], Some(12)).await.unwrap(), vec![2]);
+        assert!(client.is_healthy().await);
+
+        // A block no endpoint has indexed yet is reported as not available
+        assert!(is_block_not_available(&client.get_block_hash(13).await.unwrap_err()));
+        assert!(client.endpoint_status()[2].healthy);
+    }
+
+    #[tokio::test]
+    async fn test_quorum_requires_agreeing_block_hashes() {
+        let a = indexer("a", 10);
+        let b = indexer("b", 10);
+        let c = indexer("c", 9);
+        c.set_block_hash(8, vec![0xff; 32]);
+        a.set_block_hash(11, vec![11; 32]);
+
+        let endpoints: Vec<Arc<dyn MetashrewClientLike>> = vec![Arc::new(a), Arc::new(b), Arc::new(c)];
+        let majority = client(&endpoints, EndpointStrategy::Quorum, 2);
+        let unanimous = client(&endpoints, EndpointStrategy::Quorum, 3);
+
+        // The tip is the highest height a quorum has indexed
+        assert_eq!(majority.get_height().await.unwrap(), 10);
+        assert_eq!(unanimous.get_height().await.unwrap(), 9);
+
+        // A dissenting endpoint is outvoted by the majority, but blocks a unanimous quorum
+        assert_eq!(majority.get_block_hash(8).await.unwrap(), vec![8; 32]);
+        let error = unanimous.get_block_hash(8).await.unwrap_err();
+        assert!(!is_block_not_available(&error));
+        assert_eq!(unanimous.get_block_hash(7).await.unwrap(), vec![7; 32]);
+
+        // A block served by too few endpoints is not available yet
+        assert!(is_block_not_available(&majority.get_block_hash(11).await.unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn test_quorum_fails_without_enough_healthy_endpoints() {
+        let endpoints: Vec<Arc<dyn MetashrewClientLike>> =
+            vec![Arc::new(indexer("a", 10)), Arc::new(UnreachableEndpoint), Arc::new(UnreachableEndpoint)];
+        let client = client(&endpoints, EndpointStrategy::Quorum, 2);
+
+        assert!(client.get_height().await.is_err());
+        assert!(!client.is_healthy().await);
+        assert!(MultiEndpointClient::new(endpoints.clone(), EndpointStrategy::Quorum, 4).is_err());
+
+        // A minority quorum would let two hashes both reach it
+        assert!(MultiEndpointClient::new(endpoints, EndpointStrategy::Quorum, 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quorum_batches_block_hashes_per_endpoint() {
+        let a = RecordingEndpoint::new(indexer("a", 10));
+        let b = RecordingEndpoint::new(indexer("b", 10));
+        let c = RecordingEndpoint::new(indexer("c", 8));
+        c.inner.set_block_hash(6, vec![0xff; 32]);
+
+        let endpoints: Vec<Arc<dyn MetashrewClientLike>> = vec![a.clone(), b.clone(), c.clone()];
+        let client = client(&endpoints, EndpointStrategy::Quorum, 2);
+        assert_eq!(client.get_height().await.unwrap(), 10);
+
+        // One batch per endpoint, and the lagging endpoint is only asked for what it has indexed
+        let hashes = client.get_block_hashes(5..=10).await.unwrap();
+        assert_eq!(hashes, (5..=10).map(|h| vec![h as u8; 32]).collect::<Vec<_>>());
+        assert_eq!(a.requests(), vec![5..=10]);
+        assert_eq!(b.requests(), vec![5..=10]);
+        assert_eq!(c.requests(), vec![5..=8]);
+        assert!(client.endpoint_status().iter().all(|status| status.healthy));
+
+        // Each height needs its own quorum
+        b.inner.set_block_hash(7, vec![0xee; 32]);
+        c.inner.set_block_hash(7, vec![0xdd; 32]);
+        let error = client.get_block_hashes(5..=8).await.unwrap_err();
+        assert!(error.to_string().contains("height 7"));
+        assert!(!is_block_not_available(&error));
+
+        // A height too few endpoints serve is not available yet
+        a.inner.set_height(11);
+        a.inner.set_block_hash(11, vec![11; 32]);
+        client.get_height().await.unwrap();
+        assert!(is_block_not_available(&client.get_block_hashes(10..=11).await.unwrap_err()));
+    }
+}
@@ -22,6 +22,10 @@ pub enum Error {
     #[error("Metashrew client error: {0}")]
     MetashrewClient(String),
 
+    /// Metashrew rejected the configured credentials
+    #[error("Authentication error: {0}")]
+    Authentication(String),
+
     /// Error occurred during block synchronization
     #[error("Block synchronization error: {0}")]
     BlockSynchronization(String),
@@ -34,6 +38,11 @@ pub enum Error {
     #[error("Sink error: {0}")]
     Sink(String),
 
+    /// A sink rejected messages in a way that fails again on every retry,
+    /// such as a constraint violation or a value that does not fit its column
+    #[error("Sink rejected messages: {0}")]
+    SinkRejected(String),
+
     /// Error occurred during configuration
     #[error("Configuration error: {0}")]
     Configuration(String),
@@ -66,6 +75,18 @@ pub enum Error {
     #[error("File error: {0}")]
     File(String),
 
+    /// Deterministic failure of the transform module, such as a trap or invalid output
+    #[error("Transform error: {0}")]
+    Transform(String),
+
+    /// A replayed run requested an exchange that was not recorded
+    #[error("Replay error: {0}")]
+    Replay(String),
+
+    /// Error occurred in the embedded HTTP server
+    #[error("HTTP server error: {0}")]
+    Server(String),
+
     /// Generic error with a message
     #[error("{0}")]
     Generic(String),
@@ -75,6 +96,59 @@ pub enum Error {
     Anyhow(#[from] anyhow::Error),
 }
 
+impl Error {
+    /// Check whether the error is transient
+    ///
+    /// Transient errors come from metashrew or a sink and may succeed when
+    /// retried. Everything else, including transform and authentication
+    /// failures, messages a sink rejected, and I/O errors such as a missing
+    /// file or denied permission, fails the same way every time.
+    ///
+    /// # Returns
+    ///
+    /// True if the operation that failed is worth retrying
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Io(e) => is_transient_io_error(e),
+            _ => matches!(
+                self,
+                Error::MetashrewClient(_)
+                    | Error::Http(_)
+                    | Error::Sink(_)
+                    | Error::Kafka(_)
+                    | Error::Postgres(_)
+                    | Error::File(_)
+            ),
+        }
+    }
+}
+
+/// Check whether an I/O error may succeed when retried
+///
+/// # Arguments
+///
+/// * `error` - The I/O error
+///
+/// # Returns
+///
+/// False for errors caused by the path, permissions or data, true otherwise
+pub fn is_transient_io_error(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    
+    !matches!(
+        error.kind(),
+        ErrorKind::NotFound
+            | ErrorKind::PermissionDenied
+            | ErrorKind::AlreadyExists
+            | ErrorKind::InvalidInput
+            | ErrorKind::InvalidData
+            | ErrorKind::Unsupported
+            | ErrorKind::IsADirectory
+            | ErrorKind::NotADirectory
+            | ErrorKind::ReadOnlyFilesystem
+    )
+}
+
 impl From<&str> for Error {
     fn from(s: &str) -> Self {
         Error::Generic(s.to_string())
@@ -93,8 +167,8 @@ impl From<rdkafka::error::KafkaError> for Error {
     }
 }
 
-impl From<postgres::Error> for Error {
-    fn from(e: postgres::Error) -> Self {
+impl From<tokio_postgres::Error> for Error {
+    fn from(e: tokio_postgres::Error) -> Self {
         Error::Postgres(format!("PostgreSQL error: {}", e))
     }
 }
@@ -121,6 +195,18 @@ mod tests {
         assert_eq!(error.to_string(), "Metashrew client error: connection failed");
     }
 
+    #[test]
+    fn test_error_is_transient() {
+        assert!(Error::MetashrewClient("timeout".to_string()).is_transient());
+        assert!(Error::Sink("broker down".to_string()).is_transient());
+        assert!(!Error::Transform("unreachable executed".to_string()).is_transient());
+        assert!(!Error::Configuration("bad config".to_string()).is_transient());
+        assert!(!Error::Authentication("401 Unauthorized".to_string()).is_transient());
+        assert!(!Error::SinkRejected("duplicate key".to_string()).is_transient());
+        assert!(Error::from(std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out")).is_transient());
+        assert!(!Error::from(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied")).is_transient());
+    }
+
     #[test]
     fn test_error_from_io_error() {
         let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
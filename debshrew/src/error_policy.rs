@@ -0,0 +1,262 @@
+//! Error handling policy
+//!
+//! This module defines how the synchronizer reacts to failures. Transient
+//! errors, which come from metashrew or a sink, are retried with exponential
+//! backoff. Deterministic transform failures either halt the pipeline or, when
+//! a dead-letter queue is configured, are recorded there so the pipeline can
+//! move past the failing block without emitting CDC messages for it.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default number of consecutive retries at the same block before halting
+pub const DEFAULT_MAX_RETRIES: u32 = 10;
+
+/// Retry policy for transient errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The delay before the first retry
+    pub initial_backoff: Duration,
+
+    /// The maximum delay between retries
+    pub max_backoff: Duration,
+
+    /// The maximum number of consecutive retries before giving up (None retries forever)
+    pub max_retries: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(1000),
+            max_backoff: Duration::from_millis(60_000),
+            max_retries: Some(DEFAULT_MAX_RETRIES),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Get the delay before a retry
+    ///
+    /// The delay doubles with every consecutive failure, up to the maximum
+    /// backoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `failures` - The number of consecutive failures so far, starting at 1
+    ///
+    /// # Returns
+    ///
+    /// The delay before the next attempt
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(31);
+        self.initial_backoff
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Get the delay before a retry, with jitter
+    ///
+    /// The delay is drawn uniformly between half and all of
+    /// [`backoff`](Self::backoff), so that clients failing at the same time do
+    /// not retry in lockstep.
+    ///
+    /// # Arguments
+    ///
+    /// * `failures` - The number of consecutive failures so far, starting at 1
+    ///
+    /// # Returns
+    ///
+    /// The delay before the next attempt
+    pub fn backoff_with_jitter(&self, failures: u32) -> Duration {
+        let backoff = self.backoff(failures);
+        let half = backoff / 2;
+        half + (backoff - half).mul_f64(rand::random::<f64>())
+    }
+
+    /// Check whether another retry is allowed
+    ///
+    /// # Arguments
+    ///
+    /// * `failures` - The number of consecutive failures so far
+    ///
+    /// # Returns
+    ///
+    /// True if the operation should be retried
+    pub fn should_retry(&self, failures: u32) -> bool {
+        self.max_retries.is_none_or(|max_retries| failures <= max_retries)
+    }
+}
+
+/// Dead letter
+///
+/// A block the transform failed on, recorded instead of halting the pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The block height
+    pub height: u32,
+
+    /// The block hash
+    pub hash: String,
+
+    /// The transform error, including any trap details
+    pub error: String,
+
+    /// The time the block was dead-lettered, in milliseconds since the epoch
+    pub timestamp: u64,
+}
+
+/// File-backed dead-letter queue
+///
+/// Dead letters are appended to the file as newline-delimited JSON.
+#[derive(Debug, Clone)]
+pub struct DeadLetterQueue {
+    /// The path of the dead-letter file
+    path: PathBuf,
+}
+
+impl DeadLetterQueue {
+    /// Create a new dead-letter queue
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the dead-letter file
+    ///
+    /// # Returns
+    ///
+    /// A new dead-letter queue
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Get the path of the dead-letter file
+    ///
+    /// # Returns
+    ///
+    /// The path of the dead-letter file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a dead letter
+    ///
+    /// # Arguments
+    ///
+    /// * `letter` - The dead letter to append
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the dead letter was written successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the dead-letter file cannot be written
+    pub fn push(&self, letter: &DeadLetter) -> Result<()> {
+        let line = serde_json::to_string(letter)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| Error::File(format!("Failed to open dead-letter file {}: {}", self.path.display(), e)))?;
+
+        writeln!(file, "{}", line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| Error::File(format!("Failed to write dead-letter file {}: {}", self.path.display(), e)))?;
+
+        Ok(())
+    }
+
+    /// Load every dead letter
+    ///
+    /// # Returns
+    ///
+    /// The dead letters in the order they were written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the dead-letter file cannot be read or parsed
+    pub fn load(&self) -> Result<Vec<DeadLetter>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| Error::File(format!("Failed to read dead-letter file {}: {}", self.path.display(), e)))?;
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .map_err(|e| Error::File(format!("Failed to parse dead-letter file {}: {}", self.path.display(), e)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+            max_retries: Some(3),
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(1000));
+
+        for failures in 1..=5 {
+            let delay = policy.backoff_with_jitter(failures);
+            assert!(delay >= policy.backoff(failures) / 2);
+            assert!(delay <= policy.backoff(failures));
+        }
+
+        assert!(policy.should_retry(3));
+        assert!(!policy.should_retry(4));
+        assert!(RetryPolicy::default().should_retry(DEFAULT_MAX_RETRIES));
+        assert!(!RetryPolicy::default().should_retry(DEFAULT_MAX_RETRIES + 1));
+
+        let forever = RetryPolicy {
+            max_retries: None,
+            ..RetryPolicy::default()
+        };
+        assert!(forever.should_retry(u32::MAX));
+    }
+
+    #[test]
+    fn test_dead_letter_queue() {
+        let dir = tempdir().unwrap();
+        let queue = DeadLetterQueue::new(dir.path().join("dead-letters.jsonl"));
+        assert!(queue.load().unwrap().is_empty());
+
+        let letters: Vec<DeadLetter> = (1..=2)
+            .map(|height| DeadLetter {
+                height,
+                hash: format!("{:02x}", height),
+                error: "Transform error: unreachable".to_string(),
+                timestamp: 1234,
+            })
+            .collect();
+        for letter in &letters {
+            queue.push(letter).unwrap();
+        }
+
+        assert_eq!(queue.load().unwrap(), letters);
+    }
+}
@@ -0,0 +1,585 @@
+//! Fault injection
+//!
+//! This module provides wrappers around metashrew clients and CDC sinks that
+//! inject latency, errors, timeouts, stale heights and flapping block hashes,
+//! so the synchronizer's retry and reorg handling can be exercised in tests
+//! and in a chaos run. Faults are drawn from a seeded random number
+//! generator, so a sequence of calls sees the same faults on every run.
+
+use crate::error::{Error, Result};
+use crate::sink::CdcSink;
+use crate::traits::{BlockProviderLike, MetashrewClientLike, ViewCall, ViewProviderLike};
+use async_trait::async_trait;
+use debshrew_support::{CdcControlMessage, CdcMessage};
+use log::debug;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Faults to inject
+///
+/// Rates are probabilities between 0 and 1, rolled independently for every
+/// call. The default injects nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultConfig {
+    /// Seed of the random number generator
+    #[serde(default)]
+    pub seed: u64,
+
+    /// Minimum latency added to every call, in milliseconds
+    #[serde(default)]
+    pub min_latency_ms: u64,
+
+    /// Maximum latency added to every call, in milliseconds
+    #[serde(default)]
+    pub max_latency_ms: u64,
+
+    /// Rate of calls that fail immediately
+    #[serde(default)]
+    pub error_rate: f64,
+
+    /// Rate of calls that fail with an error that is not retried
+    #[serde(default)]
+    pub permanent_error_rate: f64,
+
+    /// Rate of calls that hang for `timeout_ms` and then fail
+    #[serde(default)]
+    pub timeout_rate: f64,
+
+    /// How long a timed out call hangs, in milliseconds
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Rate of height requests answered with a height below the real one
+    #[serde(default)]
+    pub stale_height_rate: f64,
+
+    /// Maximum number of blocks a stale height lags behind
+    #[serde(default = "default_max_staleness")]
+    pub max_staleness: u32,
+
+    /// Rate of block hash requests answered with a different hash, as during a reorg
+    #[serde(default)]
+    pub flapping_hash_rate: f64,
+}
+
+/// Get the default timeout of injected timeouts
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+/// Get the default maximum staleness of injected stale heights
+fn default_max_staleness() -> u32 {
+    3
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            min_latency_ms: 0,
+            max_latency_ms: 0,
+            error_rate: 0.0,
+            permanent_error_rate: 0.0,
+            timeout_rate: 0.0,
+            timeout_ms: default_timeout_ms(),
+            stale_height_rate: 0.0,
+            max_staleness: default_max_staleness(),
+            flapping_hash_rate: 0.0,
+        }
+    }
+}
+
+impl FaultConfig {
+    /// Load a fault configuration from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The configuration file
+    ///
+    /// # Returns
+    ///
+    /// The validated fault configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or the configuration is invalid
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        let config: Self = serde_json::from_str(&contents).map_err(|e| {
+            Error::Configuration(format!("Invalid fault configuration {}: {}", path.as_ref().display(), e))
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Validate the fault configuration
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the configuration is valid
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rate is not between 0 and 1 or the latency range is empty
+    pub fn validate(&self) -> Result<()> {
+        let rates = [
+            ("error_rate", self.error_rate),
+            ("permanent_error_rate", self.permanent_error_rate),
+            ("timeout_rate", self.timeout_rate),
+            ("stale_height_rate", self.stale_height_rate),
+            ("flapping_hash_rate", self.flapping_hash_rate),
+        ];
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(Error::Configuration(format!("{} must be between 0 and 1, got {}", name, rate)));
+            }
+        }
+
+        if self.min_latency_ms > self.max_latency_ms {
+            return Err(Error::Configuration(format!(
+                "min_latency_ms ({}) must not exceed max_latency_ms ({})",
+                self.min_latency_ms, self.max_latency_ms
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Seeded source of faults
+///
+/// Clones share the random number generator.
+#[derive(Debug, Clone)]
+pub struct FaultInjector {
+    /// The faults to inject
+    config: FaultConfig,
+
+    /// The random number generator the faults are drawn from
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl FaultInjector {
+    /// Create a new fault injector
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The faults to inject
+    ///
+    /// # Returns
+    ///
+    /// A new fault injector seeded from the configuration
+    pub fn new(config: FaultConfig) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed);
+        Self {
+            config,
+            rng: Arc::new(Mutex::new(rng)),
+        }
+    }
+
+    /// Get the faults being injected
+    ///
+    /// # Returns
+    ///
+    /// The fault configuration
+    pub fn config(&self) -> &FaultConfig {
+        &self.config
+    }
+
+    /// Delay a call and possibly fail it
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The name of the call, used in error messages
+    /// * `error` - Builds the transient error of the wrapped component from a message
+    /// * `permanent_error` - Builds the permanent error of the wrapped component from a message
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the call should go ahead
+    ///
+    /// # Errors
+    ///
+    /// Returns the injected error or timeout
+    pub async fn inject(
+        &self,
+        operation: &str,
+        error: fn(String) -> Error,
+        permanent_error: fn(String) -> Error,
+    ) -> Result<()> {
+        let (latency, timeout, fail, fail_permanently) = {
+            let mut rng = self.lock_rng();
+            let latency = rng.gen_range(self.config.min_latency_ms..=self.config.max_latency_ms);
+            let timeout = roll(&mut rng, self.config.timeout_rate);
+            let fail = roll(&mut rng, self.config.error_rate);
+            let fail_permanently = roll(&mut rng, self.config.permanent_error_rate);
+            (latency, timeout, fail, fail_permanently)
+        };
+
+        if latency > 0 {
+            tokio::time::sleep(Duration::from_millis(latency)).await;
+        }
+
+        if timeout {
+            debug!("Injecting a timeout into {}", operation);
+            tokio::time::sleep(Duration::from_millis(self.config.timeout_ms)).await;
+            return Err(error(format!("Injected timeout after {} ms in {}", self.config.timeout_ms, operation)));
+        }
+
+        if fail {
+            debug!("Injecting a failure into {}", operation);
+            return Err(error(format!("Injected failure in {}", operation)));
+        }
+
+        if fail_permanently {
+            debug!("Injecting a permanent failure into {}", operation);
+            return Err(permanent_error(format!("Injected permanent failure in {}", operation)));
+        }
+
+        Ok(())
+    }
+
+    /// Possibly make a height stale
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The real height
+    ///
+    /// # Returns
+    ///
+    /// The real height, or a height up to `max_staleness` blocks below it
+    pub fn stale_height(&self, height: u32) -> u32 {
+        let mut rng = self.lock_rng();
+        if !roll(&mut rng, self.config.stale_height_rate) || self.config.max_staleness == 0 {
+            return height;
+        }
+        let staleness = rng.gen_range(1..=self.config.max_staleness);
+        debug!("Injecting a height {} blocks behind {}", staleness, height);
+        height.saturating_sub(staleness)
+    }
+
+    /// Possibly replace a block hash
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height
+    /// * `hash` - The real block hash
+    ///
+    /// # Returns
+    ///
+    /// The real hash, or a hash differing from it in every byte
+    pub fn flapping_hash(&self, height: u32, hash: Vec<u8>) -> Vec<u8> {
+        if !roll(&mut self.lock_rng(), self.config.flapping_hash_rate) {
+            return hash;
+        }
+        debug!("Injecting a different block hash at height {}", height);
+        hash.into_iter().map(|byte| !byte).collect()
+    }
+
+    /// Lock the random number generator, recovering from a poisoned lock
+    fn lock_rng(&self) -> std::sync::MutexGuard<'_, StdRng> {
+        match self.rng.lock() {
+            Ok(rng) => rng,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Roll a fault with the given rate
+fn roll(rng: &mut StdRng, rate: f64) -> bool {
+    rate > 0.0 && rng.gen_bool(rate)
+}
+
+/// Metashrew client wrapper that injects faults
+///
+/// Errors and timeouts are reported as metashrew client errors, so the
+/// synchronizer treats them as transient. Permanent failures are reported as
+/// authentication errors, which halt the synchronizer like rejected credentials.
+#[derive(Debug, Clone)]
+pub struct FaultyClient<C> {
+    /// The wrapped client
+    inner: C,
+
+    /// The source of faults
+    injector: FaultInjector,
+}
+
+impl<C> FaultyClient<C> {
+    /// Create a new faulty client
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The client to wrap
+    /// * `config` - The faults to inject
+    ///
+    /// # Returns
+    ///
+    /// A new faulty client
+    pub fn new(inner: C, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            injector: FaultInjector::new(config),
+        }
+    }
+
+    /// Get the wrapped client
+    ///
+    /// # Returns
+    ///
+    /// The wrapped client
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<C: MetashrewClientLike> BlockProviderLike for FaultyClient<C> {
+    async fn get_height(&self) -> Result<u32> {
+        self.injector.inject("get_height", Error::MetashrewClient, Error::Authentication).await?;
+        let height = BlockProviderLike::get_height(&self.inner).await?;
+        Ok(self.injector.stale_height(height))
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+        self.injector.inject("get_block_hash", Error::MetashrewClient, Error::Authentication).await?;
+        let hash = BlockProviderLike::get_block_hash(&self.inner, height).await?;
+        Ok(self.injector.flapping_hash(height, hash))
+    }
+
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        self.injector.inject("get_block_hashes", Error::MetashrewClient, Error::Authentication).await?;
+        let hashes = BlockProviderLike::get_block_hashes(&self.inner, heights.clone()).await?;
+        Ok(heights.zip(hashes).map(|(height, hash)| self.injector.flapping_hash(height, hash)).collect())
+    }
+}
+
+#[async_trait]
+impl<C: MetashrewClientLike> ViewProviderLike for FaultyClient<C> {
+    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+        self.injector.inject("call_view", Error::MetashrewClient, Error::Authentication).await?;
+        ViewProviderLike::call_view(&self.inner, view_name, params, height).await
+    }
+
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        self.injector.inject("call_views", Error::MetashrewClient, Error::Authentication).await?;
+        ViewProviderLike::call_views(&self.inner, calls).await
+    }
+}
+
+#[async_trait]
+impl<C: MetashrewClientLike> MetashrewClientLike for FaultyClient<C> {
+    fn get_identifier(&self) -> String {
+        format!("{} (fault injection)", self.inner.get_identifier())
+    }
+
+    async fn is_healthy(&self) -> bool {
+        self.inner.is_healthy().await
+    }
+}
+
+/// CDC sink wrapper that injects faults
+///
+/// Errors and timeouts are reported as sink errors, so the synchronizer
+/// treats them as transient. Permanent failures are reported as rejected
+/// messages, which halt the synchronizer. Closing the sink is never faulted,
+/// so a run can always shut down.
+pub struct FaultySink {
+    /// The wrapped sink
+    inner: Box<dyn CdcSink>,
+
+    /// The source of faults
+    injector: FaultInjector,
+}
+
+impl FaultySink {
+    /// Create a new faulty sink
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The sink to wrap
+    /// * `config` - The faults to inject
+    ///
+    /// # Returns
+    ///
+    /// A new faulty sink
+    pub fn new(inner: Box<dyn CdcSink>, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            injector: FaultInjector::new(config),
+        }
+    }
+}
+
+#[async_trait]
+impl CdcSink for FaultySink {
+    async fn send(&self, messages: Vec<CdcMessage>) -> Result<()> {
+        self.injector.inject("send", Error::Sink, Error::SinkRejected).await?;
+        self.inner.send(messages).await
+    }
+
+    async fn send_control(&self, message: CdcControlMessage) -> Result<()> {
+        self.injector.inject("send_control", Error::Sink, Error::SinkRejected).await?;
+        self.inner.send_control(message).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.injector.inject("flush", Error::Sink, Error::SinkRejected).await?;
+        self.inner.flush().await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.inner.close().await
+    }
+
+    async fn is_healthy(&self) -> bool {
+        self.inner.is_healthy().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::MemoryAdapterBuilder;
+    use crate::error_policy::RetryPolicy;
+    use crate::runtime::WasmRuntime;
+    use crate::sink::NullSink;
+    use crate::synchronizer::BlockSynchronizer;
+
+    fn chaos_config(seed: u64) -> FaultConfig {
+        FaultConfig {
+            seed,
+            max_latency_ms: 2,
+            error_rate: 0.2,
+            timeout_rate: 0.05,
+            timeout_ms: 5,
+            stale_height_rate: 0.3,
+            flapping_hash_rate: 0.1,
+            ..FaultConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_faults_are_reproducible() {
+        let metashrew = MemoryAdapterBuilder::new().with_height(100).build();
+        for height in 1..=100 {
+            metashrew.set_block_hash(height, vec![height as u8; 4]);
+        }
+
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            let client = FaultyClient::new(metashrew.clone(), chaos_config(7));
+            let mut answers = Vec::new();
+            for height in 1..=50 {
+                answers.push(BlockProviderLike::get_height(&client).await.ok());
+                answers.push(BlockProviderLike::get_block_hash(&client, height).await.ok().map(|hash| hash[0] as u32));
+            }
+            runs.push(answers);
+        }
+        assert_eq!(runs[0], runs[1]);
+
+        // Every kind of fault showed up
+        let answers = &runs[0];
+        assert!(answers.iter().any(Option::is_none));
+        assert!(answers.iter().step_by(2).any(|height| matches!(height, Some(height) if *height < 100)));
+        assert!(answers.iter().skip(1).step_by(2).any(|hash| matches!(hash, Some(byte) if *byte > 100)));
+
+        // The default configuration injects nothing
+        let client = FaultyClient::new(metashrew, FaultConfig::default());
+        for height in 1..=50 {
+            assert_eq!(BlockProviderLike::get_block_hash(&client, height).await.unwrap(), vec![height as u8; 4]);
+        }
+    }
+
+    #[test]
+    fn test_fault_config_validation() {
+        assert!(FaultConfig::default().validate().is_ok());
+        assert!(chaos_config(0).validate().is_ok());
+
+        let config = FaultConfig { error_rate: 1.5, ..FaultConfig::default() };
+        assert!(matches!(config.validate(), Err(Error::Configuration(_))));
+
+        let config = FaultConfig { min_latency_ms: 10, max_latency_ms: 5, ..FaultConfig::default() };
+        assert!(config.validate().is_err());
+
+        let config = FaultConfig { permanent_error_rate: -0.1, ..FaultConfig::default() };
+        assert!(config.validate().is_err());
+
+        let config: FaultConfig = serde_json::from_str(r#"{"seed": 3, "error_rate": 0.5}"#).unwrap();
+        assert_eq!(config.seed, 3);
+        assert_eq!(config.timeout_ms, 30_000);
+        assert_eq!(config.permanent_error_rate, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_faults_are_not_transient() {
+        let config = FaultConfig { permanent_error_rate: 1.0, ..FaultConfig::default() };
+
+        let client = FaultyClient::new(MemoryAdapterBuilder::new().with_height(1).build(), config.clone());
+        let error = BlockProviderLike::get_height(&client).await.unwrap_err();
+        assert!(matches!(error, Error::Authentication(_)));
+        assert!(!error.is_transient());
+
+        let sink = FaultySink::new(Box::new(NullSink), config);
+        let error = sink.send(Vec::new()).await.unwrap_err();
+        assert!(matches!(error, Error::SinkRejected(_)));
+        assert!(!error.is_transient());
+        assert!(sink.close().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_synchronizer_halts_on_permanent_faults() {
+        let metashrew = MemoryAdapterBuilder::new().with_height(5).build();
+        for height in 0..=5 {
+            metashrew.set_block_hash(height, vec![height as u8; 32]);
+        }
+        let config = FaultConfig { seed: 1, permanent_error_rate: 1.0, ..FaultConfig::default() };
+        let sink = FaultySink::new(Box::new(NullSink), config);
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(metashrew, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_end_height(5);
+        synchronizer.set_retry_policy(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            max_retries: None,
+        });
+
+        // Retrying forever would time out
+        let result = tokio::time::timeout(Duration::from_secs(10), synchronizer.run())
+            .await
+            .expect("run retried a permanent failure");
+        assert!(matches!(result, Err(Error::SinkRejected(_))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_synchronizer_survives_chaos() {
+        let metashrew = MemoryAdapterBuilder::new().with_height(20).build();
+        for height in 0..=20 {
+            metashrew.set_block_hash(height, vec![height as u8; 32]);
+        }
+        let client = FaultyClient::new(metashrew, chaos_config(42));
+        let sink = FaultySink::new(Box::new(NullSink), chaos_config(43));
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_end_height(10);
+        synchronizer.set_retry_policy(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            max_retries: None,
+        });
+
+        tokio::time::timeout(Duration::from_secs(60), synchronizer.run())
+            .await
+            .expect("run did not get through the injected faults")
+            .unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 10);
+    }
+}
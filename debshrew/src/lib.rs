@@ -15,7 +15,7 @@
 //! # Example
 //!
 //! ```no_run
-//! use debshrew::{BlockSynchronizer, MetashrewClient, JsonRpcClient, create_sink, SinkConfig, WasmRuntime};
+//! use debshrew::{BlockSynchronizer, JsonRpcClient, create_sink, SinkConfig, WasmRuntime};
 //! use std::path::Path;
 //!
 //! #[tokio::main]
@@ -50,25 +50,50 @@
 #![warn(rustdoc::missing_doc_code_examples)]
 
 pub mod adapters;
+pub mod admin;
 pub mod block;
+pub mod checkpoint;
 pub mod client;
 pub mod config;
+pub mod endpoints;
 pub mod error;
+pub mod error_policy;
+pub mod faults;
+pub mod metrics;
+pub mod mock_metashrew;
+pub mod observer;
+pub mod replay;
 pub mod runtime;
+pub mod server;
 pub mod sink;
+pub mod source;
+pub mod status;
 pub mod synchronizer;
+pub mod tip;
 pub mod traits;
 
 #[cfg(test)]
 pub mod tests;
 
 /// Re-export common types and functions for convenience
+pub use admin::AdminHandle;
 pub use block::BlockCache;
+pub use checkpoint::{Checkpoint, CheckpointStore};
 pub use client::*;
 pub use config::*;
+pub use endpoints::{EndpointStatus, MultiEndpointClient};
 pub use runtime::WasmRuntime;
-pub use debshrew_support::{CdcMessage, CdcHeader, CdcOperation, CdcPayload, TransformState};
+pub use debshrew_support::{CdcControl, CdcControlMessage, CdcMessage, CdcHeader, CdcOperation, CdcPayload, TransformState};
 pub use error::{Error, Result};
+pub use error_policy::{DeadLetter, DeadLetterQueue, RetryPolicy};
+pub use faults::{FaultConfig, FaultInjector, FaultyClient, FaultySink};
+pub use mock_metashrew::{MockMetashrewServer, MockScenario, MockView};
+pub use observer::SyncObserver;
+pub use replay::{Exchange, RecordingClient, ReplayClient};
 pub use sink::{CdcSink, create_sink, ConsoleSink, FileSink, KafkaSink, NullSink, PostgresSink};
-pub use synchronizer::{BlockSynchronizer, Synchronizer};
+pub use server::{Readiness, StatusServer};
+pub use source::{BlockSource, PollingBlockSource, StreamBlockSource, TipEvent};
+pub use status::{ErrorStatus, ReorgStatus, StatusHandle, SyncStatus};
+pub use synchronizer::{BlockSynchronizer, StopHandle, Synchronizer};
+pub use tip::{Tip, TipTracker};
 pub use traits::*;
\ No newline at end of file
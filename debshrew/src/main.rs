@@ -5,16 +5,26 @@
 
 use clap::{Parser, Subcommand};
 use debshrew::{
-    client::JsonRpcClient,
-    config::{Config, SinkConfig},
+    checkpoint::CheckpointStore,
+    config::{Config, EndpointStrategy, ErrorPolicyConfig, MetashrewConfig, SinkConfig, TransformFailureAction},
     create_sink,
+    endpoints::MultiEndpointClient,
     error::Result,
+    error_policy::DEFAULT_MAX_RETRIES,
+    faults::{FaultConfig, FaultySink, FaultyClient},
+    metrics,
+    mock_metashrew::{MockMetashrewServer, MockScenario},
+    replay::{RecordingClient, ReplayClient},
+    server::StatusServer,
+    source::StreamBlockSource,
+    traits::MetashrewClientLike,
     BlockSynchronizer,
     WasmRuntime,
 };
 use env_logger::Env;
-use log::{error, info};
+use log::{error, info, warn};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::signal;
 
 /// Debshrew CLI
@@ -28,6 +38,7 @@ struct Cli {
 
 /// CLI commands
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // Parsed once at startup
 enum Commands {
     /// Run the debshrew service
     Run {
@@ -39,6 +50,14 @@ enum Commands {
         #[clap(short, long)]
         metashrew_url: Option<String>,
         
+        /// Additional metashrew URL to fail over to (can be repeated)
+        #[clap(long = "metashrew-endpoint")]
+        metashrew_endpoints: Vec<String>,
+        
+        /// Only process blocks whose hash this many metashrew endpoints agree on
+        #[clap(long)]
+        metashrew_quorum: Option<usize>,
+        
         /// Path to the transform WASM module
         #[clap(short, long)]
         transform: Option<PathBuf>,
@@ -59,6 +78,101 @@ enum Commands {
         #[clap(short = 'b', long)]
         start_height: Option<u32>,
         
+        /// Last block height to emit before exiting
+        #[clap(short = 'e', long)]
+        end_height: Option<u32>,
+        
+        /// Path of the checkpoint file
+        #[clap(long)]
+        checkpoint_path: Option<PathBuf>,
+        
+        /// Interval between tip checks in milliseconds
+        #[clap(long, default_value = "1000")]
+        polling_interval: u64,
+        
+        /// URL of a newline-delimited JSON stream of tip events
+        #[clap(long)]
+        tip_stream_url: Option<String>,
+        
+        /// Number of upcoming blocks to prefetch during catch-up
+        #[clap(long, default_value = "8")]
+        prefetch_depth: usize,
+        
+        /// Prefetch the view calls recorded for the previous block
+        #[clap(long)]
+        prefetch_views: bool,
+        
+        /// Number of confirmations required before a block's CDC messages are emitted
+        #[clap(long, default_value = "0")]
+        finality_depth: u32,
+        
+        /// Path to the configuration file of a separate sink for finalized blocks
+        #[clap(long)]
+        finalized_sink_config: Option<PathBuf>,
+        
+        /// Mark block and reorg boundaries with control messages
+        #[clap(long)]
+        control_messages: bool,
+        
+        /// Address to serve /healthz, /readyz and /status on (e.g. 0.0.0.0:9090)
+        #[clap(long)]
+        http_listen_address: Option<String>,
+        
+        /// Serve the admin endpoints for pausing, resuming and rewinding on the HTTP server
+        #[clap(long)]
+        admin_api: bool,
+        
+        /// Environment variable holding the token admin requests must present as a bearer token
+        #[clap(long, default_value = "DEBSHREW_ADMIN_TOKEN")]
+        admin_token_env: String,
+        
+        /// Write blocks the transform fails on to this file instead of halting
+        #[clap(long)]
+        dead_letter_path: Option<PathBuf>,
+        
+        /// Maximum number of consecutive retries of a transient error before halting [default: 10]
+        #[clap(long)]
+        max_retries: Option<u32>,
+        
+        /// Record every metashrew exchange to this fixture file
+        #[clap(long, conflicts_with = "replay_fixture")]
+        record_fixture: Option<PathBuf>,
+        
+        /// Serve metashrew exchanges from this recorded fixture instead of metashrew
+        #[clap(long)]
+        replay_fixture: Option<PathBuf>,
+        
+        /// Inject the faults described in this file into metashrew and sink calls
+        #[clap(long)]
+        chaos_config: Option<PathBuf>,
+        
+        /// Pipeline name used as the `pipeline` label on metrics
+        #[clap(long, default_value = "debshrew")]
+        pipeline_name: String,
+        
+        /// Path to serve Prometheus metrics on
+        #[clap(long, default_value = "/metrics")]
+        metrics_path: String,
+        
+        /// Log level
+        #[clap(short, long, default_value = "info")]
+        log_level: String,
+    },
+    
+    /// Serve a mock metashrew JSON-RPC API from an in-memory chain
+    MockMetashrew {
+        /// Path to the scenario file with the initial blocks and view results
+        #[clap(short, long)]
+        scenario: Option<PathBuf>,
+        
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        listen_address: String,
+        
+        /// Inject the faults described in this file into JSON-RPC requests
+        #[clap(long)]
+        chaos_config: Option<PathBuf>,
+        
         /// Log level
         #[clap(short, long, default_value = "info")]
         log_level: String,
@@ -76,11 +190,32 @@ async fn main() -> Result<()> {
         Commands::Run {
             config,
             metashrew_url,
+            metashrew_endpoints,
+            metashrew_quorum,
             transform,
             sink_type,
             sink_config,
             cache_size,
             start_height,
+            end_height,
+            checkpoint_path,
+            polling_interval,
+            tip_stream_url,
+            prefetch_depth,
+            prefetch_views,
+            finality_depth,
+            finalized_sink_config,
+            control_messages,
+            http_listen_address,
+            admin_api,
+            admin_token_env,
+            dead_letter_path,
+            max_retries,
+            record_fixture,
+            replay_fixture,
+            chaos_config,
+            pipeline_name,
+            metrics_path,
             log_level,
         } => {
             // Initialize logger
@@ -89,7 +224,19 @@ async fn main() -> Result<()> {
             // Load configuration
             let config = if let Some(config_path) = config {
                 info!("Loading configuration from {}", config_path.display());
-                Config::from_file(config_path)?
+                let mut config = Config::from_file(config_path)?;
+                
+                // The block range and checkpoint of a run override the file, so bounded runs can be scripted
+                if start_height.is_some() {
+                    config.start_height = start_height;
+                }
+                if end_height.is_some() {
+                    config.end_height = end_height;
+                }
+                if let Some(path) = checkpoint_path {
+                    config.checkpoint_path = Some(path.to_string_lossy().to_string());
+                }
+                config
             } else {
                 // Create configuration from command line arguments
                 let metashrew_url = metashrew_url.ok_or_else(|| {
@@ -135,6 +282,14 @@ async fn main() -> Result<()> {
                                 schema: postgres_config["schema"].as_str().unwrap_or("public").to_string(),
                                 batch_size: postgres_config["batch_size"].as_u64().unwrap_or(100) as usize,
                                 flush_interval: postgres_config["flush_interval"].as_u64().unwrap_or(1000),
+                                pool_size: postgres_config["pool_size"].as_u64().unwrap_or(4) as usize,
+                                ca_cert_path: postgres_config["ca_cert_path"].as_str().map(str::to_string),
+                                key_column: postgres_config["key_column"].as_str().unwrap_or("id").to_string(),
+                                key_columns: postgres_config["key_columns"].as_object()
+                                    .map(|columns| columns.iter()
+                                        .filter_map(|(table, column)| Some((table.clone(), column.as_str()?.to_string())))
+                                        .collect())
+                                    .unwrap_or_default(),
                             }
                         }
                         "file" => {
@@ -173,14 +328,24 @@ async fn main() -> Result<()> {
                     SinkConfig::Console { pretty_print: false }
                 };
                 
+                // The finalized sink configuration is a full sink configuration, including its type
+                let finalized_sink = if let Some(finalized_sink_path) = finalized_sink_config {
+                    let finalized_sink_str = std::fs::read_to_string(finalized_sink_path)?;
+                    Some(serde_json::from_str::<SinkConfig>(&finalized_sink_str)?)
+                } else {
+                    None
+                };
+                
                 Config {
-                    metashrew: debshrew::config::MetashrewConfig {
-                        url: metashrew_url,
-                        username: None,
-                        password: None,
-                        timeout: 30,
-                        max_retries: 3,
-                        retry_delay: 1000,
+                    metashrew: MetashrewConfig {
+                        endpoints: metashrew_endpoints,
+                        strategy: if metashrew_quorum.is_some() {
+                            EndpointStrategy::Quorum
+                        } else {
+                            EndpointStrategy::Failover
+                        },
+                        quorum: metashrew_quorum,
+                        ..MetashrewConfig::new(&metashrew_url)
                     },
                     transform: debshrew::config::TransformConfig {
                         path: transform_path.to_string_lossy().to_string(),
@@ -188,6 +353,31 @@ async fn main() -> Result<()> {
                     sink: sink_config,
                     cache_size,
                     start_height,
+                    end_height,
+                    checkpoint_path: checkpoint_path.map(|path| path.to_string_lossy().to_string()),
+                    polling_interval,
+                    tip_stream_url,
+                    prefetch_depth,
+                    prefetch_views,
+                    finality_depth,
+                    finalized_sink,
+                    control_messages,
+                    http_listen_address,
+                    admin_api,
+                    admin_token: None,
+                    admin_token_env: admin_api.then_some(admin_token_env),
+                    pipeline_name,
+                    metrics_path,
+                    error_policy: ErrorPolicyConfig {
+                        on_transform_failure: if dead_letter_path.is_some() {
+                            TransformFailureAction::DeadLetter
+                        } else {
+                            TransformFailureAction::Halt
+                        },
+                        dead_letter_path: dead_letter_path.map(|path| path.to_string_lossy().to_string()),
+                        max_retries: max_retries.or(Some(DEFAULT_MAX_RETRIES)),
+                        ..ErrorPolicyConfig::default()
+                    },
                     log_level,
                 }
             };
@@ -195,9 +385,31 @@ async fn main() -> Result<()> {
             // Validate configuration
             config.validate()?;
             
-            // Create metashrew client
-            info!("Connecting to metashrew at {}", config.metashrew.url);
-            let client = JsonRpcClient::from_config(&config.metashrew)?;
+            // Create metashrew client, or serve a recorded run from its fixture
+            let client: Arc<dyn MetashrewClientLike> = if let Some(path) = &replay_fixture {
+                info!("Replaying metashrew exchanges from {}", path.display());
+                Arc::new(ReplayClient::load(path)?)
+            } else {
+                info!("Connecting to metashrew at {}", config.metashrew.urls().join(", "));
+                let client = MultiEndpointClient::from_config(&config.metashrew)?;
+                match &record_fixture {
+                    Some(path) => Arc::new(RecordingClient::new(client, path)?),
+                    None => Arc::new(client),
+                }
+            };
+            
+            // Inject faults for a chaos run, outside the recorder so fixtures only hold real answers
+            let faults = match &chaos_config {
+                Some(path) => {
+                    info!("Injecting faults from {}", path.display());
+                    Some(FaultConfig::from_file(path)?)
+                }
+                None => None,
+            };
+            let client: Arc<dyn MetashrewClientLike> = match &faults {
+                Some(faults) => Arc::new(FaultyClient::new(client, faults.clone())),
+                None => client,
+            };
             
             // Load transform module
             info!("Loading transform module from {}", config.transform.path);
@@ -205,47 +417,183 @@ async fn main() -> Result<()> {
             
             // Create CDC sink
             info!("Creating CDC sink");
-            let sink = create_sink(&config.sink)?;
+            let mut sink = create_sink(&config.sink)?;
+            if let Some(faults) = &faults {
+                // A different seed keeps the sink's faults independent of metashrew's
+                sink = Box::new(FaultySink::new(sink, FaultConfig { seed: faults.seed.wrapping_add(1), ..faults.clone() }));
+            }
             
             // Create block synchronizer
             info!("Creating block synchronizer with cache size {}", config.cache_size);
             let mut synchronizer = BlockSynchronizer::new(client, runtime, sink, config.cache_size)?;
             
-            // Set starting height if provided
+            // Set starting height if provided, otherwise resume from the checkpoint
+            let checkpoint_store = config.checkpoint_path.as_ref().map(CheckpointStore::new);
             if let Some(height) = config.start_height {
                 info!("Setting starting height to {}", height);
                 synchronizer.set_starting_height(height);
+            } else if let Some(checkpoint) = checkpoint_store.as_ref().map(|store| store.load()).transpose()?.flatten() {
+                info!("Resuming after checkpoint at height {}", checkpoint.height);
+                synchronizer.set_starting_height(checkpoint.height);
+            }
+            
+            if let Some(store) = checkpoint_store {
+                synchronizer.set_checkpoint_store(store);
+            }
+            
+            // Stop after the end height for bounded runs
+            if let Some(height) = config.end_height {
+                info!("Setting end height to {}", height);
+                synchronizer.set_end_height(height);
+            }
+            
+            // Wake on pushed tip events if a stream is configured, otherwise poll
+            synchronizer.set_polling_interval(config.polling_interval);
+            if let Some(url) = &config.tip_stream_url {
+                info!("Subscribing to tip events at {}", url);
+                synchronizer.set_block_source(Box::new(StreamBlockSource::new(url, config.polling_interval)?));
+            }
+            
+            // Configure the catch-up prefetch stage
+            synchronizer.set_prefetch_depth(config.prefetch_depth);
+            synchronizer.set_prefetch_views(config.prefetch_views);
+            
+            // Hold back blocks until they are final
+            if config.finality_depth > 0 {
+                info!("Emitting CDC messages after {} confirmations", config.finality_depth);
+                synchronizer.set_finality_depth(config.finality_depth);
+            }
+            
+            // Attach the finalized sink, turning the main sink into a tentative stream
+            if let Some(finalized_sink_config) = &config.finalized_sink {
+                info!("Creating finalized CDC sink");
+                synchronizer.set_finalized_sink(create_sink(finalized_sink_config)?);
+            }
+            
+            // Frame blocks and reorgs with control messages
+            synchronizer.set_control_messages(config.control_messages);
+            
+            // Retry transient errors, and dead-letter failing blocks if configured
+            synchronizer.set_retry_policy(config.error_policy.retry_policy());
+            if let Some(queue) = config.error_policy.dead_letter_queue() {
+                info!("Writing blocks the transform fails on to {}", queue.path().display());
+                synchronizer.set_dead_letter_queue(queue);
+            }
+            
+            // Serve health, readiness, status and metrics from the live synchronizer
+            if let Some(address) = &config.http_listen_address {
+                let address = address.parse()
+                    .map_err(|e| format!("Invalid HTTP listen address {}: {}", address, e))?;
+                let client: Arc<dyn MetashrewClientLike> = synchronizer.get_client();
+                let mut server = StatusServer::new(synchronizer.status_handle(), client);
+                server.add_sink("sink", synchronizer.get_sink());
+                if let Some(finalized_sink) = synchronizer.get_finalized_sink() {
+                    server.add_sink("finalized_sink", finalized_sink);
+                }
+                
+                info!("Serving metrics for pipeline {} on {}", config.pipeline_name, config.metrics_path);
+                server.set_metrics(&config.metrics_path, metrics::install_recorder(&config.pipeline_name)?);
+                if config.admin_api {
+                    let token = config.resolve_admin_token()?
+                        .ok_or("The admin API requires an admin token")?;
+                    info!("Serving the admin API on {}", address);
+                    server.set_admin(synchronizer.admin_handle(), &token);
+                }
+                server.spawn(address)?;
             }
             
             // Run the synchronizer
             info!("Starting block synchronization");
             
-            // Handle Ctrl+C
-            let (shutdown_tx, mut shutdown_rx) = tokio::sync::oneshot::channel();
-            
+            // Stop gracefully on SIGINT or SIGTERM, finishing the block in flight;
+            // a second signal exits at once, e.g. when a sink flush hangs
+            let stop_handle = synchronizer.stop_handle();
             tokio::spawn(async move {
-                signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
-                info!("Received Ctrl+C, shutting down...");
-                let _ = shutdown_tx.send(());
+                shutdown_signal().await;
+                info!("Shutting down synchronizer after the current block; signal again to exit immediately");
+                stop_handle.stop();
+                
+                shutdown_signal().await;
+                warn!("Exiting without shutting down the synchronizer cleanly");
+                std::process::exit(1);
             });
             
-            // Run the synchronizer until shutdown signal
+            // Run the synchronizer until it is stopped or reaches the end height;
+            // it flushes and closes the sinks itself, also when it fails
+            if let Err(e) = synchronizer.run().await {
+                error!("Synchronizer error: {}", e);
+                return Err(e);
+            }
+            
+            info!("Debshrew service stopped");
+        }
+        
+        Commands::MockMetashrew {
+            scenario,
+            listen_address,
+            chaos_config,
+            log_level,
+        } => {
+            // Initialize logger
+            env_logger::Builder::from_env(Env::default().default_filter_or(&log_level)).init();
+            
+            // Load the scenario, or start from an empty chain
+            let scenario = match scenario {
+                Some(path) => {
+                    info!("Loading mock scenario from {}", path.display());
+                    MockScenario::from_file(path)?
+                }
+                None => MockScenario::default(),
+            };
+            
+            let address = listen_address.parse()
+                .map_err(|e| format!("Invalid listen address {}: {}", listen_address, e))?;
+            let mut server = MockMetashrewServer::new(scenario.build()?);
+            if let Some(path) = chaos_config {
+                info!("Injecting faults from {}", path.display());
+                server.set_faults(FaultConfig::from_file(path)?);
+            }
+            let (_, server) = server.spawn(address)?;
+            
+            // Serve until stopped
             tokio::select! {
-                result = synchronizer.run() => {
-                    if let Err(e) = result {
-                        error!("Synchronizer error: {}", e);
-                        return Err(e);
-                    }
+                result = server => {
+                    result.map_err(|e| format!("Mock metashrew task failed: {}", e))??;
                 }
-                _ = &mut shutdown_rx => {
-                    info!("Shutting down synchronizer");
-                    synchronizer.stop();
+                _ = shutdown_signal() => {
+                    info!("Mock metashrew stopped");
                 }
             }
-            
-            info!("Debshrew service stopped");
         }
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Wait for a shutdown signal
+///
+/// Resolves on SIGINT (Ctrl+C) and, on Unix, on SIGTERM as sent by
+/// `docker stop`.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM");
+        
+        tokio::select! {
+            result = signal::ctrl_c() => {
+                result.expect("Failed to listen for Ctrl+C");
+                info!("Received SIGINT");
+            }
+            _ = terminate.recv() => {
+                info!("Received SIGTERM");
+            }
+        }
+    }
+    
+    #[cfg(not(unix))]
+    {
+        signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
+        info!("Received Ctrl+C");
+    }
+}
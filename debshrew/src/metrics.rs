@@ -0,0 +1,300 @@
+//! Prometheus metrics
+//!
+//! This module defines the metrics recorded by the pipeline and installs the
+//! Prometheus recorder that renders them. Every series carries a `pipeline`
+//! label, so several pipelines can be scraped into the same Prometheus.
+//!
+//! Metrics are recorded through the `metrics` facade, which is a no-op until a
+//! recorder is installed, so library users that do not expose metrics pay
+//! nothing for them.
+
+use crate::error::{Error, Result};
+use crate::sink::CdcSink;
+use debshrew_support::{CdcMessage, CdcOperation};
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::time::{Duration, Instant};
+
+/// Number of blocks run through the transform
+pub const BLOCKS_PROCESSED: &str = "debshrew_blocks_processed_total";
+
+/// Time taken by the transform for a single block
+pub const TRANSFORM_DURATION: &str = "debshrew_transform_duration_seconds";
+
+/// Number of view calls made by the transform, by view and result source
+pub const VIEW_CALLS: &str = "debshrew_view_calls_total";
+
+/// Time taken by view calls served by metashrew, by view
+pub const VIEW_CALL_DURATION: &str = "debshrew_view_call_duration_seconds";
+
+/// Number of failed view calls, by view
+pub const VIEW_CALL_FAILURES: &str = "debshrew_view_call_failures_total";
+
+/// Number of CDC messages produced by the transform, by table and operation
+pub const CDC_MESSAGES: &str = "debshrew_cdc_messages_total";
+
+/// Time taken by sink sends, by stream
+pub const SINK_SEND_DURATION: &str = "debshrew_sink_send_duration_seconds";
+
+/// Number of failed sink sends, by stream
+pub const SINK_SEND_FAILURES: &str = "debshrew_sink_send_failures_total";
+
+/// Number of reorgs handled
+pub const REORGS: &str = "debshrew_reorgs_total";
+
+/// Number of blocks rolled back per reorg
+pub const REORG_DEPTH: &str = "debshrew_reorg_depth_blocks";
+
+/// Number of failed synchronization attempts, by kind (`transient` or `fatal`)
+pub const FAILURES: &str = "debshrew_failures_total";
+
+/// The number of consecutive failures at the failing block
+pub const CONSECUTIVE_FAILURES: &str = "debshrew_consecutive_failures";
+
+/// Number of blocks written to the dead-letter file
+pub const DEAD_LETTERS: &str = "debshrew_dead_letters_total";
+
+/// The last processed block height
+pub const CURRENT_HEIGHT: &str = "debshrew_current_height";
+
+/// The height metashrew reports as indexed
+pub const INDEXED_HEIGHT: &str = "debshrew_indexed_height";
+
+/// The number of indexed blocks not processed yet
+pub const TIP_LAG: &str = "debshrew_tip_lag_blocks";
+
+/// Whether a metashrew endpoint is healthy (1) or not (0), by endpoint
+pub const ENDPOINT_HEALTHY: &str = "debshrew_endpoint_healthy";
+
+/// The height a metashrew endpoint reports as indexed, by endpoint
+pub const ENDPOINT_HEIGHT: &str = "debshrew_endpoint_height";
+
+/// The number of blocks a metashrew endpoint is behind the highest endpoint, by endpoint
+pub const ENDPOINT_LAG: &str = "debshrew_endpoint_lag_blocks";
+
+/// Histogram buckets for durations, in seconds
+const DURATION_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Histogram buckets for reorg depths, in blocks
+const DEPTH_BUCKETS: &[f64] = &[1.0, 2.0, 3.0, 4.0, 6.0, 10.0, 20.0, 50.0, 100.0];
+
+/// Install the global Prometheus recorder
+///
+/// # Arguments
+///
+/// * `pipeline` - The pipeline name added as a label to every series
+///
+/// # Returns
+///
+/// A handle used to render the metrics in the Prometheus text format
+///
+/// # Errors
+///
+/// Returns an error if a recorder is already installed
+pub fn install_recorder(pipeline: &str) -> Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .add_global_label("pipeline", pipeline)
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), DURATION_BUCKETS)
+        .and_then(|builder| builder.set_buckets_for_metric(Matcher::Full(REORG_DEPTH.to_string()), DEPTH_BUCKETS))
+        .and_then(|builder| builder.install_recorder())
+        .map_err(|e| Error::Generic(format!("Failed to install metrics recorder: {}", e)))?;
+
+    describe();
+    Ok(handle)
+}
+
+/// Register the descriptions of every metric
+fn describe() {
+    describe_counter!(BLOCKS_PROCESSED, Unit::Count, "Number of blocks run through the transform");
+    describe_histogram!(TRANSFORM_DURATION, Unit::Seconds, "Time taken by the transform for a single block");
+    describe_counter!(VIEW_CALLS, Unit::Count, "Number of view calls made by the transform");
+    describe_histogram!(VIEW_CALL_DURATION, Unit::Seconds, "Time taken by view calls served by metashrew");
+    describe_counter!(VIEW_CALL_FAILURES, Unit::Count, "Number of failed view calls");
+    describe_counter!(CDC_MESSAGES, Unit::Count, "Number of CDC messages produced by the transform");
+    describe_histogram!(SINK_SEND_DURATION, Unit::Seconds, "Time taken by sink sends");
+    describe_counter!(SINK_SEND_FAILURES, Unit::Count, "Number of failed sink sends");
+    describe_counter!(REORGS, Unit::Count, "Number of reorgs handled");
+    describe_histogram!(REORG_DEPTH, Unit::Count, "Number of blocks rolled back per reorg");
+    describe_counter!(FAILURES, Unit::Count, "Number of failed synchronization attempts");
+    describe_gauge!(CONSECUTIVE_FAILURES, Unit::Count, "The number of consecutive failures at the failing block");
+    describe_counter!(DEAD_LETTERS, Unit::Count, "Number of blocks written to the dead-letter file");
+    describe_gauge!(CURRENT_HEIGHT, Unit::Count, "The last processed block height");
+    describe_gauge!(INDEXED_HEIGHT, Unit::Count, "The height metashrew reports as indexed");
+    describe_gauge!(TIP_LAG, Unit::Count, "The number of indexed blocks not processed yet");
+    describe_gauge!(ENDPOINT_HEALTHY, "Whether a metashrew endpoint is healthy");
+    describe_gauge!(ENDPOINT_HEIGHT, Unit::Count, "The height a metashrew endpoint reports as indexed");
+    describe_gauge!(ENDPOINT_LAG, Unit::Count, "The number of blocks a metashrew endpoint is behind the highest endpoint");
+}
+
+/// Record a transformed block
+///
+/// # Arguments
+///
+/// * `duration` - The time taken by the transform
+/// * `messages` - The CDC messages produced for the block
+pub fn record_transform(duration: Duration, messages: &[CdcMessage]) {
+    counter!(BLOCKS_PROCESSED, 1);
+    histogram!(TRANSFORM_DURATION, duration.as_secs_f64());
+
+    for message in messages {
+        counter!(
+            CDC_MESSAGES,
+            1,
+            "table" => message.payload.table.clone(),
+            "operation" => operation_label(&message.payload.operation)
+        );
+    }
+}
+
+/// Record a view call made by the transform
+///
+/// # Arguments
+///
+/// * `view` - The name of the view function
+/// * `duration` - The time taken by metashrew, or None if the result was prefetched
+/// * `success` - Whether the call succeeded
+pub fn record_view_call(view: &str, duration: Option<Duration>, success: bool) {
+    let source = if duration.is_some() { "metashrew" } else { "prefetch" };
+    counter!(VIEW_CALLS, 1, "view" => view.to_string(), "source" => source);
+
+    if let Some(duration) = duration {
+        histogram!(VIEW_CALL_DURATION, duration.as_secs_f64(), "view" => view.to_string());
+    }
+    if !success {
+        counter!(VIEW_CALL_FAILURES, 1, "view" => view.to_string());
+    }
+}
+
+/// Send CDC messages to a sink, recording the send latency and failures
+///
+/// # Arguments
+///
+/// * `sink` - The sink
+/// * `stream` - The stream the messages belong to (`tentative` or `finalized`)
+/// * `messages` - The CDC messages to send
+///
+/// # Returns
+///
+/// Ok(()) if the messages were sent successfully
+///
+/// # Errors
+///
+/// Returns the sink's error if the messages cannot be sent
+pub async fn send_to_sink(sink: &dyn CdcSink, stream: &'static str, messages: Vec<CdcMessage>) -> Result<()> {
+    let start = Instant::now();
+    let result = sink.send(messages).await;
+
+    histogram!(SINK_SEND_DURATION, start.elapsed().as_secs_f64(), "stream" => stream);
+    if result.is_err() {
+        counter!(SINK_SEND_FAILURES, 1, "stream" => stream);
+    }
+
+    result
+}
+
+/// Record a handled reorg
+///
+/// # Arguments
+///
+/// * `depth` - The number of blocks rolled back
+pub fn record_reorg(depth: u32) {
+    counter!(REORGS, 1);
+    histogram!(REORG_DEPTH, depth as f64);
+}
+
+/// Record a failed synchronization attempt
+///
+/// # Arguments
+///
+/// * `transient` - Whether the error is transient and will be retried
+/// * `consecutive_failures` - The number of consecutive failures at the failing block
+pub fn record_failure(transient: bool, consecutive_failures: u32) {
+    let kind = if transient { "transient" } else { "fatal" };
+    counter!(FAILURES, 1, "kind" => kind);
+    gauge!(CONSECUTIVE_FAILURES, consecutive_failures as f64);
+}
+
+/// Record that the synchronizer is no longer failing
+pub fn record_recovery() {
+    gauge!(CONSECUTIVE_FAILURES, 0.0);
+}
+
+/// Record a block written to the dead-letter file
+pub fn record_dead_letter() {
+    counter!(DEAD_LETTERS, 1);
+}
+
+/// Record the synchronizer's progress against the metashrew tip
+///
+/// # Arguments
+///
+/// * `current_height` - The last processed block height
+/// * `indexed_height` - The height metashrew reports as indexed, if known
+pub fn record_progress(current_height: u32, indexed_height: Option<u32>) {
+    gauge!(CURRENT_HEIGHT, current_height as f64);
+
+    if let Some(indexed_height) = indexed_height {
+        gauge!(INDEXED_HEIGHT, indexed_height as f64);
+        gauge!(TIP_LAG, indexed_height.saturating_sub(current_height) as f64);
+    }
+}
+
+/// Record the state of a metashrew endpoint
+///
+/// # Arguments
+///
+/// * `endpoint` - The endpoint identifier
+/// * `healthy` - Whether the endpoint answered the last height request
+/// * `height` - The height the endpoint last reported, if known
+/// * `lag` - The number of blocks the endpoint is behind the highest endpoint, if known
+pub fn record_endpoint(endpoint: &str, healthy: bool, height: Option<u32>, lag: Option<u32>) {
+    gauge!(ENDPOINT_HEALTHY, if healthy { 1.0 } else { 0.0 }, "endpoint" => endpoint.to_string());
+
+    if let Some(height) = height {
+        gauge!(ENDPOINT_HEIGHT, height as f64, "endpoint" => endpoint.to_string());
+    }
+    if let Some(lag) = lag {
+        gauge!(ENDPOINT_LAG, lag as f64, "endpoint" => endpoint.to_string());
+    }
+}
+
+/// Get the label value for a CDC operation
+fn operation_label(operation: &CdcOperation) -> &'static str {
+    match operation {
+        CdcOperation::Create => "create",
+        CdcOperation::Update => "update",
+        CdcOperation::Delete => "delete",
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::sink::NullSink;
+    use std::sync::OnceLock;
+
+    /// Get the recorder shared by every test, installing it on first use
+    pub(crate) fn test_handle() -> &'static PrometheusHandle {
+        static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+        HANDLE.get_or_init(|| install_recorder("test-pipeline").unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_rendered_with_pipeline_label() {
+        let handle = test_handle();
+
+        record_view_call("balances", Some(Duration::from_millis(3)), true);
+        record_view_call("balances", None, false);
+        record_reorg(2);
+        record_progress(90, Some(100));
+        send_to_sink(&NullSink::new(), "tentative", Vec::new()).await.unwrap();
+
+        let rendered = handle.render();
+        assert!(rendered.contains("debshrew_view_calls_total{pipeline=\"test-pipeline\""));
+        assert!(rendered.contains("source=\"prefetch\""));
+        assert!(rendered.contains("debshrew_view_call_failures_total"));
+        assert!(rendered.contains("debshrew_reorg_depth_blocks_bucket"));
+        assert!(rendered.contains("debshrew_sink_send_duration_seconds_bucket"));
+        assert!(rendered.contains("debshrew_tip_lag_blocks{pipeline=\"test-pipeline\"}"));
+    }
+}
@@ -0,0 +1,621 @@
+//! Mock metashrew JSON-RPC server
+//!
+//! This module serves `metashrew_height`, `metashrew_getblockhash` and
+//! `metashrew_view` over HTTP from a [`MemoryMetashrewAdapter`], so pipelines
+//! can be tested end to end without running an indexer. The chain is loaded
+//! from a [`MockScenario`] and driven through admin endpoints:
+//!
+//! - `POST /admin/advance`: mine blocks, optionally `{"count": 3}`
+//! - `POST /admin/reorg`: replace the blocks above a height, e.g.
+//!   `{"fork_height": 100, "length": 2}`
+//! - `POST /admin/view`: set a view result, in the scenario's view format
+//!
+//! Every other `POST` is handled as a JSON-RPC request or batch. With faults
+//! configured, requests are delayed, answered with `503 Service Unavailable`
+//! or, for permanent failures, `403 Forbidden`, or see stale heights and
+//! flapping block hashes.
+
+use crate::adapters::MemoryMetashrewAdapter;
+use crate::error::{Error, Result};
+use crate::faults::{FaultConfig, FaultInjector};
+use crate::server::json_response;
+use crate::traits::{BlockProviderLike, BlockchainSimulatorLike, ViewProviderLike};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// JSON-RPC error code for blocks and heights that are not indexed yet
+const NOT_INDEXED: i32 = -32000;
+
+/// JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// JSON-RPC error code for invalid parameters
+const INVALID_PARAMS: i32 = -32602;
+
+/// JSON-RPC error code for unparseable requests
+const PARSE_ERROR: i32 = -32700;
+
+/// Initial chain and view results of a mock metashrew
+///
+/// Blocks up to `height` without a hash in `block_hashes` get a deterministic
+/// hash, so a scenario only lists the hashes it cares about.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockScenario {
+    /// The indexed height, or None for the highest listed block hash
+    #[serde(default)]
+    pub height: Option<u32>,
+
+    /// Hex-encoded block hashes by height
+    #[serde(default)]
+    pub block_hashes: BTreeMap<u32, String>,
+
+    /// View results
+    #[serde(default)]
+    pub views: Vec<MockView>,
+}
+
+/// A view result served by a mock metashrew
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockView {
+    /// The view function name
+    pub view_name: String,
+
+    /// The hex-encoded view parameters
+    #[serde(default)]
+    pub params: String,
+
+    /// The height the result applies to, or None for every height
+    #[serde(default)]
+    pub height: Option<u32>,
+
+    /// The hex-encoded view result
+    pub result: String,
+}
+
+impl MockScenario {
+    /// Load a scenario from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The scenario file
+    ///
+    /// # Returns
+    ///
+    /// The scenario
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid scenario
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        serde_json::from_str(&contents).map_err(|e| {
+            Error::Configuration(format!("Invalid mock scenario {}: {}", path.as_ref().display(), e))
+        })
+    }
+
+    /// Build an in-memory metashrew from the scenario
+    ///
+    /// # Returns
+    ///
+    /// A memory adapter holding the scenario's chain and view results
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hex value is invalid
+    pub fn build(&self) -> Result<MemoryMetashrewAdapter> {
+        let mut adapter = MemoryMetashrewAdapter::with_identifier("mock-metashrew");
+
+        let height = self
+            .height
+            .or_else(|| self.block_hashes.keys().next_back().copied())
+            .unwrap_or(0);
+        if let Some(hash) = self.block_hashes.get(&0) {
+            adapter.set_block_hash(0, decode(hash, "block hash")?);
+        }
+        for block in 1..=height {
+            match self.block_hashes.get(&block) {
+                Some(hash) => {
+                    adapter.set_block_hash(block, decode(hash, "block hash")?);
+                    adapter.set_height(block);
+                }
+                None => {
+                    adapter.advance_block(None)?;
+                }
+            }
+        }
+
+        for view in &self.views {
+            view.apply(&adapter)?;
+        }
+
+        Ok(adapter)
+    }
+}
+
+impl MockView {
+    /// Set the view result on an adapter
+    fn apply(&self, adapter: &MemoryMetashrewAdapter) -> Result<()> {
+        let params = decode(&self.params, "view params")?;
+        let result = decode(&self.result, "view result")?;
+        adapter.set_view_result(&self.view_name, &params, self.height, result);
+        Ok(())
+    }
+}
+
+/// Decode a hex value, with or without a `0x` prefix
+fn decode(value: &str, what: &str) -> Result<Vec<u8>> {
+    hex::decode(value.strip_prefix("0x").unwrap_or(value))
+        .map_err(|e| Error::Configuration(format!("Invalid hex in {} '{}': {}", what, value, e)))
+}
+
+/// Body of an advance request
+#[derive(Debug, Deserialize)]
+struct AdvanceRequest {
+    /// The number of blocks to mine
+    #[serde(default = "default_advance_count")]
+    count: u32,
+}
+
+/// Get the default number of blocks mined per advance request
+fn default_advance_count() -> u32 {
+    1
+}
+
+/// Body of a reorg request
+#[derive(Debug, Deserialize)]
+struct ReorgRequest {
+    /// The last block kept from the current chain
+    fork_height: u32,
+
+    /// The number of blocks on the new chain, or None to replace as many as are rolled back
+    #[serde(default)]
+    length: Option<u32>,
+}
+
+/// A JSON-RPC error: code and message
+type RpcFailure = (i32, String);
+
+/// Mock metashrew JSON-RPC server
+///
+/// The server shares its adapter, so a test can also change the chain
+/// directly through [`MockMetashrewServer::adapter`].
+#[derive(Debug, Clone)]
+pub struct MockMetashrewServer {
+    /// The chain and view results being served
+    adapter: MemoryMetashrewAdapter,
+
+    /// The number of reorgs simulated so far, so every reorg produces new hashes
+    reorgs: Arc<AtomicU32>,
+
+    /// The faults injected into JSON-RPC requests, if any
+    faults: Option<FaultInjector>,
+}
+
+impl MockMetashrewServer {
+    /// Create a new mock metashrew server
+    ///
+    /// # Arguments
+    ///
+    /// * `adapter` - The chain and view results to serve
+    ///
+    /// # Returns
+    ///
+    /// A new mock metashrew server
+    pub fn new(adapter: MemoryMetashrewAdapter) -> Self {
+        Self {
+            adapter,
+            reorgs: Arc::new(AtomicU32::new(0)),
+            faults: None,
+        }
+    }
+
+    /// Inject faults into JSON-RPC requests
+    ///
+    /// Admin requests are never faulted.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The faults to inject
+    pub fn set_faults(&mut self, config: FaultConfig) {
+        self.faults = Some(FaultInjector::new(config));
+    }
+
+    /// Get the adapter being served
+    ///
+    /// # Returns
+    ///
+    /// The adapter, sharing its state with the server
+    pub fn adapter(&self) -> &MemoryMetashrewAdapter {
+        &self.adapter
+    }
+
+    /// Start serving in the background
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to listen on (port 0 picks a free port)
+    ///
+    /// # Returns
+    ///
+    /// The address the server is listening on and the server task
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be bound
+    pub fn spawn(self, address: SocketAddr) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let builder = Server::try_bind(&address)
+            .map_err(|e| Error::Server(format!("Failed to bind {}: {}", address, e)))?;
+
+        let server = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let server = server.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.handle(request).await) }
+                }))
+            }
+        });
+
+        let http = builder.serve(make_service);
+        let local_address = http.local_addr();
+        info!("Mock metashrew listening on {}", local_address);
+
+        let handle = tokio::spawn(async move {
+            http.await
+                .map_err(|e| Error::Server(format!("Mock metashrew failed: {}", e)))
+        });
+
+        Ok((local_address, handle))
+    }
+
+    /// Handle a request
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        debug!("{} {}", request.method(), request.uri().path());
+
+        if request.method() != Method::POST {
+            return json_response(StatusCode::METHOD_NOT_ALLOWED, &json!({ "error": "method not allowed" }));
+        }
+
+        let path = request.uri().path().to_string();
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body,
+            Err(e) => return json_response(StatusCode::BAD_REQUEST, &json!({ "error": e.to_string() })),
+        };
+
+        match path.as_str() {
+            "/admin/advance" => self.handle_advance(&body),
+            "/admin/reorg" => self.handle_reorg(&body),
+            "/admin/view" => self.handle_view(&body),
+            path if path.starts_with("/admin/") => {
+                json_response(StatusCode::NOT_FOUND, &json!({ "error": "not found" }))
+            }
+            _ => self.handle_rpc(&body).await,
+        }
+    }
+
+    /// Mine blocks
+    fn handle_advance(&self, body: &[u8]) -> Response<Body> {
+        let count = if body.is_empty() {
+            default_advance_count()
+        } else {
+            match serde_json::from_slice::<AdvanceRequest>(body) {
+                Ok(advance) => advance.count,
+                Err(e) => return bad_request(format!("Invalid advance request: {}", e)),
+            }
+        };
+
+        let mut adapter = self.adapter.clone();
+        let mut tip = None;
+        for _ in 0..count {
+            match adapter.advance_block(None) {
+                Ok(block) => tip = Some(block),
+                Err(e) => return json_response(StatusCode::CONFLICT, &json!({ "error": e.to_string() })),
+            }
+        }
+
+        match tip {
+            Some((height, hash)) => {
+                info!("Mock metashrew advanced to height {}", height);
+                json_response(StatusCode::OK, &json!({ "height": height, "hash": hex::encode(hash) }))
+            }
+            None => bad_request("Advance count must be greater than 0".to_string()),
+        }
+    }
+
+    /// Replace the blocks above a fork height
+    fn handle_reorg(&self, body: &[u8]) -> Response<Body> {
+        let reorg = match serde_json::from_slice::<ReorgRequest>(body) {
+            Ok(reorg) => reorg,
+            Err(e) => return bad_request(format!("Invalid reorg request: {}", e)),
+        };
+
+        let mut adapter = self.adapter.clone();
+        let tip = adapter.current_height();
+        let length = reorg.length.unwrap_or_else(|| tip.saturating_sub(reorg.fork_height));
+
+        // Distinct block data makes repeated reorgs at the same height produce new hashes
+        let reorg_number = self.reorgs.fetch_add(1, Ordering::Relaxed);
+        let blocks = (0..length)
+            .map(|index| format!("reorg {} block {}", reorg_number, index).into_bytes())
+            .collect();
+
+        match adapter.simulate_reorg(reorg.fork_height, blocks) {
+            Ok((height, hash)) => {
+                info!("Mock metashrew reorganized above height {} to height {}", reorg.fork_height, height);
+                json_response(StatusCode::OK, &json!({ "height": height, "hash": hex::encode(hash) }))
+            }
+            Err(e) => json_response(StatusCode::CONFLICT, &json!({ "error": e.to_string() })),
+        }
+    }
+
+    /// Set a view result
+    fn handle_view(&self, body: &[u8]) -> Response<Body> {
+        let view = match serde_json::from_slice::<MockView>(body) {
+            Ok(view) => view,
+            Err(e) => return bad_request(format!("Invalid view request: {}", e)),
+        };
+
+        match view.apply(&self.adapter) {
+            Ok(()) => json_response(StatusCode::OK, &json!({ "status": "ok" })),
+            Err(e) => bad_request(e.to_string()),
+        }
+    }
+
+    /// Answer a JSON-RPC request or batch
+    async fn handle_rpc(&self, body: &[u8]) -> Response<Body> {
+        if let Some(faults) = &self.faults {
+            if let Err(e) = faults.inject("JSON-RPC request", Error::MetashrewClient, Error::Authentication).await {
+                let status = if e.is_transient() { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::FORBIDDEN };
+                return json_response(status, &json!({ "error": e.to_string() }));
+            }
+        }
+
+        let request = match serde_json::from_slice::<Value>(body) {
+            Ok(request) => request,
+            Err(e) => return json_response(StatusCode::OK, &rpc_response(Value::Null, Err((PARSE_ERROR, e.to_string())))),
+        };
+
+        match request {
+            Value::Array(requests) => {
+                let mut responses = Vec::with_capacity(requests.len());
+                for request in requests {
+                    responses.push(self.answer(&request).await);
+                }
+                json_response(StatusCode::OK, &responses)
+            }
+            request => json_response(StatusCode::OK, &self.answer(&request).await),
+        }
+    }
+
+    /// Answer a single JSON-RPC request
+    async fn answer(&self, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+        let params = request.get("params").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        debug!("Mock metashrew request {} ({})", id, method);
+
+        let result = match method {
+            "metashrew_height" => self.height().await,
+            "metashrew_getblockhash" => self.block_hash(params).await,
+            "metashrew_view" => self.view(params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+        rpc_response(id, result)
+    }
+
+    /// Answer `metashrew_height`
+    async fn height(&self) -> std::result::Result<String, RpcFailure> {
+        let height = self.adapter.current_height();
+        let height = match &self.faults {
+            Some(faults) => faults.stale_height(height),
+            None => height,
+        };
+        Ok(height.to_string())
+    }
+
+    /// Answer `metashrew_getblockhash`
+    async fn block_hash(&self, params: &[Value]) -> std::result::Result<String, RpcFailure> {
+        let height = params
+            .first()
+            .and_then(Value::as_u64)
+            .and_then(|height| u32::try_from(height).ok())
+            .ok_or_else(|| (INVALID_PARAMS, "Expected a block height".to_string()))?;
+
+        self.adapter
+            .get_block_hash(height)
+            .await
+            .map(|hash| match &self.faults {
+                Some(faults) => faults.flapping_hash(height, hash),
+                None => hash,
+            })
+            .map(|hash| format!("0x{}", hex::encode(hash)))
+            .map_err(|_| (NOT_INDEXED, format!("Block hash not found for height {}", height)))
+    }
+
+    /// Answer `metashrew_view`
+    ///
+    /// A result set for the requested height takes precedence over one set
+    /// for every height.
+    async fn view(&self, params: &[Value]) -> std::result::Result<String, RpcFailure> {
+        let (view_name, view_params) = match params {
+            [Value::String(view_name), Value::String(view_params), ..] => (view_name, view_params),
+            _ => return Err((INVALID_PARAMS, "Expected a view name and hex-encoded parameters".to_string())),
+        };
+        let view_params = hex::decode(view_params.strip_prefix("0x").unwrap_or(view_params))
+            .map_err(|e| (INVALID_PARAMS, format!("Invalid view parameters: {}", e)))?;
+
+        let tip = self.adapter.current_height();
+        let height = match params.get(2) {
+            None => tip,
+            Some(Value::String(height)) if height == "latest" => tip,
+            Some(height) => height
+                .as_u64()
+                .and_then(|height| u32::try_from(height).ok())
+                .ok_or_else(|| (INVALID_PARAMS, format!("Invalid view height {}", height)))?,
+        };
+        if height > tip {
+            return Err((NOT_INDEXED, format!("Height {} is not yet indexed", height)));
+        }
+
+        let result = match self.adapter.call_view(view_name, &view_params, Some(height)).await {
+            Ok(result) => Ok(result),
+            Err(_) => self.adapter.call_view(view_name, &view_params, None).await,
+        };
+        result
+            .map(|result| format!("0x{}", hex::encode(result)))
+            .map_err(|_| {
+                (INVALID_PARAMS, format!("No result for view '{}' with params {} at height {}", view_name, hex::encode(&view_params), height))
+            })
+    }
+}
+
+/// Build a JSON-RPC response
+fn rpc_response(id: Value, result: std::result::Result<String, RpcFailure>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err((code, message)) => json!({ "jsonrpc": "2.0", "error": { "code": code, "message": message }, "id": id }),
+    }
+}
+
+/// Build a 400 response
+fn bad_request(error: String) -> Response<Body> {
+    json_response(StatusCode::BAD_REQUEST, &json!({ "error": error }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::JsonRpcClient;
+    use crate::tip::is_block_not_available;
+    use crate::traits::ViewCall;
+
+    fn spawn_mock(scenario: &MockScenario) -> (String, JsonRpcClient) {
+        let server = MockMetashrewServer::new(scenario.build().unwrap());
+        let (address, _handle) = server.spawn("127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("http://{}", address);
+        let client = JsonRpcClient::new(&url).unwrap();
+        (url, client)
+    }
+
+    fn scenario() -> MockScenario {
+        serde_json::from_value(json!({
+            "height": 3,
+            "block_hashes": { "2": "0x0202" },
+            "views": [
+                { "view_name": "balance", "params": "07", "result": "2a" },
+                { "view_name": "balance", "params": "07", "height": 3, "result": "2b" }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mock_metashrew_serves_scenario() {
+        let (_url, client) = spawn_mock(&scenario());
+
+        assert_eq!(client.get_height().await.unwrap(), 3);
+        assert_eq!(client.get_block_hash(2).await.unwrap(), vec![2, 2]);
+        let hashes = client.get_block_hashes(1..=3).await.unwrap();
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(hashes[1], vec![2, 2]);
+        assert_ne!(hashes[0], hashes[2]);
+
+        // Blocks above the tip are not indexed yet
+        let error = client.get_block_hash(4).await.unwrap_err();
+        assert!(is_block_not_available(&error));
+
+        // A result for the height wins over one for every height
+        assert_eq!(client.call_view("balance", &[7], Some(2)).await.unwrap(), vec![0x2a]);
+        assert_eq!(client.call_view("balance", &[7], Some(3)).await.unwrap(), vec![0x2b]);
+        assert_eq!(client.call_view("balance", &[7], None).await.unwrap(), vec![0x2b]);
+
+        let results = client
+            .call_views(&[ViewCall::new("balance", &[7], Some(1)), ViewCall::new("balance", &[8], Some(1))])
+            .await
+            .unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &vec![0x2a]);
+        assert!(results[1].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_metashrew_admin_endpoints() {
+        let (url, client) = spawn_mock(&scenario());
+        let http = reqwest::Client::new();
+        let old_hashes = client.get_block_hashes(1..=3).await.unwrap();
+
+        let response = http.post(format!("{}/admin/advance", url)).json(&json!({ "count": 2 })).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(client.get_height().await.unwrap(), 5);
+
+        // Replacing the blocks above height 1 keeps the chain length by default
+        let response = http.post(format!("{}/admin/reorg", url)).json(&json!({ "fork_height": 1 })).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(client.get_height().await.unwrap(), 5);
+        let new_hashes = client.get_block_hashes(1..=3).await.unwrap();
+        assert_eq!(new_hashes[0], old_hashes[0]);
+        assert_ne!(new_hashes[1], old_hashes[1]);
+
+        // A second reorg at the same height produces new blocks again
+        http.post(format!("{}/admin/reorg", url)).json(&json!({ "fork_height": 1, "length": 1 })).send().await.unwrap();
+        assert_eq!(client.get_height().await.unwrap(), 2);
+        assert_ne!(client.get_block_hash(2).await.unwrap(), new_hashes[1]);
+
+        let response = http.post(format!("{}/admin/reorg", url)).json(&json!({ "fork_height": 10 })).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+
+        let view = json!({ "view_name": "supply", "result": "ff" });
+        http.post(format!("{}/admin/view", url)).json(&view).send().await.unwrap();
+        assert_eq!(client.call_view("supply", &[], Some(2)).await.unwrap(), vec![0xff]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_metashrew_injects_faults() {
+        let mut server = MockMetashrewServer::new(scenario().build().unwrap());
+        server.set_faults(FaultConfig { error_rate: 1.0, ..FaultConfig::default() });
+        let (address, _handle) = server.spawn("127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("http://{}", address);
+        let http = reqwest::Client::new();
+
+        let request = json!({ "jsonrpc": "2.0", "method": "metashrew_height", "params": [], "id": 0 });
+        let response = http.post(&url).json(&request).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+        // Admin requests still go through
+        let response = http.post(format!("{}/admin/advance", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        // Permanent failures are answered the way rejected credentials are
+        let mut server = MockMetashrewServer::new(scenario().build().unwrap());
+        server.set_faults(FaultConfig { permanent_error_rate: 1.0, ..FaultConfig::default() });
+        let (address, _handle) = server.spawn("127.0.0.1:0".parse().unwrap()).unwrap();
+        let response = http.post(format!("http://{}", address)).json(&request).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_mock_scenario_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scenario.json");
+        std::fs::write(&path, r#"{"block_hashes": {"1": "01", "4": "04"}}"#).unwrap();
+
+        let adapter = MockScenario::from_file(&path).unwrap().build().unwrap();
+        assert_eq!(adapter.current_height(), 4);
+        assert_eq!(adapter.block_count(), 4);
+
+        std::fs::write(&path, r#"{"block_hashes": {"1": "xyz"}}"#).unwrap();
+        assert!(MockScenario::from_file(&path).unwrap().build().is_err());
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(MockScenario::from_file(&path), Err(Error::Configuration(_))));
+    }
+}
@@ -0,0 +1,60 @@
+//! Synchronizer lifecycle hooks
+//!
+//! This module defines the observer interface for applications that embed a
+//! [`BlockSynchronizer`](crate::BlockSynchronizer) and need to react to its
+//! events, such as refreshing their own caches when a block is processed or a
+//! reorg rolls blocks back.
+//!
+//! Hooks are called inline on the synchronization task, in the order the
+//! events happen, so they should return quickly; slow work belongs on a
+//! separate task.
+
+use crate::error::Error;
+use debshrew_support::CdcMessage;
+
+/// Observer of synchronizer events
+///
+/// Every hook has an empty default implementation, so observers only
+/// implement the events they care about.
+pub trait SyncObserver: Send + Sync {
+    /// Called once a block has been processed
+    ///
+    /// A block is processed once it was transformed and its CDC messages were
+    /// handed to the sink, or held back for the finality depth. Blocks
+    /// replayed after a reorg are reported again.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height
+    /// * `hash` - The block hash, hex-encoded
+    /// * `messages` - The CDC messages the transform produced for the block
+    fn on_block_processed(&self, _height: u32, _hash: &str, _messages: &[CdcMessage]) {}
+
+    /// Called once a reorg has been rolled back, before the new chain is processed
+    ///
+    /// # Arguments
+    ///
+    /// * `fork_height` - The common ancestor the synchronizer rolled back to
+    /// * `old_tip` - The last processed block height before the reorg
+    /// * `new_tip` - The height the new chain is processed up to
+    fn on_reorg(&self, _fork_height: u32, _old_tip: u32, _new_tip: u32) {}
+
+    /// Called whenever the synchronizer encounters an error
+    ///
+    /// This includes transient errors that are retried, so an error is not
+    /// necessarily fatal.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error
+    fn on_error(&self, _error: &Error) {}
+
+    /// Called when a synchronization step processed new blocks and reached the metashrew tip
+    ///
+    /// While following the tip, this is called after every new block.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height the synchronizer caught up to
+    fn on_caught_up(&self, _height: u32) {}
+}
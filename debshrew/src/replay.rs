@@ -0,0 +1,610 @@
+//! Recording and replaying metashrew exchanges
+//!
+//! This module provides a client wrapper that records every exchange with
+//! metashrew to a fixture file, and a client that serves a run from such a
+//! fixture without contacting metashrew. A transform's output depends on the
+//! view results it saw, so a recorded fixture reproduces a production run
+//! offline, in CI or in a regression test.
+//!
+//! A fixture is a newline-delimited JSON file with one exchange per line and
+//! binary values hex-encoded. The recorder appends each new answer as it
+//! happens, so a fixture survives a crash of the recorded run. A request can
+//! appear more than once, e.g. the height as the tip advances or a block hash
+//! replaced by a reorg, and the replay serves its answers in recording order.
+
+use crate::error::{Error, Result};
+use crate::tip::is_block_not_available;
+use crate::traits::{BlockProviderLike, MetashrewClientLike, ViewCall, ViewProviderLike};
+use async_trait::async_trait;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A single exchange with metashrew, as stored in a fixture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Exchange {
+    /// A height request
+    Height {
+        /// The height metashrew reported
+        height: u32,
+    },
+
+    /// A block hash request
+    BlockHash {
+        /// The requested height
+        height: u32,
+
+        /// The hex-encoded block hash, or None if metashrew did not have the block yet
+        hash: Option<String>,
+    },
+
+    /// A view call
+    View {
+        /// The view function name
+        view_name: String,
+
+        /// The hex-encoded view parameters
+        params: String,
+
+        /// The height the view was called at
+        height: Option<u32>,
+
+        /// The hex-encoded view result
+        result: String,
+    },
+}
+
+/// Key of a recorded view call: view name, parameters and height
+type ViewKey = (String, Vec<u8>, Option<u32>);
+
+/// What an exchange asked metashrew
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Request {
+    /// The height
+    Height,
+
+    /// The block hash at a height
+    BlockHash(u32),
+
+    /// A view call
+    View(ViewKey),
+}
+
+/// What metashrew answered
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Answer {
+    /// The height
+    Height(u32),
+
+    /// The block hash, or None if metashrew did not have the block yet
+    BlockHash(Option<Vec<u8>>),
+
+    /// The view result
+    View(Vec<u8>),
+}
+
+/// An answer as recorded in a fixture
+#[derive(Debug)]
+struct Recorded {
+    /// What metashrew answered
+    answer: Answer,
+
+    /// Whether the answer replaced an earlier answer to the same request
+    replaces: bool,
+}
+
+/// The exchanges of a fixture, in recording order
+#[derive(Debug, Default)]
+struct Fixture {
+    /// Every distinct answer, in the order it was recorded
+    answers: Vec<Recorded>,
+
+    /// Positions in `answers` of the answers to each request, in recording order
+    history: HashMap<Request, Vec<usize>>,
+}
+
+impl Fixture {
+    /// Load a fixture file
+    fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::File(format!("Failed to read fixture {}: {}", path.display(), e)))?;
+
+        let mut fixture = Self::default();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange = serde_json::from_str(line).map_err(|e| {
+                Error::Replay(format!("Invalid exchange on line {} of {}: {}", index + 1, path.display(), e))
+            })?;
+            fixture.apply(exchange).map_err(|e| {
+                Error::Replay(format!("Invalid exchange on line {} of {}: {}", index + 1, path.display(), e))
+            })?;
+        }
+
+        Ok(fixture)
+    }
+
+    /// Apply an exchange
+    ///
+    /// Returns whether the exchange was added, i.e. whether its answer differs
+    /// from the last one recorded for the same request.
+    fn apply(&mut self, exchange: Exchange) -> Result<bool> {
+        let (request, answer) = match exchange {
+            Exchange::Height { height } => (Request::Height, Answer::Height(height)),
+            Exchange::BlockHash { height, hash } => {
+                let hash = hash.map(|hash| decode(&hash, "block hash")).transpose()?;
+                (Request::BlockHash(height), Answer::BlockHash(hash))
+            }
+            Exchange::View { view_name, params, height, result } => {
+                let key = (view_name, decode(&params, "view params")?, height);
+                (Request::View(key), Answer::View(decode(&result, "view result")?))
+            }
+        };
+
+        let positions = self.history.entry(request).or_default();
+        if positions.last().is_some_and(|&position| self.answers[position].answer == answer) {
+            return Ok(false);
+        }
+
+        let replaces = !positions.is_empty();
+        positions.push(self.answers.len());
+        self.answers.push(Recorded { answer, replaces });
+        Ok(true)
+    }
+
+    /// Count the distinct requests matching a predicate
+    fn count_requests(&self, predicate: impl Fn(&Request) -> bool) -> usize {
+        self.history.keys().filter(|request| predicate(request)).count()
+    }
+}
+
+/// Decode a hex value from a fixture
+fn decode(value: &str, what: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|e| Error::Replay(format!("Invalid hex in {}: {}", what, e)))
+}
+
+/// Client wrapper that records every exchange with metashrew to a fixture
+///
+/// All calls are forwarded to the inner client. Successful answers, and
+/// block hash requests for blocks metashrew does not have yet, are appended
+/// to the fixture unless the same answer is already recorded. Recording to an
+/// existing fixture extends it.
+#[derive(Debug)]
+pub struct RecordingClient<C: MetashrewClientLike> {
+    /// The recorded client
+    inner: C,
+
+    /// The fixture path
+    path: PathBuf,
+
+    /// The recorded exchanges and the open fixture file
+    recording: Mutex<(Fixture, File)>,
+}
+
+impl<C: MetashrewClientLike> RecordingClient<C> {
+    /// Create a new recording client
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The client to record
+    /// * `path` - The fixture path, created if it does not exist
+    ///
+    /// # Returns
+    ///
+    /// A new recording client
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an existing fixture cannot be read or the file cannot be opened
+    pub fn new(inner: C, path: &Path) -> Result<Self> {
+        let fixture = if path.exists() { Fixture::load(path)? } else { Fixture::default() };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::File(format!("Failed to open fixture {}: {}", path.display(), e)))?;
+
+        info!("Recording metashrew exchanges to {}", path.display());
+
+        Ok(Self {
+            inner,
+            path: path.to_path_buf(),
+            recording: Mutex::new((fixture, file)),
+        })
+    }
+
+    /// Get the recorded client
+    ///
+    /// # Returns
+    ///
+    /// The recorded client
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Record an exchange unless it is already recorded
+    fn record(&self, exchange: Exchange) -> Result<()> {
+        let mut recording = match self.recording.lock() {
+            Ok(recording) => recording,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let (fixture, file) = &mut *recording;
+
+        if !fixture.apply(exchange.clone())? {
+            return Ok(());
+        }
+
+        let line = serde_json::to_string(&exchange)?;
+        writeln!(file, "{}", line)
+            .map_err(|e| Error::File(format!("Failed to write fixture {}: {}", self.path.display(), e)))
+    }
+
+    /// Record a block hash answer
+    fn record_block_hash(&self, height: u32, result: &Result<Vec<u8>>) -> Result<()> {
+        let hash = match result {
+            Ok(hash) => Some(hex::encode(hash)),
+            Err(e) if is_block_not_available(e) => None,
+            Err(_) => return Ok(()),
+        };
+        self.record(Exchange::BlockHash { height, hash })
+    }
+
+    /// Record a view call answer
+    fn record_view(&self, view_name: &str, params: &[u8], height: Option<u32>, result: &[u8]) -> Result<()> {
+        self.record(Exchange::View {
+            view_name: view_name.to_string(),
+            params: hex::encode(params),
+            height,
+            result: hex::encode(result),
+        })
+    }
+}
+
+#[async_trait]
+impl<C: MetashrewClientLike> BlockProviderLike for RecordingClient<C> {
+    async fn get_height(&self) -> Result<u32> {
+        let height = self.inner.get_height().await?;
+        self.record(Exchange::Height { height })?;
+        Ok(height)
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+        let result = self.inner.get_block_hash(height).await;
+        self.record_block_hash(height, &result)?;
+        result
+    }
+
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        let hashes = self.inner.get_block_hashes(heights.clone()).await?;
+        for (height, hash) in heights.zip(&hashes) {
+            self.record(Exchange::BlockHash { height, hash: Some(hex::encode(hash)) })?;
+        }
+        Ok(hashes)
+    }
+}
+
+#[async_trait]
+impl<C: MetashrewClientLike> ViewProviderLike for RecordingClient<C> {
+    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+        let result = self.inner.call_view(view_name, params, height).await?;
+        self.record_view(view_name, params, height, &result)?;
+        Ok(result)
+    }
+
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        let results = self.inner.call_views(calls).await?;
+        for (call, result) in calls.iter().zip(&results) {
+            if let Ok(result) = result {
+                self.record_view(&call.view_name, &call.params, call.height, result)?;
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl<C: MetashrewClientLike> MetashrewClientLike for RecordingClient<C> {
+    fn get_identifier(&self) -> String {
+        format!("{} (recording to {})", self.inner.get_identifier(), self.path.display())
+    }
+
+    async fn is_healthy(&self) -> bool {
+        self.inner.is_healthy().await
+    }
+}
+
+/// Client that serves a run from a recorded fixture
+///
+/// Metashrew is never contacted. The replay moves through the fixture in
+/// recording order, so the tip advances and reorgs happen as they did in the
+/// recorded run. A request that was not recorded fails with
+/// [`Error::Replay`], so a replayed run cannot silently diverge from the
+/// recorded one.
+#[derive(Debug)]
+pub struct ReplayClient {
+    /// The fixture path
+    path: PathBuf,
+
+    /// The recorded exchanges
+    fixture: Fixture,
+
+    /// Number of recorded answers the replay has moved past
+    position: Mutex<usize>,
+}
+
+impl ReplayClient {
+    /// Load a replay client from a fixture
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The fixture path
+    ///
+    /// # Returns
+    ///
+    /// A replay client serving the recorded exchanges
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fixture cannot be read or contains an invalid exchange
+    pub fn load(path: &Path) -> Result<Self> {
+        let fixture = Fixture::load(path)?;
+        debug!(
+            "Loaded fixture {} with {} answers for {} block hashes and {} view calls",
+            path.display(),
+            fixture.answers.len(),
+            fixture.count_requests(|request| matches!(request, Request::BlockHash(_))),
+            fixture.count_requests(|request| matches!(request, Request::View(_)))
+        );
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            fixture,
+            position: Mutex::new(0),
+        })
+    }
+
+    /// Answer a request the way metashrew did at this point of the recording
+    ///
+    /// A request takes its next recorded answer once every answer recorded
+    /// before it that replaced an earlier one has been taken, so a reorg's
+    /// block hashes are served in the order they changed. Until then, the
+    /// answer current at this point of the recording is served. A height
+    /// request always takes the next recorded height, moving the replay past
+    /// everything recorded before it, as the tip is what drives the recorded run.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request
+    ///
+    /// # Returns
+    ///
+    /// The answer, or None if the request was never recorded
+    fn answer(&self, request: &Request) -> Option<Answer> {
+        let positions = self.fixture.history.get(request)?;
+        let mut position = match self.position.lock() {
+            Ok(position) => position,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some(&next) = positions.iter().find(|&&recorded| recorded >= *position) {
+            let reached = *request == Request::Height
+                || self.fixture.answers[*position..next].iter().all(|recorded| !recorded.replaces);
+            if reached {
+                *position = next + 1;
+                return Some(self.fixture.answers[next].answer.clone());
+            }
+        }
+
+        // A request made earlier than in the recording, e.g. by a prefetch, gets its first answer
+        let current = positions.iter().rev().find(|&&recorded| recorded < *position).unwrap_or(&positions[0]);
+        Some(self.fixture.answers[*current].answer.clone())
+    }
+}
+
+#[async_trait]
+impl BlockProviderLike for ReplayClient {
+    async fn get_height(&self) -> Result<u32> {
+        match self.answer(&Request::Height) {
+            Some(Answer::Height(height)) => Ok(height),
+            _ => Err(Error::Replay(format!("No height was recorded in {}", self.path.display()))),
+        }
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+        match self.answer(&Request::BlockHash(height)) {
+            Some(Answer::BlockHash(Some(hash))) => Ok(hash),
+            Some(Answer::BlockHash(None)) => Err(Error::MetashrewClient(format!("Block hash not found for height {}", height))),
+            _ => Err(Error::Replay(format!(
+                "Block hash for height {} was not recorded in {}",
+                height,
+                self.path.display()
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl ViewProviderLike for ReplayClient {
+    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+        let request = Request::View((view_name.to_string(), params.to_vec(), height));
+        match self.answer(&request) {
+            Some(Answer::View(result)) => Ok(result),
+            _ => Err(Error::Replay(format!(
+                "View call '{}' with params {} at height {:?} was not recorded in {}",
+                view_name,
+                hex::encode(params),
+                height,
+                self.path.display()
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl MetashrewClientLike for ReplayClient {
+    fn get_identifier(&self) -> String {
+        format!("replay:{}", self.path.display())
+    }
+
+    async fn is_healthy(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::MemoryAdapterBuilder;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_recorded_exchanges_replay() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fixture.ndjson");
+
+        let metashrew = MemoryAdapterBuilder::new()
+            .with_height(2)
+            .with_block_hash(1, vec![1; 32])
+            .with_block_hash(2, vec![2; 32])
+            .with_view_result("balance", &[7], Some(2), vec![42])
+            .build();
+        let recorder = RecordingClient::new(metashrew, &path).unwrap();
+
+        assert_eq!(recorder.get_height().await.unwrap(), 2);
+        assert_eq!(recorder.get_block_hashes(1..=2).await.unwrap(), vec![vec![1; 32], vec![2; 32]]);
+        assert!(recorder.get_block_hash(3).await.is_err());
+        assert_eq!(recorder.call_view("balance", &[7], Some(2)).await.unwrap(), vec![42]);
+
+        // Repeated exchanges are only recorded once
+        recorder.get_height().await.unwrap();
+        recorder.call_view("balance", &[7], Some(2)).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 5);
+
+        let replay = ReplayClient::load(&path).unwrap();
+        assert_eq!(replay.get_height().await.unwrap(), 2);
+        assert_eq!(replay.get_block_hash(1).await.unwrap(), vec![1; 32]);
+        assert_eq!(replay.get_block_hash(2).await.unwrap(), vec![2; 32]);
+        assert_eq!(replay.call_view("balance", &[7], Some(2)).await.unwrap(), vec![42]);
+
+        // A block that was not available yet stays unavailable
+        let error = replay.get_block_hash(3).await.unwrap_err();
+        assert!(is_block_not_available(&error));
+
+        // Anything that was not recorded fails loudly
+        assert!(matches!(replay.get_block_hash(4).await, Err(Error::Replay(_))));
+        assert!(matches!(replay.call_view("balance", &[8], Some(2)).await, Err(Error::Replay(_))));
+        assert!(matches!(replay.call_view("balance", &[7], Some(1)).await, Err(Error::Replay(_))));
+    }
+
+    #[tokio::test]
+    async fn test_recording_extends_fixture() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fixture.ndjson");
+
+        let metashrew = MemoryAdapterBuilder::new().with_height(1).build();
+        RecordingClient::new(metashrew.clone(), &path).unwrap().get_height().await.unwrap();
+
+        // The tip moved on since the first recording
+        metashrew.set_height(3);
+        let recorder = RecordingClient::new(metashrew, &path).unwrap();
+        recorder.get_height().await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        // The replayed tip advances as the recorded one did
+        let replay = ReplayClient::load(&path).unwrap();
+        assert_eq!(replay.get_height().await.unwrap(), 1);
+        assert_eq!(replay.get_height().await.unwrap(), 3);
+        assert_eq!(replay.get_height().await.unwrap(), 3);
+    }
+
+    /// Record a run in which the tip advances, and then a reorg replaces blocks 2 and 3
+    async fn record_reorg(path: &Path) {
+        let metashrew = MemoryAdapterBuilder::new()
+            .with_height(2)
+            .with_block_hash(1, vec![1; 32])
+            .with_block_hash(2, vec![2; 32])
+            .build();
+        let recorder = RecordingClient::new(metashrew.clone(), path).unwrap();
+        recorder.get_height().await.unwrap();
+        recorder.get_block_hashes(1..=2).await.unwrap();
+
+        metashrew.set_height(3);
+        metashrew.set_block_hash(3, vec![3; 32]);
+        recorder.get_height().await.unwrap();
+        recorder.get_block_hash(3).await.unwrap();
+        recorder.get_block_hash(2).await.unwrap();
+
+        metashrew.set_block_hash(3, vec![0x33; 32]);
+        metashrew.set_block_hash(2, vec![0x22; 32]);
+        recorder.get_block_hash(3).await.unwrap();
+        recorder.get_block_hash(2).await.unwrap();
+
+        metashrew.set_height(4);
+        metashrew.set_block_hash(4, vec![4; 32]);
+        recorder.get_height().await.unwrap();
+        recorder.get_block_hash(4).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_follows_tip_advances_and_reorgs() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fixture.ndjson");
+        record_reorg(&path).await;
+
+        let replay = ReplayClient::load(&path).unwrap();
+        assert_eq!(replay.get_height().await.unwrap(), 2);
+        assert_eq!(replay.get_block_hashes(1..=2).await.unwrap(), vec![vec![1; 32], vec![2; 32]]);
+
+        // Polling the tip block again does not jump ahead to the reorg
+        assert_eq!(replay.get_block_hash(2).await.unwrap(), vec![2; 32]);
+
+        assert_eq!(replay.get_height().await.unwrap(), 3);
+        assert_eq!(replay.get_block_hash(3).await.unwrap(), vec![3; 32]);
+
+        // The reorg replaces the blocks in the order they changed
+        assert_eq!(replay.get_block_hash(3).await.unwrap(), vec![0x33; 32]);
+        assert_eq!(replay.get_block_hash(2).await.unwrap(), vec![0x22; 32]);
+        assert_eq!(replay.get_block_hash(1).await.unwrap(), vec![1; 32]);
+
+        assert_eq!(replay.get_height().await.unwrap(), 4);
+        assert_eq!(replay.get_block_hash(4).await.unwrap(), vec![4; 32]);
+        assert_eq!(replay.get_height().await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_replay_tip_moves_past_unrequested_answers() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fixture.ndjson");
+        record_reorg(&path).await;
+
+        // Blocks requested after the tip moved on see the state of the recording at that tip
+        let replay = ReplayClient::load(&path).unwrap();
+        assert_eq!(replay.get_height().await.unwrap(), 2);
+        assert_eq!(replay.get_height().await.unwrap(), 3);
+        assert_eq!(replay.get_height().await.unwrap(), 4);
+        assert_eq!(
+            replay.get_block_hashes(1..=4).await.unwrap(),
+            vec![vec![1; 32], vec![0x22; 32], vec![0x33; 32], vec![4; 32]]
+        );
+    }
+
+    #[test]
+    fn test_invalid_fixture() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fixture.ndjson");
+
+        fs::write(&path, "{\"type\":\"height\",\"height\":1}\n{\"type\":\"view\"}\n").unwrap();
+        let error = ReplayClient::load(&path).unwrap_err();
+        assert!(matches!(error, Error::Replay(_)));
+        assert!(error.to_string().contains("line 2"));
+
+        assert!(matches!(ReplayClient::load(&dir.path().join("missing")), Err(Error::File(_))));
+    }
+}
@@ -4,8 +4,11 @@
 //! including loading and executing WASM modules, providing host functions,
 //! and managing WASM memory.
 
-use crate::error::Result;
-use crate::client::MetashrewClient;
+use crate::error::{Error, Result};
+use crate::client::JsonRpcClient;
+use crate::config::MetashrewConfig;
+use crate::metrics;
+use crate::traits::ViewProviderLike;
 use debshrew_runtime::transform::TransformResult;
 use debshrew_support::{CdcMessage, CdcHeader, CdcOperation, CdcPayload, TransformState};
 use std::collections::HashMap;
@@ -13,7 +16,7 @@ use std::path::Path;
 use std::sync::Arc;
 use wasmtime::{Engine, Module, Store, Linker, Config, StoreLimitsBuilder, ResourceLimiter, StoreLimits};
 use anyhow::anyhow;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 // We no longer use a global buffer - view results are stored in the caller's state
 
@@ -46,16 +49,130 @@ impl ResourceLimiter for LargeMemoryLimiter {
 pub struct RuntimeState {
     /// The current view result buffer
     pub view_result: Vec<u8>,
+    
+    /// The last view call that failed while processing the block, if any
+    pub view_error: Option<String>,
+    
+    /// Whether the failed view call was missing from a replayed fixture
+    pub view_error_unrecorded: bool,
 }
 
 impl Default for RuntimeState {
     fn default() -> Self {
         Self {
             view_result: Vec::new(),
+            view_error: None,
+            view_error_unrecorded: false,
         }
     }
 }
 
+/// View results fetched ahead of the transform
+///
+/// The cache is shared between the `__view` host function and the block
+/// synchronizer's prefetch stage. While enabled, the host function records the
+/// view calls made for each block so the synchronizer can replay them against
+/// upcoming heights, and consumes any prefetched result instead of calling
+/// metashrew.
+#[derive(Debug, Clone, Default)]
+pub struct ViewPrefetchCache {
+    /// Shared state between clones
+    inner: Arc<std::sync::Mutex<ViewPrefetchState>>,
+}
+
+#[derive(Debug, Default)]
+struct ViewPrefetchState {
+    /// Whether view calls are recorded and prefetched results are used
+    enabled: bool,
+
+    /// Prefetched results: (view_name, params, height) -> result
+    results: HashMap<(String, Vec<u8>, u32), Vec<u8>>,
+
+    /// View calls recorded since the last call to `take_recorded`
+    recorded: Vec<(String, Vec<u8>)>,
+}
+
+impl ViewPrefetchCache {
+    /// Create a new, disabled view prefetch cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable view prefetching
+    ///
+    /// Disabling the cache drops all prefetched results and recorded calls.
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut state = self.inner.lock().unwrap();
+        state.enabled = enabled;
+        if !enabled {
+            state.results.clear();
+            state.recorded.clear();
+        }
+    }
+
+    /// Check whether view prefetching is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.inner.lock().unwrap().enabled
+    }
+
+    /// Store a prefetched view result
+    pub fn insert(&self, view_name: &str, params: &[u8], height: u32, result: Vec<u8>) {
+        let mut state = self.inner.lock().unwrap();
+        if state.enabled {
+            state.results.insert((view_name.to_string(), params.to_vec(), height), result);
+        }
+    }
+
+    /// Remove and return a prefetched view result, if one is available
+    pub fn take(&self, view_name: &str, params: &[u8], height: u32) -> Option<Vec<u8>> {
+        let mut state = self.inner.lock().unwrap();
+        state.results.remove(&(view_name.to_string(), params.to_vec(), height))
+    }
+
+    /// Record a view call made by the transform
+    ///
+    /// Calls are recorded once per distinct view name and params.
+    pub fn record(&self, view_name: &str, params: &[u8]) {
+        let mut state = self.inner.lock().unwrap();
+        if !state.enabled {
+            return;
+        }
+
+        let already_recorded = state.recorded.iter()
+            .any(|(name, p)| name == view_name && p.as_slice() == params);
+        if !already_recorded {
+            state.recorded.push((view_name.to_string(), params.to_vec()));
+        }
+    }
+
+    /// Take the view calls recorded since the last call
+    pub fn take_recorded(&self) -> Vec<(String, Vec<u8>)> {
+        let mut state = self.inner.lock().unwrap();
+        std::mem::take(&mut state.recorded)
+    }
+
+    /// Drop all prefetched results, e.g. after the chain was rolled back
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().results.clear();
+    }
+
+    /// Drop prefetched results for heights below `height`
+    pub fn evict_below(&self, height: u32) {
+        let mut state = self.inner.lock().unwrap();
+        state.results.retain(|(_, _, h), _| *h >= height);
+    }
+
+    /// Get the number of prefetched results held
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().results.len()
+    }
+
+    /// Check if no prefetched results are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 /// WASM runtime for executing transform modules
 pub struct WasmRuntime {
     /// The wasmtime engine
@@ -78,6 +195,12 @@ pub struct WasmRuntime {
     
     /// The metashrew URL
     metashrew_url: String,
+
+    /// The provider the `__view` host function calls
+    view_provider: Arc<dyn ViewProviderLike>,
+
+    /// Prefetched view results shared with the block synchronizer
+    view_cache: ViewPrefetchCache,
 }
 
 impl std::fmt::Debug for WasmRuntime {
@@ -141,6 +264,8 @@ impl WasmRuntime {
             state: TransformState::new(),
             cdc_cache: HashMap::new(),
             metashrew_url: metashrew_url.to_string(),
+            view_provider: Self::default_view_provider(metashrew_url)?,
+            view_cache: ViewPrefetchCache::new(),
         })
     }
 
@@ -170,9 +295,29 @@ impl WasmRuntime {
             state: TransformState::new(),
             cdc_cache: HashMap::new(),
             metashrew_url: metashrew_url.to_string(),
+            view_provider: Self::default_view_provider(metashrew_url)?,
+            view_cache: ViewPrefetchCache::new(),
         })
     }
     
+    /// Create the view provider used until another one is set
+    ///
+    /// # Arguments
+    ///
+    /// * `metashrew_url` - The metashrew URL
+    ///
+    /// # Returns
+    ///
+    /// A JSON-RPC client for the metashrew URL
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid
+    fn default_view_provider(metashrew_url: &str) -> Result<Arc<dyn ViewProviderLike>> {
+        let client = JsonRpcClient::from_config(&MetashrewConfig::new(metashrew_url))?;
+        Ok(Arc::new(client))
+    }
+
     /// Get the metashrew URL
     ///
     /// # Returns
@@ -182,6 +327,28 @@ impl WasmRuntime {
         &self.metashrew_url
     }
 
+    /// Set the provider view calls are made against
+    ///
+    /// By default, view calls go to a JSON-RPC client for the metashrew URL the
+    /// runtime was created with. The block synchronizer replaces it with its
+    /// own client, so the transform reads views from the same source as blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - The view provider
+    pub fn set_view_provider(&mut self, provider: Arc<dyn ViewProviderLike>) {
+        self.view_provider = provider;
+    }
+
+    /// Get the view prefetch cache
+    ///
+    /// # Returns
+    ///
+    /// A handle to the cache consulted by the `__view` host function
+    pub fn view_prefetch_cache(&self) -> ViewPrefetchCache {
+        self.view_cache.clone()
+    }
+
     /// Set the current block height
     ///
     /// # Arguments
@@ -249,6 +416,7 @@ impl WasmRuntime {
         // Create shared state for closures
         let current_height = self.current_height;
         let current_hash_for_block_hash = self.current_hash.clone();
+        let view_cache = self.view_cache.clone();
         
         // Register all required host functions
         linker.func_wrap(env_module, "__load", |mut caller: wasmtime::Caller<'_, RuntimeState>, ptr: i32| {
@@ -283,15 +451,7 @@ impl WasmRuntime {
             log::debug!("Wrote {} bytes of view result to WASM memory at ptr {}", view_result.len(), ptr);
         }).map_err(|e| anyhow!("Failed to register __load: {}", e))?;
         
-        // Use the client passed to the runtime instead of creating a new one with hardcoded URL
-        let client_clone = Arc::new(crate::client::JsonRpcClient::from_config(&crate::config::MetashrewConfig {
-            url: self.get_metashrew_url().to_string(),
-            username: None,
-            password: None,
-            timeout: 30,
-            max_retries: 3,
-            retry_delay: 1000,
-        }).unwrap());
+        let view_provider = self.view_provider.clone();
         
         linker.func_wrap(env_module, "__view", move |mut caller: wasmtime::Caller<'_, RuntimeState>, view_name_ptr: i32, input_ptr: i32| -> i32 {
             // Get the memory export
@@ -342,19 +502,28 @@ impl WasmRuntime {
             // Call the view function
             log::debug!("Calling view function '{}' with {} bytes of input", view_name, input_len);
             
-            // Use the client to call the view function
-            let client = client_clone.clone();
+            // Record the call so the synchronizer can prefetch it for upcoming blocks
+            view_cache.record(view_name, &input_bytes);
             
-            // We can't create a new runtime here, so we'll use a blocking call
-            // This is not ideal, but it's a workaround for now
-            let result = match tokio::task::block_in_place(|| {
-                let _rt = tokio::runtime::Handle::current();
-                futures::executor::block_on(async {
-                    client.call_view(view_name, &input_bytes, Some(current_height)).await
-                })
-            }) {
-                Ok(r) => Ok(r),
-                Err(e) => Err(e),
+            // Use the view provider to call the view function
+            let client = view_provider.clone();
+            
+            let result = if let Some(data) = view_cache.take(view_name, &input_bytes, current_height) {
+                log::debug!("Using prefetched result for view '{}' at height {}", view_name, current_height);
+                metrics::record_view_call(view_name, None, true);
+                Ok(data)
+            } else {
+                // We can't create a new runtime here, so we'll use a blocking call
+                // This is not ideal, but it's a workaround for now
+                let start = Instant::now();
+                let result = tokio::task::block_in_place(|| {
+                    let _rt = tokio::runtime::Handle::current();
+                    futures::executor::block_on(async {
+                        client.call_view(view_name, &input_bytes, Some(current_height)).await
+                    })
+                });
+                metrics::record_view_call(view_name, Some(start.elapsed()), result.is_ok());
+                result
             };
             
             match result {
@@ -369,6 +538,8 @@ impl WasmRuntime {
                 },
                 Err(e) => {
                     log::error!("View call '{}' failed: {}", view_name, e);
+                    caller.data_mut().view_error = Some(format!("View call '{}' failed: {}", view_name, e));
+                    caller.data_mut().view_error_unrecorded = matches!(e, Error::Replay(_));
                     return -1;
                 }
             }
@@ -459,13 +630,27 @@ impl WasmRuntime {
         
         // Call the process_block function
         // The return value is a pointer to the serialized CDC messages
-        let cdc_ptr = process_block.call(&mut store, ())
-            .map_err(|e| anyhow!("Failed to call process_block function: {}", e))?;
+        let call_result = process_block.call(&mut store, ());
+        
+        // A failed view call makes the transform fail too, but metashrew is to blame, not the transform
+        if let Some(view_error) = store.data().view_error.clone() {
+            if !matches!(call_result, Ok(ptr) if ptr >= 0) {
+                let message = format!("{} while processing block {}", view_error, height);
+                // Retrying a call that was never recorded cannot succeed
+                if store.data().view_error_unrecorded {
+                    return Err(Error::Replay(message));
+                }
+                return Err(Error::MetashrewClient(message));
+            }
+        }
+        
+        let cdc_ptr = call_result
+            .map_err(|e| Error::Transform(format!("Transform trapped at block {}: {:?}", height, e)))?;
         
         log::debug!("WASM process_block returned pointer: {}", cdc_ptr);
         
         if cdc_ptr < 0 {
-            return Err(anyhow!("Process block failed with code {}", cdc_ptr).into());
+            return Err(Error::Transform(format!("Process block failed with code {} at block {}", cdc_ptr, height)));
         }
         
         // Deserialize the CDC messages from WASM memory using the pointer returned by the process_block function
@@ -496,7 +681,7 @@ impl WasmRuntime {
                 // Read the serialized CDC messages
                 let mut serialized_data = vec![0u8; len];
                 memory.read(&store, (cdc_ptr + 4) as usize, &mut serialized_data)
-                    .map_err(|e| anyhow!("Failed to read CDC message data: {}", e))?;
+                    .map_err(|e| Error::Transform(format!("Failed to read CDC message data at block {}: {}", height, e)))?;
                 
                 log::debug!("Read {} bytes of CDC message data from WASM", serialized_data.len());
                 log::debug!("First 100 bytes: {:?}", &serialized_data[..std::cmp::min(100, serialized_data.len())]);
@@ -510,7 +695,7 @@ impl WasmRuntime {
                     Err(e) => {
                         log::error!("Failed to deserialize CDC messages from WASM: {}", e);
                         log::error!("Raw data as string: {}", String::from_utf8_lossy(&serialized_data));
-                        return Err(anyhow!("Failed to deserialize CDC messages: {}", e).into());
+                        return Err(Error::Transform(format!("Failed to deserialize CDC messages at block {}: {}", height, e)));
                     }
                 }
             }
@@ -526,6 +711,23 @@ impl WasmRuntime {
         Ok(TransformResult::new(cdc_messages, self.state.clone()))
     }
     
+    /// Skip a block that could not be transformed
+    ///
+    /// The block is recorded without CDC messages, so a later rollback
+    /// across it has nothing to invert.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height
+    ///
+    /// # Returns
+    ///
+    /// An empty result with the current state
+    pub fn skip_block(&mut self, height: u32) -> TransformResult {
+        self.cdc_cache.insert(height, Vec::new());
+        TransformResult::new(Vec::new(), self.state.clone())
+    }
+    
     /// Handle a rollback
     ///
     /// # Arguments
@@ -591,15 +793,7 @@ impl WasmRuntime {
             log::debug!("Wrote {} bytes of view result to WASM memory at ptr {}", view_result.len(), ptr);
         }).map_err(|e| anyhow!("Failed to register __load: {}", e))?;
         
-        // Use the client passed to the runtime instead of creating a new one with hardcoded URL
-        let client_clone = Arc::new(crate::client::JsonRpcClient::from_config(&crate::config::MetashrewConfig {
-            url: self.get_metashrew_url().to_string(),
-            username: None,
-            password: None,
-            timeout: 30,
-            max_retries: 3,
-            retry_delay: 1000,
-        }).unwrap());
+        let view_provider = self.view_provider.clone();
         
         linker.func_wrap(env_module, "__view", move |mut caller: wasmtime::Caller<'_, RuntimeState>, view_name_ptr: i32, input_ptr: i32| -> i32 {
             // Get the memory export
@@ -650,11 +844,12 @@ impl WasmRuntime {
             // Call the view function
             log::debug!("Calling view function '{}' with {} bytes of input", view_name, input_len);
             
-            // Use the client to call the view function
-            let client = client_clone.clone();
+            // Use the view provider to call the view function
+            let client = view_provider.clone();
             
             // We can't create a new runtime here, so we'll use a blocking call
             // This is not ideal, but it's a workaround for now
+            let start = Instant::now();
             let result = match tokio::task::block_in_place(|| {
                 let _rt = tokio::runtime::Handle::current();
                 futures::executor::block_on(async {
@@ -664,6 +859,7 @@ impl WasmRuntime {
                 Ok(r) => Ok(r),
                 Err(e) => Err(e),
             };
+            metrics::record_view_call(view_name, Some(start.elapsed()), result.is_ok());
             
             match result {
                 Ok(data) => {
@@ -838,6 +1034,7 @@ impl WasmRuntime {
                 block_height: new_height,
                 block_hash: hex::encode(&self.current_hash),
                 transaction_id: None,
+                inverse: true,
             },
             payload: CdcPayload {
                 operation,
@@ -919,6 +1116,7 @@ mod tests {
                 block_height: 123,
                 block_hash: "000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d".to_string(),
                 transaction_id: None,
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,
@@ -939,5 +1137,38 @@ mod tests {
         assert_eq!(inverse.payload.key, "test_key");
         assert_eq!(inverse.payload.before, create_message.payload.after);
         assert_eq!(inverse.payload.after, None);
+        assert!(inverse.header.inverse);
+    }
+
+    #[test]
+    fn test_view_prefetch_cache() {
+        let cache = ViewPrefetchCache::new();
+
+        // Nothing is recorded or stored while disabled
+        cache.record("balances", &[1]);
+        cache.insert("balances", &[1], 10, vec![42]);
+        assert!(cache.take_recorded().is_empty());
+        assert!(cache.is_empty());
+
+        cache.set_enabled(true);
+
+        // Calls are recorded once per view name and params
+        cache.record("balances", &[1]);
+        cache.record("balances", &[1]);
+        cache.record("balances", &[2]);
+        assert_eq!(cache.take_recorded(), vec![
+            ("balances".to_string(), vec![1]),
+            ("balances".to_string(), vec![2]),
+        ]);
+        assert!(cache.take_recorded().is_empty());
+
+        // Prefetched results are consumed by height
+        cache.insert("balances", &[1], 10, vec![42]);
+        cache.insert("balances", &[1], 11, vec![43]);
+        assert_eq!(cache.take("balances", &[1], 10), Some(vec![42]));
+        assert_eq!(cache.take("balances", &[1], 10), None);
+
+        cache.evict_below(12);
+        assert!(cache.is_empty());
     }
 }
\ No newline at end of file
@@ -0,0 +1,521 @@
+//! Embedded HTTP server
+//!
+//! This module provides the optional HTTP server used by supervisors and
+//! operators to inspect a running pipeline. It exposes:
+//!
+//! - `/healthz`: the process is alive
+//! - `/readyz`: metashrew and every sink are reachable
+//! - `/status`: the live synchronizer status, as published through a [`StatusHandle`]
+//! - `/metrics` (or a configured path): Prometheus metrics, when a recorder is attached
+//! - `POST /admin/pause`, `POST /admin/resume` and `POST /admin/rewind`: operator
+//!   control of the synchronizer, when an [`AdminHandle`] is attached; requests must
+//!   carry the admin token as `Authorization: Bearer <token>`
+
+use crate::admin::AdminHandle;
+use crate::error::{Error, Result};
+use crate::sink::CdcSink;
+use crate::status::StatusHandle;
+use crate::traits::MetashrewClientLike;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{debug, info};
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+/// Maximum time a single readiness check may take before it counts as failed
+const READINESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Readiness report served on `/readyz`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Readiness {
+    /// Whether every dependency is reachable
+    pub ready: bool,
+
+    /// Whether metashrew is reachable
+    pub metashrew: bool,
+
+    /// Whether each sink is reachable, by name
+    pub sinks: BTreeMap<String, bool>,
+}
+
+/// Body of a rewind request
+#[derive(Debug, Deserialize)]
+struct RewindRequest {
+    /// The block height to rewind to
+    height: u32,
+}
+
+/// Embedded HTTP server
+///
+/// The server reads the synchronizer status through a shared handle and checks
+/// the synchronizer's client and sinks directly, so it always reports on the
+/// live pipeline.
+#[derive(Clone)]
+pub struct StatusServer {
+    /// The synchronizer status
+    status: StatusHandle,
+
+    /// The metashrew client
+    client: Arc<dyn MetashrewClientLike>,
+
+    /// The sinks to check for readiness, by name
+    sinks: Vec<(String, Arc<Box<dyn CdcSink>>)>,
+
+    /// The path metrics are served on and the recorder rendering them
+    metrics: Option<(String, PrometheusHandle)>,
+
+    /// The handle the admin endpoints control the synchronizer through and the
+    /// token admin requests must present
+    admin: Option<(AdminHandle, String)>,
+}
+
+impl StatusServer {
+    /// Create a new status server
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The synchronizer status handle
+    /// * `client` - The metashrew client checked for readiness
+    ///
+    /// # Returns
+    ///
+    /// A new status server
+    pub fn new(status: StatusHandle, client: Arc<dyn MetashrewClientLike>) -> Self {
+        Self {
+            status,
+            client,
+            sinks: Vec::new(),
+            metrics: None,
+            admin: None,
+        }
+    }
+
+    /// Add a sink to the readiness check
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name the sink is reported under
+    /// * `sink` - The sink
+    pub fn add_sink(&mut self, name: &str, sink: Arc<Box<dyn CdcSink>>) {
+        self.sinks.push((name.to_string(), sink));
+    }
+
+    /// Serve Prometheus metrics
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to serve the metrics on
+    /// * `handle` - The handle of the installed Prometheus recorder
+    pub fn set_metrics(&mut self, path: &str, handle: PrometheusHandle) {
+        self.metrics = Some((path.to_string(), handle));
+    }
+
+    /// Serve the admin endpoints
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - The admin handle of the synchronizer
+    /// * `token` - The bearer token admin requests must present
+    pub fn set_admin(&mut self, handle: AdminHandle, token: &str) {
+        self.admin = Some((handle, token.to_string()));
+    }
+
+    /// Check whether metashrew and every sink are reachable
+    ///
+    /// # Returns
+    ///
+    /// The readiness report
+    pub async fn readiness(&self) -> Readiness {
+        let metashrew = time::timeout(READINESS_TIMEOUT, self.client.is_healthy())
+            .await
+            .unwrap_or(false);
+
+        let mut sinks = BTreeMap::new();
+        for (name, sink) in &self.sinks {
+            let healthy = time::timeout(READINESS_TIMEOUT, sink.is_healthy())
+                .await
+                .unwrap_or(false);
+            sinks.insert(name.clone(), healthy);
+        }
+
+        Readiness {
+            ready: metashrew && sinks.values().all(|healthy| *healthy),
+            metashrew,
+            sinks,
+        }
+    }
+
+    /// Start serving in the background
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to listen on (port 0 picks a free port)
+    ///
+    /// # Returns
+    ///
+    /// The address the server is listening on and the server task
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be bound
+    pub fn spawn(self, address: SocketAddr) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let builder = Server::try_bind(&address)
+            .map_err(|e| Error::Server(format!("Failed to bind {}: {}", address, e)))?;
+
+        let server = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let server = server.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.handle(request).await) }
+                }))
+            }
+        });
+
+        let http = builder.serve(make_service);
+        let local_address = http.local_addr();
+        info!("HTTP server listening on {}", local_address);
+
+        let handle = tokio::spawn(async move {
+            http.await
+                .map_err(|e| Error::Server(format!("HTTP server failed: {}", e)))
+        });
+
+        Ok((local_address, handle))
+    }
+
+    /// Handle a request
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        debug!("{} {}", request.method(), request.uri().path());
+
+        if let Some((admin, token)) = &self.admin {
+            if request.uri().path().starts_with("/admin/") {
+                if !is_authorized(&request, token) {
+                    let mut response = json_response(StatusCode::UNAUTHORIZED, &serde_json::json!({ "error": "unauthorized" }));
+                    response
+                        .headers_mut()
+                        .insert(WWW_AUTHENTICATE, "Bearer".parse().expect("valid header value"));
+                    return response;
+                }
+                return self.handle_admin(admin, request).await;
+            }
+        }
+
+        if request.method() != Method::GET {
+            return json_response(StatusCode::METHOD_NOT_ALLOWED, &serde_json::json!({ "error": "method not allowed" }));
+        }
+
+        if let Some((path, handle)) = &self.metrics {
+            if request.uri().path() == path {
+                let mut response = Response::new(Body::from(handle.render()));
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, "text/plain; version=0.0.4".parse().expect("valid header value"));
+                return response;
+            }
+        }
+
+        match request.uri().path() {
+            "/healthz" => json_response(StatusCode::OK, &serde_json::json!({ "status": "ok" })),
+            "/readyz" => {
+                let readiness = self.readiness().await;
+                let status = if readiness.ready {
+                    StatusCode::OK
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE
+                };
+                json_response(status, &readiness)
+            }
+            "/status" => json_response(StatusCode::OK, &self.status.snapshot()),
+            _ => json_response(StatusCode::NOT_FOUND, &serde_json::json!({ "error": "not found" })),
+        }
+    }
+
+    /// Handle an admin request
+    async fn handle_admin(&self, admin: &AdminHandle, request: Request<Body>) -> Response<Body> {
+        if request.method() != Method::POST {
+            return json_response(StatusCode::METHOD_NOT_ALLOWED, &serde_json::json!({ "error": "method not allowed" }));
+        }
+
+        match request.uri().path() {
+            "/admin/pause" => {
+                admin.pause();
+                json_response(StatusCode::ACCEPTED, &serde_json::json!({ "status": "pausing" }))
+            }
+            "/admin/resume" => {
+                admin.resume();
+                json_response(StatusCode::ACCEPTED, &serde_json::json!({ "status": "resuming" }))
+            }
+            "/admin/rewind" => {
+                let body = match hyper::body::to_bytes(request.into_body()).await {
+                    Ok(body) => body,
+                    Err(e) => return json_response(StatusCode::BAD_REQUEST, &serde_json::json!({ "error": e.to_string() })),
+                };
+                let height = match serde_json::from_slice::<RewindRequest>(&body) {
+                    Ok(rewind) => rewind.height,
+                    Err(e) => {
+                        let error = format!("Invalid rewind request: {}", e);
+                        return json_response(StatusCode::BAD_REQUEST, &serde_json::json!({ "error": error }));
+                    }
+                };
+
+                // Reject rewinds the synchronizer cannot serve from its block cache
+                let status = self.status.snapshot();
+                let cached = status.cache_lowest_height.is_some_and(|lowest| height >= lowest);
+                if height >= status.current_height || !cached {
+                    let error = format!(
+                        "Cannot rewind to height {}: the current height is {} and the lowest cached block is {:?}",
+                        height, status.current_height, status.cache_lowest_height
+                    );
+                    return json_response(StatusCode::CONFLICT, &serde_json::json!({ "error": error }));
+                }
+
+                admin.request_rewind(height);
+                json_response(StatusCode::ACCEPTED, &serde_json::json!({ "status": "rewinding", "height": height }))
+            }
+            _ => json_response(StatusCode::NOT_FOUND, &serde_json::json!({ "error": "not found" })),
+        }
+    }
+}
+
+/// Check whether a request carries the admin token
+///
+/// The token is compared in constant time so the comparison does not leak how
+/// much of a guessed token is correct.
+fn is_authorized(request: &Request<Body>, token: &str) -> bool {
+    let presented = match request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        Some(presented) => presented.as_bytes(),
+        None => return false,
+    };
+
+    presented.len() == token.len()
+        && presented
+            .iter()
+            .zip(token.as_bytes())
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Build a JSON response
+pub(crate) fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().expect("valid header value"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockMetashrewClient;
+    use crate::sink::NullSink;
+    use async_trait::async_trait;
+    use debshrew_support::CdcMessage;
+
+    /// A sink whose destination is unreachable
+    struct UnreachableSink;
+
+    #[async_trait]
+    impl CdcSink for UnreachableSink {
+        async fn send(&self, _messages: Vec<CdcMessage>) -> Result<()> {
+            Err(Error::Sink("unreachable".to_string()))
+        }
+
+        async fn flush(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn close(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn is_healthy(&self) -> bool {
+            false
+        }
+    }
+
+    fn spawn_server(sink: Box<dyn CdcSink>) -> (String, StatusHandle) {
+        let status = StatusHandle::new();
+        let mut server = StatusServer::new(status.clone(), Arc::new(MockMetashrewClient::new()));
+        server.add_sink("sink", Arc::new(sink));
+
+        let (address, _handle) = server.spawn("127.0.0.1:0".parse().unwrap()).unwrap();
+        (format!("http://{}", address), status)
+    }
+
+    #[tokio::test]
+    async fn test_health_and_status_endpoints() {
+        let (url, status) = spawn_server(Box::new(NullSink));
+        status.update(|status| {
+            status.running = true;
+            status.current_height = 95;
+            status.indexed_height = Some(100);
+        });
+        status.record_reorg(94, 90);
+
+        let response = reqwest::get(format!("{}/healthz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = reqwest::get(format!("{}/readyz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let readiness: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(readiness["metashrew"], true);
+        assert_eq!(readiness["sinks"]["sink"], true);
+
+        let response = reqwest::get(format!("{}/status", url)).await.unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["current_height"], 95);
+        assert_eq!(body["lag"], 5);
+        assert_eq!(body["last_reorg"]["common_ancestor"], 90);
+
+        let response = reqwest::get(format!("{}/unknown", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let status = StatusHandle::new();
+        let mut server = StatusServer::new(status, Arc::new(MockMetashrewClient::new()));
+        server.set_metrics("/custom-metrics", crate::metrics::tests::test_handle().clone());
+        let (address, _handle) = server.spawn("127.0.0.1:0".parse().unwrap()).unwrap();
+
+        crate::metrics::record_reorg(3);
+
+        let response = reqwest::get(format!("http://{}/custom-metrics", address)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("debshrew_reorgs_total{pipeline=\"test-pipeline\"}"));
+
+        // Metrics are only served on the configured path
+        let response = reqwest::get(format!("http://{}/metrics", address)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_reports_unreachable_sink() {
+        let (url, _status) = spawn_server(Box::new(UnreachableSink));
+
+        let response = reqwest::get(format!("{}/readyz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let readiness: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(readiness["ready"], false);
+        assert_eq!(readiness["metashrew"], true);
+        assert_eq!(readiness["sinks"]["sink"], false);
+
+        // Liveness does not depend on the sink
+        let response = reqwest::get(format!("{}/healthz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_admin_endpoints() {
+        let status = StatusHandle::new();
+        status.update(|status| {
+            status.current_height = 100;
+            status.cache_lowest_height = Some(95);
+        });
+        let admin = AdminHandle::new();
+        let mut server = StatusServer::new(status, Arc::new(MockMetashrewClient::new()));
+        server.set_admin(admin.clone(), "secret");
+        let (address, _handle) = server.spawn("127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("http://{}", address);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let client = reqwest::Client::builder().default_headers(headers).build().unwrap();
+
+        let response = client.post(format!("{}/admin/pause", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        assert!(admin.is_paused());
+
+        let response = client.post(format!("{}/admin/resume", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        assert!(!admin.is_paused());
+
+        // Admin commands must be posted
+        let response = client.get(format!("{}/admin/pause", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED);
+
+        // Rewinds are limited to the block cache
+        let response = client
+            .post(format!("{}/admin/rewind", url))
+            .json(&serde_json::json!({ "height": 90 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+        assert_eq!(admin.pending_rewind(), None);
+
+        let response = client
+            .post(format!("{}/admin/rewind", url))
+            .body("not json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let response = client
+            .post(format!("{}/admin/rewind", url))
+            .json(&serde_json::json!({ "height": 97 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        assert_eq!(admin.pending_rewind(), Some(97));
+    }
+
+    #[tokio::test]
+    async fn test_admin_endpoints_require_token() {
+        let admin = AdminHandle::new();
+        let mut server = StatusServer::new(StatusHandle::new(), Arc::new(MockMetashrewClient::new()));
+        server.set_admin(admin.clone(), "secret");
+        let (address, _handle) = server.spawn("127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("http://{}", address);
+        let client = reqwest::Client::new();
+
+        let response = client.post(format!("{}/admin/pause", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[reqwest::header::WWW_AUTHENTICATE], "Bearer");
+
+        for token in ["Bearer wrong!", "Bearer secre", "secret", "Basic c2VjcmV0"] {
+            let response = client
+                .post(format!("{}/admin/pause", url))
+                .header(reqwest::header::AUTHORIZATION, token)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        }
+        assert!(!admin.is_paused());
+
+        // The other endpoints stay open
+        let response = client.get(format!("{}/healthz", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = client
+            .post(format!("{}/admin/pause", url))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        assert!(admin.is_paused());
+    }
+}
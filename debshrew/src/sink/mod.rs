@@ -6,16 +6,25 @@
 use crate::config::SinkConfig;
 use crate::error::{Error, Result};
 use async_trait::async_trait;
-use debshrew_support::CdcMessage;
+use bytes::BytesMut;
+use debshrew_support::{CdcControl, CdcControlMessage, CdcMessage, CdcOperation};
+use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use postgres_native_tls::MakeTlsConnector;
 use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
 use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::sync::Mutex as TokioMutex;
-use postgres::types::ToSql;
+use tokio_postgres::config::SslMode;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::{to_sql_checked, Format, IsNull, ToSql, Type};
+use tokio_postgres::NoTls;
 
 /// CDC sink trait
 ///
@@ -38,6 +47,27 @@ pub trait CdcSink: Send + Sync {
     /// Returns an error if the messages cannot be sent
     async fn send(&self, messages: Vec<CdcMessage>) -> Result<()>;
     
+    /// Send a control message to the sink
+    ///
+    /// Control messages mark block and reorg boundaries and are only sent when
+    /// they are enabled on the synchronizer. The default implementation ignores
+    /// them.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The control message to send
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the control message was sent successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the control message cannot be sent
+    async fn send_control(&self, _message: CdcControlMessage) -> Result<()> {
+        Ok(())
+    }
+    
     /// Flush the sink
     ///
     /// # Returns
@@ -59,6 +89,18 @@ pub trait CdcSink: Send + Sync {
     ///
     /// Returns an error if the sink cannot be closed
     async fn close(&self) -> Result<()>;
+    
+    /// Check whether the sink's destination is reachable
+    ///
+    /// This is used for readiness checks. The default implementation assumes
+    /// the sink is always reachable.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the sink can accept messages, `false` otherwise
+    async fn is_healthy(&self) -> bool {
+        true
+    }
 }
 
 /// Create a CDC sink from a configuration
@@ -86,13 +128,25 @@ pub fn create_sink(config: &SinkConfig) -> Result<Box<dyn CdcSink>> {
             )?;
             Ok(Box::new(sink))
         }
-        SinkConfig::Postgres { connection_string, schema, batch_size, flush_interval } => {
-            let sink = PostgresSink::new(
+        SinkConfig::Postgres {
+            connection_string,
+            schema,
+            batch_size,
+            flush_interval,
+            pool_size,
+            ca_cert_path,
+            key_column,
+            key_columns,
+        } => {
+            let sink = PostgresSink::with_pool(
                 connection_string,
                 schema,
                 *batch_size,
                 *flush_interval,
-            )?;
+                *pool_size,
+                ca_cert_path.as_deref(),
+            )?
+            .with_key_columns(key_column, key_columns.clone());
             Ok(Box::new(sink))
         }
         SinkConfig::File { path, append, flush_interval } => {
@@ -106,9 +160,12 @@ pub fn create_sink(config: &SinkConfig) -> Result<Box<dyn CdcSink>> {
     }
 }
 
+pub use debshrew_support::CONTROL_MESSAGE_KEY;
+
 /// Kafka CDC sink
 ///
-/// This sink sends CDC messages to a Kafka topic.
+/// This sink sends CDC messages to a Kafka topic. Control messages are sent to
+/// the same topic under [`CONTROL_MESSAGE_KEY`].
 pub struct KafkaSink {
     /// The Kafka producer
     producer: FutureProducer,
@@ -171,6 +228,38 @@ impl KafkaSink {
     }
 }
 
+/// Classify a failed Kafka send
+///
+/// Messages that are too large or malformed, and topics the producer may not
+/// write to, fail the same way on every retry.
+///
+/// # Arguments
+///
+/// * `context` - What was being sent
+/// * `e` - The Kafka error
+///
+/// # Returns
+///
+/// The error, permanent or transient
+fn kafka_send_error(context: &str, e: KafkaError) -> Error {
+    let permanent = matches!(
+        e.rdkafka_error_code(),
+        Some(
+            RDKafkaErrorCode::MessageSizeTooLarge
+                | RDKafkaErrorCode::InvalidMessageSize
+                | RDKafkaErrorCode::InvalidMessage
+                | RDKafkaErrorCode::InvalidRecord
+                | RDKafkaErrorCode::TopicAuthorizationFailed
+        )
+    );
+    
+    if permanent {
+        Error::SinkRejected(format!("{}: {}", context, e))
+    } else {
+        Error::Kafka(format!("{}: {}", context, e))
+    }
+}
+
 #[async_trait]
 impl CdcSink for KafkaSink {
     async fn send(&self, messages: Vec<CdcMessage>) -> Result<()> {
@@ -183,7 +272,7 @@ impl CdcSink for KafkaSink {
                 
                 // Serialize the message to JSON
                 let value = serde_json::to_string(message)
-                    .map_err(|e| Error::Kafka(format!("Failed to serialize message: {}", e)))?;
+                    .map_err(|e| Error::SinkRejected(format!("Failed to serialize message: {}", e)))?;
                 
                 // Send the message to Kafka and wait for the result
                 self.producer.send(
@@ -193,13 +282,29 @@ impl CdcSink for KafkaSink {
                     Duration::from_millis(5000),
                 )
                 .await
-                .map_err(|(e, _)| Error::Kafka(format!("Failed to send message: {}", e)))?;
+                .map_err(|(e, _)| kafka_send_error("Failed to send message", e))?;
             }
         }
         
         Ok(())
     }
     
+    async fn send_control(&self, message: CdcControlMessage) -> Result<()> {
+        let value = serde_json::to_string(&message)
+            .map_err(|e| Error::SinkRejected(format!("Failed to serialize control message: {}", e)))?;
+        
+        self.producer.send(
+            FutureRecord::to(&self.topic)
+                .key(CONTROL_MESSAGE_KEY)
+                .payload(&value),
+            Duration::from_millis(5000),
+        )
+        .await
+        .map_err(|(e, _)| kafka_send_error("Failed to send control message", e))?;
+        
+        Ok(())
+    }
+    
     async fn flush(&self) -> Result<()> {
         self.producer.flush(Duration::from_millis(self.flush_interval))
             .map_err(|e| Error::Kafka(format!("Failed to flush Kafka producer: {}", e)))?;
@@ -213,18 +318,120 @@ impl CdcSink for KafkaSink {
         
         Ok(())
     }
+    
+    async fn is_healthy(&self) -> bool {
+        // The topic metadata can only be fetched while a broker is reachable
+        let producer = self.producer.clone();
+        let topic = self.topic.clone();
+        tokio::task::spawn_blocking(move || {
+            producer.client()
+                .fetch_metadata(Some(&topic), Duration::from_millis(5000))
+                .is_ok()
+        })
+        .await
+        .unwrap_or(false)
+    }
+}
+
+/// Number of attempts to apply a batch when the connection fails
+const POSTGRES_CONNECT_ATTEMPTS: u32 = 3;
+
+/// Delay before the first reconnect, doubled for each further attempt
+const POSTGRES_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// Maximum time to wait for a new PostgreSQL connection or a free pooled one
+const POSTGRES_POOL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Minimum time a flush may take, long enough for every reconnect attempt to wait out the pool
+const POSTGRES_FLUSH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default number of pooled PostgreSQL connections
+pub const DEFAULT_POSTGRES_POOL_SIZE: usize = 4;
+
+/// Default column holding the CDC message key
+pub const DEFAULT_POSTGRES_KEY_COLUMN: &str = "id";
+
+/// Messages buffered by the PostgreSQL sink
+#[derive(Debug, Default)]
+struct PostgresBuffer {
+    /// The buffered messages, in order
+    messages: Vec<CdcMessage>,
+    
+    /// The number of leading messages the synchronizer already considers sent
+    ///
+    /// The remaining messages belong to the send or frame in progress, which
+    /// the synchronizer sends again if it fails.
+    acknowledged: usize,
+}
+
+/// Failure to apply a batch
+enum ApplyFailure {
+    /// The connection failed; the batch can be applied on a new connection
+    Connection(Error),
+    
+    /// The database rejected the batch; the error says whether retrying can help
+    Rejected(Error),
+}
+
+impl ApplyFailure {
+    /// Classify a PostgreSQL error
+    ///
+    /// Besides closed sockets, a server terminating or refusing the session
+    /// reports a connection exception or shutdown error before the socket closes.
+    /// Data exceptions, integrity constraint violations, syntax errors such as
+    /// a missing table or column, and unsupported features fail the same way
+    /// on every retry.
+    fn from_postgres(context: &str, e: tokio_postgres::Error) -> Self {
+        let connection_lost = e.is_closed()
+            || std::error::Error::source(&e).is_some_and(|source| source.is::<std::io::Error>())
+            || e.code().is_some_and(|code| {
+                code.code().starts_with("08")
+                    || [SqlState::ADMIN_SHUTDOWN, SqlState::CRASH_SHUTDOWN, SqlState::CANNOT_CONNECT_NOW].contains(code)
+            });
+        let permanent = e.code().is_some_and(|code| {
+            ["22", "23", "42", "0A"].iter().any(|class| code.code().starts_with(class))
+        });
+        let message = match e.as_db_error() {
+            Some(db_error) => format!("{}: {}", context, db_error),
+            None => format!("{}: {}", context, e),
+        };
+        let error = if permanent {
+            Error::SinkRejected(message)
+        } else {
+            Error::Postgres(message)
+        };
+        if connection_lost {
+            ApplyFailure::Connection(error)
+        } else {
+            ApplyFailure::Rejected(error)
+        }
+    }
 }
 
 /// PostgreSQL CDC sink
 ///
-/// This sink sends CDC messages to a PostgreSQL database.
+/// This sink sends CDC messages to a PostgreSQL database over a pool of
+/// connections, which are opened on first use. Statements are prepared once
+/// per connection and cached, together with the types of the columns they
+/// write, which decide how each JSON value is bound. Once the stream carries control messages,
+/// buffered messages are only applied at block and reorg boundaries, so a
+/// transaction never covers part of a block or reorg.
+///
+/// A batch that fails because the connection was lost is applied again on a
+/// new connection. Messages stay buffered until their batch is committed.
 pub struct PostgresSink {
-    /// The PostgreSQL connection string
-    connection_string: String,
+    /// The connection pool
+    pool: Pool,
     
     /// The PostgreSQL schema
     schema: String,
     
+    /// The column holding the message key, unless overridden for the table
+    key_column: String,
+    
+    /// The key column per table
+    key_columns: BTreeMap<String, String>,
+    
     /// The batch size
     batch_size: usize,
     
@@ -232,12 +439,17 @@ pub struct PostgresSink {
     flush_interval: u64,
     
     /// The message buffer
-    buffer: Arc<TokioMutex<Vec<CdcMessage>>>,
+    buffer: TokioMutex<PostgresBuffer>,
+    
+    /// Whether the stream carries control messages
+    framed: AtomicBool,
 }
 
 impl PostgresSink {
     /// Create a new PostgreSQL sink
     ///
+    /// TLS is used when the connection string sets `sslmode=require`.
+    ///
     /// # Arguments
     ///
     /// * `connection_string` - The PostgreSQL connection string
@@ -251,28 +463,167 @@ impl PostgresSink {
     ///
     /// # Errors
     ///
-    /// Returns an error if the PostgreSQL connection cannot be created
+    /// Returns an error if the connection string is invalid
     pub fn new(
         connection_string: &str,
         schema: &str,
         batch_size: usize,
         flush_interval: u64,
     ) -> Result<Self> {
-        // Validate the connection string by attempting to connect
-        let _client = postgres::Client::connect(connection_string, postgres::NoTls)
-            .map_err(|e| Error::Postgres(format!("Failed to connect to PostgreSQL: {}", e)))?;
+        Self::with_pool(connection_string, schema, batch_size, flush_interval, DEFAULT_POSTGRES_POOL_SIZE, None)
+    }
+    
+    /// Create a new PostgreSQL sink with pool and TLS options
+    ///
+    /// TLS is used when the connection string sets `sslmode=require` or a CA
+    /// certificate is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection_string` - The PostgreSQL connection string
+    /// * `schema` - The PostgreSQL schema
+    /// * `batch_size` - The batch size
+    /// * `flush_interval` - The flush interval in milliseconds
+    /// * `pool_size` - The maximum number of pooled connections
+    /// * `ca_cert_path` - A PEM file with a CA certificate to trust in addition to the system roots
+    ///
+    /// # Returns
+    ///
+    /// A new PostgreSQL sink
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection string is invalid or the TLS configuration cannot be loaded
+    pub fn with_pool(
+        connection_string: &str,
+        schema: &str,
+        batch_size: usize,
+        flush_interval: u64,
+        pool_size: usize,
+        ca_cert_path: Option<&str>,
+    ) -> Result<Self> {
+        let config = connection_string.parse::<tokio_postgres::Config>()
+            .map_err(|e| Error::Postgres(format!("Invalid PostgreSQL connection string: {}", e)))?;
+        let manager_config = ManagerConfig {
+            recycling_method: RecyclingMethod::Fast,
+        };
+        
+        let manager = if config.get_ssl_mode() == SslMode::Require || ca_cert_path.is_some() {
+            Manager::from_config(config, postgres_tls_connector(ca_cert_path)?, manager_config)
+        } else {
+            Manager::from_config(config, NoTls, manager_config)
+        };
+        
+        let pool = Pool::builder(manager)
+            .max_size(pool_size)
+            .runtime(Runtime::Tokio1)
+            .wait_timeout(Some(POSTGRES_POOL_TIMEOUT))
+            .create_timeout(Some(POSTGRES_POOL_TIMEOUT))
+            .build()
+            .map_err(|e| Error::Postgres(format!("Failed to create PostgreSQL connection pool: {}", e)))?;
         
         Ok(Self {
-            connection_string: connection_string.to_string(),
+            pool,
             schema: schema.to_string(),
+            key_column: DEFAULT_POSTGRES_KEY_COLUMN.to_string(),
+            key_columns: BTreeMap::new(),
             batch_size,
             flush_interval,
-            buffer: Arc::new(TokioMutex::new(Vec::new())),
+            buffer: TokioMutex::new(PostgresBuffer::default()),
+            framed: AtomicBool::new(false),
         })
     }
     
+    /// Set the columns holding the message keys
+    ///
+    /// Each table needs a unique constraint on its key column, which is the
+    /// conflict target of the upserts.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_column` - The key column for tables without an override
+    /// * `key_columns` - The key column per table
+    ///
+    /// # Returns
+    ///
+    /// The updated sink
+    pub fn with_key_columns(mut self, key_column: &str, key_columns: BTreeMap<String, String>) -> Self {
+        self.key_column = key_column.to_string();
+        self.key_columns = key_columns;
+        self
+    }
+    
+    /// Get the column holding the message key for a table
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table name
+    ///
+    /// # Returns
+    ///
+    /// The key column
+    fn key_column(&self, table: &str) -> &str {
+        self.key_columns.get(table).unwrap_or(&self.key_column)
+    }
+    
+    /// Apply the buffered messages if the buffer reached the batch size
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The locked message buffer
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the buffer was applied successfully or is not full yet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the messages cannot be applied
+    async fn apply_full_batch(&self, buffer: &mut PostgresBuffer) -> Result<()> {
+        if buffer.messages.len() < self.batch_size {
+            return Ok(());
+        }
+        
+        self.apply_buffer(buffer).await
+    }
+    
+    /// Apply every buffered message
+    ///
+    /// On failure, the acknowledged messages stay buffered. The messages of
+    /// the send or frame in progress are dropped, because the synchronizer
+    /// sends them again.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The locked message buffer
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the messages were applied successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the messages cannot be applied
+    async fn apply_buffer(&self, buffer: &mut PostgresBuffer) -> Result<()> {
+        match self.apply_messages(&buffer.messages).await {
+            Ok(()) => {
+                buffer.messages.clear();
+                buffer.acknowledged = 0;
+                Ok(())
+            }
+            Err(e) => {
+                let acknowledged = buffer.acknowledged;
+                buffer.messages.truncate(acknowledged);
+                Err(e)
+            }
+        }
+    }
+    
     /// Apply CDC messages to a PostgreSQL database
     ///
+    /// The messages are applied in one transaction. If the connection fails,
+    /// the transaction is applied again on a new connection.
+    ///
     /// # Arguments
     ///
     /// * `messages` - The CDC messages to apply
@@ -285,164 +636,362 @@ impl PostgresSink {
     ///
     /// Returns an error if the messages cannot be applied
     async fn apply_messages(&self, messages: &[CdcMessage]) -> Result<()> {
-        // Connect to PostgreSQL
-        let mut client = postgres::Client::connect(&self.connection_string, postgres::NoTls)
-            .map_err(|e| Error::Postgres(format!("Failed to connect to PostgreSQL: {}", e)))?;
+        let mut attempt = 1;
+        loop {
+            match self.apply_transaction(messages).await {
+                Ok(()) => return Ok(()),
+                Err(ApplyFailure::Connection(e)) if attempt < POSTGRES_CONNECT_ATTEMPTS => {
+                    let delay = POSTGRES_RECONNECT_DELAY * 2u32.pow(attempt - 1);
+                    log::warn!("{}; reconnecting in {:?} (attempt {}/{})", e, delay, attempt + 1, POSTGRES_CONNECT_ATTEMPTS);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(ApplyFailure::Connection(e)) | Err(ApplyFailure::Rejected(e)) => return Err(e),
+            }
+        }
+    }
+    
+    /// Apply CDC messages in a single transaction
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - The CDC messages to apply
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the transaction was committed
+    ///
+    /// # Errors
+    ///
+    /// Returns the failure, classified by whether it can be retried on a new connection
+    async fn apply_transaction(&self, messages: &[CdcMessage]) -> std::result::Result<(), ApplyFailure> {
+        let mut client = self.pool.get().await
+            .map_err(|e| ApplyFailure::Connection(Error::Postgres(format!("Failed to connect to PostgreSQL: {}", e))))?;
         
-        // Start a transaction
-        client.batch_execute("BEGIN")
-            .map_err(|e| Error::Postgres(format!("Failed to start transaction: {}", e)))?;
+        let transaction = client.transaction().await
+            .map_err(|e| ApplyFailure::from_postgres("Failed to start transaction", e))?;
         
-        // Process each message
         for message in messages {
-            let table = format!("{}.{}", self.schema, message.payload.table);
+            let key_column = self.key_column(&message.payload.table);
+            let Some((query, values)) = build_statement(&self.schema, key_column, message).map_err(ApplyFailure::Rejected)? else {
+                continue;
+            };
             
-            match message.payload.operation {
-                debshrew_support::CdcOperation::Create => {
-                    // Extract fields from the after state
-                    if let Some(after) = &message.payload.after {
-                        let fields: Vec<String> = after.as_object()
-                            .ok_or_else(|| Error::Postgres("Invalid after state".to_string()))?
-                            .keys()
-                            .map(|k| k.to_string())
-                            .collect();
-                        
-                        // Convert JSON values to strings for PostgreSQL
-                        let values: Vec<String> = fields.iter()
-                            .map(|f| after[f].to_string())
-                            .collect();
-                        
-                        // Build the INSERT statement
-                        let placeholders: Vec<String> = (1..=fields.len())
-                            .map(|i| format!("${}", i))
-                            .collect();
-                        
-                        let query = format!(
-                            "INSERT INTO {} ({}) VALUES ({})",
-                            table,
-                            fields.join(", "),
-                            placeholders.join(", ")
-                        );
-                        
-                        // Execute the INSERT statement
-                        let params: Vec<&(dyn ToSql + Sync)> = values.iter()
-                            .map(|v| v as &(dyn ToSql + Sync))
-                            .collect();
-                        
-                        client.execute(&query, &params)
-                            .map_err(|e| Error::Postgres(format!("Failed to execute INSERT: {}", e)))?;
-                    }
-                }
-                debshrew_support::CdcOperation::Update => {
-                    // Extract fields from the after state
-                    if let Some(after) = &message.payload.after {
-                        let fields: Vec<String> = after.as_object()
-                            .ok_or_else(|| Error::Postgres("Invalid after state".to_string()))?
-                            .keys()
-                            .map(|k| k.to_string())
-                            .collect();
-                        
-                        // Convert JSON values to strings for PostgreSQL
-                        let values: Vec<String> = fields.iter()
-                            .map(|f| after[f].to_string())
-                            .collect();
-                        
-                        // Build the UPDATE statement
-                        let set_clauses: Vec<String> = fields.iter()
-                            .enumerate()
-                            .map(|(i, f)| format!("{} = ${}", f, i + 1))
-                            .collect();
-                        
-                        let query = format!(
-                            "UPDATE {} SET {} WHERE id = ${}",
-                            table,
-                            set_clauses.join(", "),
-                            fields.len() + 1
-                        );
-                        
-                        // Execute the UPDATE statement
-                        let mut params: Vec<&(dyn ToSql + Sync)> = values.iter()
-                            .map(|v| v as &(dyn ToSql + Sync))
-                            .collect();
-                        
-                        let key = &message.payload.key;
-                        params.push(key as &(dyn ToSql + Sync));
-                        
-                        client.execute(&query, &params)
-                            .map_err(|e| Error::Postgres(format!("Failed to execute UPDATE: {}", e)))?;
-                    }
-                }
-                debshrew_support::CdcOperation::Delete => {
-                    // Build the DELETE statement
-                    let query = format!("DELETE FROM {} WHERE id = $1", table);
-                    
-                    // Execute the DELETE statement
-                    client.execute(&query, &[&message.payload.key])
-                        .map_err(|e| Error::Postgres(format!("Failed to execute DELETE: {}", e)))?;
-                }
-            }
+            let statement = transaction.prepare_cached(&query).await
+                .map_err(|e| ApplyFailure::from_postgres("Failed to prepare statement", e))?;
+            
+            // The prepared statement carries the types of the target columns
+            let values = statement.params().iter()
+                .zip(&values)
+                .map(|(ty, value)| ColumnValue::from_json(value, ty))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| ApplyFailure::Rejected(Error::SinkRejected(format!("{} in table {}", e, message.payload.table))))?;
+            let params: Vec<&(dyn ToSql + Sync)> = values.iter()
+                .map(|v| v as &(dyn ToSql + Sync))
+                .collect();
+            
+            let context = match message.payload.operation {
+                CdcOperation::Create | CdcOperation::Update => "Failed to execute upsert",
+                CdcOperation::Delete => "Failed to execute DELETE",
+            };
+            transaction.execute(&statement, &params).await
+                .map_err(|e| ApplyFailure::from_postgres(context, e))?;
         }
         
-        // Commit the transaction
-        client.batch_execute("COMMIT")
-            .map_err(|e| Error::Postgres(format!("Failed to commit transaction: {}", e)))?;
+        transaction.commit().await
+            .map_err(|e| ApplyFailure::from_postgres("Failed to commit transaction", e))
+    }
+}
+
+/// Build the TLS connector for PostgreSQL connections
+///
+/// # Arguments
+///
+/// * `ca_cert_path` - A PEM file with a CA certificate to trust in addition to the system roots
+///
+/// # Returns
+///
+/// The TLS connector
+///
+/// # Errors
+///
+/// Returns an error if the certificate cannot be loaded
+fn postgres_tls_connector(ca_cert_path: Option<&str>) -> Result<MakeTlsConnector> {
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(path) = ca_cert_path {
+        let pem = std::fs::read(path)
+            .map_err(|e| Error::Postgres(format!("Failed to read CA certificate {}: {}", path, e)))?;
+        let certificate = native_tls::Certificate::from_pem(&pem)
+            .map_err(|e| Error::Postgres(format!("Invalid CA certificate {}: {}", path, e)))?;
+        builder.add_root_certificate(certificate);
+    }
+    
+    let connector = builder.build()
+        .map_err(|e| Error::Postgres(format!("Failed to create TLS connector: {}", e)))?;
+    Ok(MakeTlsConnector::new(connector))
+}
+
+/// A JSON value bound to a column of a PostgreSQL table
+///
+/// Values are sent in the text format of the column type, so the server
+/// parses numerics and timestamps itself.
+#[derive(Debug)]
+struct ColumnValue(Option<String>);
+
+impl ColumnValue {
+    /// Map a JSON value to the type of its column
+    ///
+    /// Booleans, integers and floats must be JSON values of that kind, or
+    /// strings holding one, and integers must fit the width of their column,
+    /// so an out-of-range value fails here rather than in PostgreSQL. Bytea columns take hex strings, with or without a
+    /// `0x` prefix. JSON and JSONB columns take any value, including nested
+    /// objects and arrays. Other columns take strings as they are and any
+    /// other value as JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The JSON value
+    /// * `ty` - The column type
+    ///
+    /// # Returns
+    ///
+    /// The value to bind
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the mismatch if the value cannot be stored in the column
+    fn from_json(value: &serde_json::Value, ty: &Type) -> std::result::Result<Self, String> {
+        use serde_json::Value;
+        
+        let mismatch = || format!("Cannot store {} in a column of type {}", value, ty);
+        let text = match (value, ty) {
+            (Value::Null, _) => return Ok(Self(None)),
+            (_, &Type::JSON) | (_, &Type::JSONB) => value.to_string(),
+            (Value::Bool(b), &Type::BOOL) => b.to_string(),
+            (Value::String(s), &Type::BOOL) => s.parse::<bool>().map_err(|_| mismatch())?.to_string(),
+            (Value::Number(n), &Type::INT2 | &Type::INT4 | &Type::INT8) => {
+                n.as_i64().filter(|i| fits_integer_column(*i, ty)).ok_or_else(mismatch)?.to_string()
+            }
+            (Value::String(s), &Type::INT2 | &Type::INT4 | &Type::INT8) => {
+                s.parse::<i64>().ok().filter(|i| fits_integer_column(*i, ty)).ok_or_else(mismatch)?.to_string()
+            }
+            (Value::Number(n), &Type::FLOAT4 | &Type::FLOAT8 | &Type::NUMERIC) => n.to_string(),
+            (Value::String(s), &Type::FLOAT4 | &Type::FLOAT8) => s.parse::<f64>().map_err(|_| mismatch())?.to_string(),
+            (Value::String(s), &Type::NUMERIC) => s.clone(),
+            (Value::String(s), &Type::BYTEA) => {
+                let bytes = hex::decode(s.strip_prefix("0x").unwrap_or(s)).map_err(|_| mismatch())?;
+                format!("\\x{}", hex::encode(bytes))
+            }
+            (_, &Type::BOOL | &Type::INT2 | &Type::INT4 | &Type::INT8 | &Type::FLOAT4 | &Type::FLOAT8 | &Type::NUMERIC | &Type::BYTEA) => {
+                return Err(mismatch());
+            }
+            (Value::String(s), _) => s.clone(),
+            (_, _) => value.to_string(),
+        };
         
-        Ok(())
+        Ok(Self(Some(text)))
+    }
+}
+
+/// Check that an integer fits an integer column
+///
+/// # Arguments
+///
+/// * `value` - The integer
+/// * `ty` - The column type, one of `smallint`, `integer` and `bigint`
+///
+/// # Returns
+///
+/// Whether the column can hold the integer
+fn fits_integer_column(value: i64, ty: &Type) -> bool {
+    match *ty {
+        Type::INT2 => i16::try_from(value).is_ok(),
+        Type::INT4 => i32::try_from(value).is_ok(),
+        _ => true,
+    }
+}
+
+impl ToSql for ColumnValue {
+    fn to_sql(&self, _ty: &Type, out: &mut BytesMut) -> std::result::Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        match &self.0 {
+            Some(text) => {
+                out.extend_from_slice(text.as_bytes());
+                Ok(IsNull::No)
+            }
+            None => Ok(IsNull::Yes),
+        }
+    }
+    
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+    
+    fn encode_format(&self, _ty: &Type) -> Format {
+        Format::Text
+    }
+    
+    to_sql_checked!();
+}
+
+/// Build the SQL statement applying a CDC message
+///
+/// Creates and updates are upserts on the key column and deletes match zero
+/// or one rows, so applying a message again leaves the table unchanged. The
+/// key column is set from the message key; a field of the same name in the
+/// after state is ignored. Every identifier is quoted, so names are matched
+/// exactly as they appear in the message.
+///
+/// # Arguments
+///
+/// * `schema` - The PostgreSQL schema
+/// * `key_column` - The column holding the message key
+/// * `message` - The CDC message
+///
+/// # Returns
+///
+/// The statement and its parameter values, or None if the message changes nothing
+///
+/// # Errors
+///
+/// Returns an error if the after state is not an object
+fn build_statement(schema: &str, key_column: &str, message: &CdcMessage) -> Result<Option<(String, Vec<serde_json::Value>)>> {
+    let table = format!("{}.{}", quote_identifier(schema), quote_identifier(&message.payload.table));
+    let key = quote_identifier(key_column);
+    
+    match message.payload.operation {
+        CdcOperation::Create | CdcOperation::Update => {
+            // Extract fields from the after state
+            let Some(after) = &message.payload.after else {
+                return Ok(None);
+            };
+            let fields: Vec<String> = after.as_object()
+                .ok_or_else(|| Error::SinkRejected("Invalid after state".to_string()))?
+                .keys()
+                .filter(|k| k.as_str() != key_column)
+                .map(|k| k.to_string())
+                .collect();
+            
+            let key_value = serde_json::Value::String(message.payload.key.clone());
+            let values: Vec<serde_json::Value> = std::iter::once(key_value)
+                .chain(fields.iter().map(|f| after[f].clone()))
+                .collect();
+            
+            let columns: Vec<String> = std::iter::once(key.clone())
+                .chain(fields.iter().map(|f| quote_identifier(f)))
+                .collect();
+            let placeholders: Vec<String> = (1..=columns.len())
+                .map(|i| format!("${}", i))
+                .collect();
+            let conflict_action = if fields.is_empty() {
+                "DO NOTHING".to_string()
+            } else {
+                let set_clauses: Vec<String> = fields.iter()
+                    .map(|f| quote_identifier(f))
+                    .map(|f| format!("{} = EXCLUDED.{}", f, f))
+                    .collect();
+                format!("DO UPDATE SET {}", set_clauses.join(", "))
+            };
+            
+            let query = format!(
+                "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) {}",
+                table,
+                columns.join(", "),
+                placeholders.join(", "),
+                key,
+                conflict_action
+            );
+            
+            Ok(Some((query, values)))
+        }
+        CdcOperation::Delete => {
+            let query = format!("DELETE FROM {} WHERE {} = $1", table, key);
+            Ok(Some((query, vec![serde_json::Value::String(message.payload.key.clone())])))
+        }
     }
 }
 
+/// Quote a PostgreSQL identifier, doubling any embedded quotes
+///
+/// # Arguments
+///
+/// * `identifier` - The schema, table or column name
+///
+/// # Returns
+///
+/// The quoted identifier
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
 #[async_trait]
 impl CdcSink for PostgresSink {
     async fn send(&self, messages: Vec<CdcMessage>) -> Result<()> {
-        // Add messages to the buffer
         let mut buffer = self.buffer.lock().await;
-        buffer.extend(messages);
+        let framed = self.framed.load(Ordering::SeqCst);
         
-        // Flush the buffer if it exceeds the batch size
-        if buffer.len() >= self.batch_size {
-            let messages_to_send = buffer.clone();
-            buffer.clear();
-            
-            // Release the lock before applying messages
-            drop(buffer);
-            
-            // Apply the messages
-            self.apply_messages(&messages_to_send).await?;
+        // Without framing, each send is retried on its own
+        if !framed {
+            buffer.acknowledged = buffer.messages.len();
+        }
+        buffer.messages.extend(messages);
+        
+        // Flush the buffer if it exceeds the batch size, unless a block boundary is coming
+        if !framed {
+            self.apply_full_batch(&mut buffer).await?;
         }
         
         Ok(())
     }
     
-    async fn flush(&self) -> Result<()> {
-        // Get the buffered messages
-        let messages_to_send = {
-            let mut buffer = self.buffer.lock().await;
-            let messages = buffer.clone();
-            buffer.clear();
-            messages
-        };
+    async fn send_control(&self, message: CdcControlMessage) -> Result<()> {
+        self.framed.store(true, Ordering::SeqCst);
         
-        // Apply the messages if there are any
-        if !messages_to_send.is_empty() {
-            // Use a timeout based on flush_interval
-            let timeout = Duration::from_millis(self.flush_interval);
-            let apply_future = self.apply_messages(&messages_to_send);
-            
-            match tokio::time::timeout(timeout, apply_future).await {
-                Ok(result) => result?,
-                Err(_) => return Err(Error::Postgres(format!("Flush operation timed out after {} ms", self.flush_interval))),
+        let mut buffer = self.buffer.lock().await;
+        match message.control {
+            CdcControl::EndBlock { .. } | CdcControl::ReorgEnd { .. } => self.apply_full_batch(&mut buffer).await,
+            CdcControl::BeginBlock { .. } | CdcControl::ReorgBegin { .. } => {
+                buffer.acknowledged = buffer.messages.len();
+                Ok(())
             }
         }
+    }
+    
+    async fn flush(&self) -> Result<()> {
+        let mut buffer = self.buffer.lock().await;
+        if buffer.messages.is_empty() {
+            return Ok(());
+        }
         
-        Ok(())
+        // Every buffered message was accepted, so a failed flush keeps them all
+        buffer.acknowledged = buffer.messages.len();
+        
+        // Leave room for reconnecting, which a short flush interval would cut off
+        let timeout = Duration::from_millis(self.flush_interval).max(POSTGRES_FLUSH_TIMEOUT);
+        match tokio::time::timeout(timeout, self.apply_buffer(&mut buffer)).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Postgres(format!("Flush operation timed out after {:?}", timeout))),
+        }
     }
     
     async fn close(&self) -> Result<()> {
         // Flush the buffer before closing
         self.flush().await?;
+        self.pool.close();
         
         Ok(())
     }
+    
+    async fn is_healthy(&self) -> bool {
+        let check = async {
+            let client = self.pool.get().await.ok()?;
+            client.simple_query("SELECT 1").await.ok()
+        };
+        tokio::time::timeout(Duration::from_millis(5000), check)
+            .await
+            .ok()
+            .flatten()
+            .is_some()
+    }
 }
 
 /// File CDC sink
@@ -497,6 +1046,24 @@ impl FileSink {
     }
 }
 
+/// Classify a failed file operation
+///
+/// # Arguments
+///
+/// * `context` - The operation that failed
+/// * `e` - The I/O error
+///
+/// # Returns
+///
+/// The error, permanent or transient
+fn file_error(context: &str, e: std::io::Error) -> Error {
+    if crate::error::is_transient_io_error(&e) {
+        Error::File(format!("{}: {}", context, e))
+    } else {
+        Error::SinkRejected(format!("{}: {}", context, e))
+    }
+}
+
 #[async_trait]
 impl CdcSink for FileSink {
     async fn send(&self, messages: Vec<CdcMessage>) -> Result<()> {
@@ -506,16 +1073,28 @@ impl CdcSink for FileSink {
         for message in messages {
             // Serialize the message to JSON
             let json = serde_json::to_string(&message)
-                .map_err(|e| Error::File(format!("Failed to serialize message: {}", e)))?;
+                .map_err(|e| Error::SinkRejected(format!("Failed to serialize message: {}", e)))?;
             
             // Write the message to the file
             writeln!(file, "{}", json)
-                .map_err(|e| Error::File(format!("Failed to write to file: {}", e)))?;
+                .map_err(|e| file_error("Failed to write to file", e))?;
         }
         
         Ok(())
     }
     
+    async fn send_control(&self, message: CdcControlMessage) -> Result<()> {
+        let json = serde_json::to_string(&message)
+            .map_err(|e| Error::SinkRejected(format!("Failed to serialize control message: {}", e)))?;
+        
+        let mut file = self.file.lock()
+            .map_err(|e| Error::File(format!("Failed to lock file: {}", e)))?;
+        writeln!(file, "{}", json)
+            .map_err(|e| file_error("Failed to write to file", e))?;
+        
+        Ok(())
+    }
+    
     async fn flush(&self) -> Result<()> {
         // Use a timeout based on flush_interval
         let timeout = Duration::from_millis(self.flush_interval);
@@ -525,7 +1104,7 @@ impl CdcSink for FileSink {
                 .map_err(|e| Error::File(format!("Failed to lock file: {}", e)))?;
             
             file.flush()
-                .map_err(|e| Error::File(format!("Failed to flush file: {}", e)))?;
+                .map_err(|e| file_error("Failed to flush file", e))?;
             
             Ok(())
         };
@@ -587,6 +1166,19 @@ impl CdcSink for ConsoleSink {
         Ok(())
     }
     
+    async fn send_control(&self, message: CdcControlMessage) -> Result<()> {
+        let json = if self.pretty_print {
+            serde_json::to_string_pretty(&message)
+        } else {
+            serde_json::to_string(&message)
+        }
+        .map_err(|e| Error::Generic(format!("Failed to serialize control message: {}", e)))?;
+        
+        println!("{}", json);
+        
+        Ok(())
+    }
+    
     async fn flush(&self) -> Result<()> {
         // No need to flush the console
         Ok(())
@@ -621,6 +1213,11 @@ impl CdcSink for NullSink {
         Ok(())
     }
     
+    async fn send_control(&self, _message: CdcControlMessage) -> Result<()> {
+        // Discard all control messages
+        Ok(())
+    }
+    
     async fn flush(&self) -> Result<()> {
         // No need to flush
         Ok(())
@@ -655,6 +1252,7 @@ mod tests {
                 block_height: 123,
                 block_hash: "000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d".to_string(),
                 transaction_id: None,
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,
@@ -737,4 +1335,322 @@ mod tests {
         assert!(contents.contains("value1"));
         assert!(contents.contains("42"));
     }
+
+    /// Environment variable with a PostgreSQL connection string to run the PostgreSQL sink against
+    ///
+    /// The PostgreSQL sink tests are ignored by default; run them against a scratch database with
+    /// `DEBSHREW_TEST_POSTGRES_URL="host=localhost user=postgres" cargo test -p debshrew -- --ignored`
+    const POSTGRES_URL_VAR: &str = "DEBSHREW_TEST_POSTGRES_URL";
+
+    /// Connect to the test database and create a fresh schema with the given tables
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema to (re)create
+    /// * `tables` - SQL run after the schema is created
+    ///
+    /// # Returns
+    ///
+    /// The connection string and an admin client for the test database
+    async fn postgres_test_schema(schema: &str, tables: &str) -> (String, tokio_postgres::Client) {
+        let url = std::env::var(POSTGRES_URL_VAR)
+            .unwrap_or_else(|_| panic!("{} must be set to run the PostgreSQL sink tests", POSTGRES_URL_VAR));
+        let (admin, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+        admin.batch_execute(&format!(
+            "DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema}; {tables}",
+        )).await.unwrap();
+        (url, admin)
+    }
+
+    #[test]
+    fn test_postgres_statements() {
+        let mut message = create_test_message();
+        let (query, values) = build_statement("cdc", "id", &message).unwrap().unwrap();
+        assert_eq!(
+            query,
+            r#"INSERT INTO "cdc"."test_table" ("id", "field1", "field2") VALUES ($1, $2, $3) "#.to_string() +
+            r#"ON CONFLICT ("id") DO UPDATE SET "field1" = EXCLUDED."field1", "field2" = EXCLUDED."field2""#
+        );
+        assert_eq!(values, vec![serde_json::json!("test_key"), serde_json::json!("value1"), serde_json::json!(42)]);
+
+        // Updates are the same upsert, and the key column comes from the message key
+        message.payload.operation = CdcOperation::Update;
+        let (update, values) = build_statement("cdc", "field1", &message).unwrap().unwrap();
+        assert_eq!(
+            update,
+            r#"INSERT INTO "cdc"."test_table" ("field1", "field2") VALUES ($1, $2) "#.to_string() +
+            r#"ON CONFLICT ("field1") DO UPDATE SET "field2" = EXCLUDED."field2""#
+        );
+        assert_eq!(values, vec![serde_json::json!("test_key"), serde_json::json!(42)]);
+
+        message.payload.after = Some(serde_json::json!({"id": "test_key"}));
+        let (query, values) = build_statement("cdc", "id", &message).unwrap().unwrap();
+        assert_eq!(query, r#"INSERT INTO "cdc"."test_table" ("id") VALUES ($1) ON CONFLICT ("id") DO NOTHING"#);
+        assert_eq!(values, vec![serde_json::json!("test_key")]);
+
+        message.payload.operation = CdcOperation::Delete;
+        let (query, values) = build_statement("cdc", "address", &message).unwrap().unwrap();
+        assert_eq!(query, r#"DELETE FROM "cdc"."test_table" WHERE "address" = $1"#);
+        assert_eq!(values, vec![serde_json::json!("test_key")]);
+
+        // Names are quoted as identifiers, so they cannot break out of the statement
+        message.payload.table = r#"t"; DROP TABLE x; --"#.to_string();
+        let (query, _) = build_statement("Mixed Case", r#"k"ey"#, &message).unwrap().unwrap();
+        assert_eq!(query, r#"DELETE FROM "Mixed Case"."t""; DROP TABLE x; --" WHERE "k""ey" = $1"#);
+        message.payload.operation = CdcOperation::Update;
+        message.payload.after = Some(serde_json::json!({r#"a"b"#: 1}));
+        let (query, _) = build_statement("cdc", "id", &message).unwrap().unwrap();
+        assert!(query.contains(r#"("id", "a""b")"#), "{}", query);
+        assert!(query.contains(r#"SET "a""b" = EXCLUDED."a""b""#), "{}", query);
+        message.payload.table = "test_table".to_string();
+
+        message.payload.operation = CdcOperation::Create;
+        message.payload.after = None;
+        assert!(build_statement("cdc", "id", &message).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_postgres_column_values() {
+        let text = |value: serde_json::Value, ty: &Type| ColumnValue::from_json(&value, ty).unwrap().0;
+
+        assert_eq!(text(serde_json::json!(null), &Type::INT8), None);
+        assert_eq!(text(serde_json::json!(true), &Type::BOOL).unwrap(), "true");
+        assert_eq!(text(serde_json::json!("false"), &Type::BOOL).unwrap(), "false");
+        assert_eq!(text(serde_json::json!(9223372036854775807i64), &Type::INT8).unwrap(), "9223372036854775807");
+        assert_eq!(text(serde_json::json!(-32768), &Type::INT2).unwrap(), "-32768");
+        assert_eq!(text(serde_json::json!("2147483647"), &Type::INT4).unwrap(), "2147483647");
+        assert_eq!(text(serde_json::json!("-7"), &Type::INT4).unwrap(), "-7");
+        assert_eq!(text(serde_json::json!(1.5), &Type::FLOAT8).unwrap(), "1.5");
+        assert_eq!(text(serde_json::json!("123456789012345678901234567890.5"), &Type::NUMERIC).unwrap(), "123456789012345678901234567890.5");
+        assert_eq!(text(serde_json::json!("abc"), &Type::TEXT).unwrap(), "abc");
+        assert_eq!(text(serde_json::json!(42), &Type::TEXT).unwrap(), "42");
+        assert_eq!(text(serde_json::json!("abc"), &Type::JSONB).unwrap(), "\"abc\"");
+        assert_eq!(text(serde_json::json!({"a": [1, 2]}), &Type::JSONB).unwrap(), r#"{"a":[1,2]}"#);
+        assert_eq!(text(serde_json::json!("0xDEAD"), &Type::BYTEA).unwrap(), "\\xdead");
+        assert_eq!(text(serde_json::json!("beef"), &Type::BYTEA).unwrap(), "\\xbeef");
+
+        for (value, ty) in [
+            (serde_json::json!(1.5), Type::INT8),
+            (serde_json::json!(18446744073709551615u64), Type::INT8),
+            (serde_json::json!("9223372036854775808"), Type::INT8),
+            (serde_json::json!(2147483648u32), Type::INT4),
+            (serde_json::json!("-32769"), Type::INT2),
+            (serde_json::json!("abc"), Type::INT4),
+            (serde_json::json!(1), Type::BOOL),
+            (serde_json::json!("0xzz"), Type::BYTEA),
+            (serde_json::json!({"a": 1}), Type::NUMERIC),
+        ] {
+            assert!(ColumnValue::from_json(&value, &ty).is_err(), "{} as {}", value, ty);
+        }
+    }
+
+    #[test]
+    fn test_postgres_key_columns() {
+        let sink = PostgresSink::new("host=localhost", "public", 2, 5000).unwrap();
+        assert_eq!(sink.key_column("balances"), DEFAULT_POSTGRES_KEY_COLUMN);
+
+        let key_columns = BTreeMap::from([("balances".to_string(), "address".to_string())]);
+        let sink = sink.with_key_columns("key", key_columns);
+        assert_eq!(sink.key_column("balances"), "address");
+        assert_eq!(sink.key_column("tokens"), "key");
+    }
+
+    #[tokio::test]
+    async fn test_postgres_sink_keeps_buffered_messages_when_unreachable() {
+        let sink = PostgresSink::new("host=127.0.0.1 port=1 user=debshrew connect_timeout=1", "public", 2, 5000).unwrap();
+
+        // The first message is buffered and acknowledged; the second fills the batch, which fails
+        sink.send(vec![create_test_message()]).await.unwrap();
+        let error = sink.send(vec![create_test_message()]).await.unwrap_err();
+        assert!(matches!(error, Error::Postgres(_)));
+        assert!(error.is_transient());
+
+        // Only the failed send is dropped, as the synchronizer sends it again
+        assert_eq!(sink.buffer.lock().await.messages.len(), 1);
+
+        // A failed flush keeps every buffered message
+        assert!(sink.flush().await.is_err());
+        assert_eq!(sink.buffer.lock().await.messages.len(), 1);
+        assert!(!sink.is_healthy().await);
+
+        assert!(PostgresSink::new("host=localhost sslmode=bogus", "public", 2, 5000).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DEBSHREW_TEST_POSTGRES_URL"]
+    async fn test_postgres_sink_reconnects() {
+        let (url, admin) = postgres_test_schema(
+            "debshrew_sink_test",
+            "CREATE TABLE debshrew_sink_test.test_table (id TEXT PRIMARY KEY, field1 TEXT, field2 TEXT);",
+        ).await;
+
+        let sink = PostgresSink::new(&format!("{} application_name=debshrew_sink_test", url), "debshrew_sink_test", 1, 5000).unwrap();
+        assert!(sink.is_healthy().await);
+        sink.send(vec![create_test_message()]).await.unwrap();
+
+        // Kill the pooled connection; the next batch is applied on a new one
+        admin.execute(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE application_name = 'debshrew_sink_test'",
+            &[],
+        ).await.unwrap();
+        let mut message = create_test_message();
+        message.payload.key = "other_key".to_string();
+        sink.send(vec![message]).await.unwrap();
+        sink.close().await.unwrap();
+
+        let rows = admin.query_one("SELECT COUNT(*) FROM debshrew_sink_test.test_table", &[]).await.unwrap();
+        assert_eq!(rows.get::<_, i64>(0), 2);
+
+        // A flush reconnects as well, however short the flush interval
+        assert!(POSTGRES_FLUSH_TIMEOUT > POSTGRES_POOL_TIMEOUT * POSTGRES_CONNECT_ATTEMPTS);
+        let sink = PostgresSink::new(&format!("{} application_name=debshrew_sink_test", url), "debshrew_sink_test", 100, 1).unwrap();
+        let mut message = create_test_message();
+        message.payload.key = "flushed_key".to_string();
+        sink.send(vec![message.clone()]).await.unwrap();
+        sink.flush().await.unwrap();
+        admin.execute(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE application_name = 'debshrew_sink_test'",
+            &[],
+        ).await.unwrap();
+        message.payload.key = "closed_key".to_string();
+        sink.send(vec![message]).await.unwrap();
+        sink.close().await.unwrap();
+
+        let rows = admin.query_one("SELECT COUNT(*) FROM debshrew_sink_test.test_table", &[]).await.unwrap();
+        assert_eq!(rows.get::<_, i64>(0), 4);
+        admin.batch_execute("DROP SCHEMA debshrew_sink_test CASCADE").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DEBSHREW_TEST_POSTGRES_URL"]
+    async fn test_postgres_sink_replays_converge() {
+        let (url, admin) = postgres_test_schema(
+            "debshrew_upsert_test",
+            "CREATE TABLE debshrew_upsert_test.test_table (address TEXT PRIMARY KEY, field1 TEXT, field2 TEXT);",
+        ).await;
+
+        let message = |operation, key: &str, field1: &str| {
+            let mut message = create_test_message();
+            message.payload.operation = operation;
+            message.payload.key = key.to_string();
+            message.payload.after = Some(serde_json::json!({"field1": field1, "field2": 42}));
+            message
+        };
+        let messages = vec![
+            message(CdcOperation::Create, "a", "first"),
+            message(CdcOperation::Create, "b", "first"),
+            message(CdcOperation::Update, "a", "second"),
+            message(CdcOperation::Delete, "b", "first"),
+            // An update of a row that was never created inserts it
+            message(CdcOperation::Update, "c", "first"),
+        ];
+
+        let key_columns = BTreeMap::from([("test_table".to_string(), "address".to_string())]);
+        let sink = PostgresSink::new(&url, "debshrew_upsert_test", 100, 5000).unwrap()
+            .with_key_columns("id", key_columns);
+        let mut contents = Vec::new();
+        for _ in 0..2 {
+            sink.send(messages.clone()).await.unwrap();
+            sink.flush().await.unwrap();
+            let rows = admin.query("SELECT address, field1 FROM debshrew_upsert_test.test_table ORDER BY address", &[])
+                .await
+                .unwrap();
+            contents.push(rows.iter().map(|row| (row.get::<_, String>(0), row.get::<_, String>(1))).collect::<Vec<_>>());
+        }
+        sink.close().await.unwrap();
+
+        assert_eq!(contents[0], vec![
+            ("a".to_string(), "second".to_string()),
+            ("c".to_string(), "first".to_string()),
+        ]);
+        assert_eq!(contents[0], contents[1]);
+        admin.batch_execute("DROP SCHEMA debshrew_upsert_test CASCADE").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DEBSHREW_TEST_POSTGRES_URL"]
+    async fn test_postgres_sink_maps_column_types() {
+        let (url, admin) = postgres_test_schema("debshrew_types_test", "
+            CREATE TABLE debshrew_types_test.test_table (
+                id BIGINT PRIMARY KEY,
+                name TEXT,
+                amount NUMERIC,
+                ratio DOUBLE PRECISION,
+                active BOOLEAN,
+                metadata JSONB,
+                script BYTEA,
+                note TEXT
+            );
+        ").await;
+
+        let mut message = create_test_message();
+        message.payload.key = "840000".to_string();
+        message.payload.after = Some(serde_json::json!({
+            "name": "abc",
+            "amount": "21000000.00000001",
+            "ratio": 0.25,
+            "active": true,
+            "metadata": {"tags": ["a", "b"], "count": 2},
+            "script": "0x0014abcd",
+            "note": null
+        }));
+        let sink = PostgresSink::new(&url, "debshrew_types_test", 1, 5000).unwrap();
+        sink.send(vec![message.clone()]).await.unwrap();
+
+        let row = admin.query_one(
+            "SELECT id, name, amount::text, ratio, active, metadata::text, script, note FROM debshrew_types_test.test_table",
+            &[],
+        ).await.unwrap();
+        assert_eq!(row.get::<_, i64>(0), 840000);
+        assert_eq!(row.get::<_, String>(1), "abc");
+        assert_eq!(row.get::<_, String>(2), "21000000.00000001");
+        assert_eq!(row.get::<_, f64>(3), 0.25);
+        assert!(row.get::<_, bool>(4));
+        assert_eq!(row.get::<_, String>(5), r#"{"tags": ["a", "b"], "count": 2}"#);
+        assert_eq!(row.get::<_, Vec<u8>>(6), vec![0x00, 0x14, 0xab, 0xcd]);
+        assert_eq!(row.get::<_, Option<String>>(7), None);
+
+        // A value that does not fit its column, or a missing column, is rejected for good
+        message.payload.after = Some(serde_json::json!({"active": "maybe"}));
+        let error = sink.send(vec![message.clone()]).await.unwrap_err();
+        assert!(matches!(error, Error::SinkRejected(_)), "{}", error);
+        assert!(error.to_string().contains("type bool"), "{}", error);
+
+        message.payload.after = Some(serde_json::json!({"missing": 1}));
+        let error = sink.send(vec![message]).await.unwrap_err();
+        assert!(matches!(error, Error::SinkRejected(_)), "{}", error);
+        assert!(!error.is_transient());
+        sink.close().await.unwrap();
+        admin.batch_execute("DROP SCHEMA debshrew_types_test CASCADE").await.unwrap();
+    }
+
+    #[test]
+    fn test_file_sink_writes_control_messages_in_order() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.json");
+        let sink = FileSink::new(file_path.to_str().unwrap(), false, 1000).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        let control = |control| CdcControlMessage {
+            source: "debshrew".to_string(),
+            timestamp: 0,
+            control,
+        };
+        rt.block_on(async {
+            sink.send_control(control(CdcControl::BeginBlock { height: 123, hash: "00".to_string(), message_count: 1 })).await.unwrap();
+            sink.send(vec![create_test_message()]).await.unwrap();
+            sink.send_control(control(CdcControl::EndBlock { height: 123, hash: "00".to_string(), message_count: 1 })).await.unwrap();
+            sink.close().await.unwrap();
+        });
+
+        let contents = std::fs::read_to_string(file_path).unwrap();
+        let lines: Vec<serde_json::Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "BEGIN_BLOCK");
+        assert_eq!(lines[1]["payload"]["table"], "test_table");
+        assert_eq!(lines[1]["header"]["inverse"], false);
+        assert_eq!(lines[2]["type"], "END_BLOCK");
+        assert_eq!(lines[2]["message_count"], 1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,439 @@
+//! Block sources
+//!
+//! This module defines the block source abstraction, which decides when the
+//! synchronizer should check metashrew for a new tip. The polling source simply
+//! waits for a fixed interval. The stream source subscribes to a newline
+//! delimited JSON stream of tip events pushed by metashrew, so new blocks are
+//! picked up as soon as they are indexed, and falls back to polling whenever
+//! the stream is unavailable.
+
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use url::Url;
+
+/// Default time without any data on the tip stream before metashrew is polled anyway
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum delay between attempts to reopen a failing tip stream
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Tip event
+///
+/// A tip event announces a newly indexed block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TipEvent {
+    /// The block height
+    pub height: u32,
+
+    /// The block hash
+    pub hash: String,
+}
+
+/// Block source trait
+///
+/// A block source tells the synchronizer when to check metashrew for a new tip.
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Wait until metashrew should be checked for a new tip
+    ///
+    /// # Returns
+    ///
+    /// The tip event that caused the wake-up, or None if the wait ended for any
+    /// other reason, such as a timer expiring or the stream dropping
+    async fn wait(&mut self) -> Option<TipEvent>;
+
+    /// Get the name of the block source, for logging
+    ///
+    /// # Returns
+    ///
+    /// The name of the block source
+    fn name(&self) -> &'static str;
+}
+
+/// Polling block source
+///
+/// Wakes the synchronizer after a fixed interval.
+#[derive(Debug, Clone)]
+pub struct PollingBlockSource {
+    /// The polling interval
+    interval: Duration,
+}
+
+impl PollingBlockSource {
+    /// Create a new polling block source
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The polling interval in milliseconds
+    ///
+    /// # Returns
+    ///
+    /// A new polling block source
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: Duration::from_millis(interval),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockSource for PollingBlockSource {
+    async fn wait(&mut self) -> Option<TipEvent> {
+        time::sleep(self.interval).await;
+        None
+    }
+
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+}
+
+/// Streaming block source
+///
+/// Subscribes to an HTTP endpoint that streams tip events as newline
+/// delimited JSON (`{"height": 840000, "hash": "..."}`), one event per line.
+/// Empty lines are ignored and can be used as heartbeats. While the stream is
+/// unavailable, the source wakes the synchronizer at the polling interval.
+/// When the stream drops it wakes the synchronizer right away so no tip is
+/// missed while reconnecting. Reconnects after a failed attempt, or after a
+/// stream that closed without sending anything, back off exponentially from
+/// the polling interval up to 30 seconds.
+#[derive(Debug)]
+pub struct StreamBlockSource {
+    /// The HTTP client
+    client: Client,
+
+    /// The URL of the tip stream
+    url: Url,
+
+    /// The open stream, if connected
+    response: Option<Response>,
+
+    /// Bytes received but not yet terminated by a newline
+    buffer: Vec<u8>,
+
+    /// The source used while the stream is unavailable
+    fallback: PollingBlockSource,
+
+    /// The maximum time to wait for stream data before polling anyway
+    idle_timeout: Duration,
+
+    /// Whether the open stream has sent any data
+    received_data: bool,
+
+    /// The number of consecutive connections that failed or sent nothing
+    failures: u32,
+
+    /// When the stream may be reopened, while backing off
+    next_connect: Option<Instant>,
+}
+
+impl StreamBlockSource {
+    /// Create a new streaming block source
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the tip stream
+    /// * `polling_interval` - The polling interval in milliseconds used while the stream is unavailable
+    ///
+    /// # Returns
+    ///
+    /// A new streaming block source
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid or the HTTP client cannot be built
+    pub fn new(url: &str, polling_interval: u64) -> Result<Self> {
+        let url = Url::parse(url)
+            .map_err(|e| Error::MetashrewClient(format!("Invalid tip stream URL: {}", e)))?;
+
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| Error::MetashrewClient(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            url,
+            response: None,
+            buffer: Vec::new(),
+            fallback: PollingBlockSource::new(polling_interval),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            received_data: false,
+            failures: 0,
+            next_connect: None,
+        })
+    }
+
+    /// Set the idle timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait for stream data before polling anyway
+    pub fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Check whether the stream is currently open
+    ///
+    /// # Returns
+    ///
+    /// True if the stream is connected, false otherwise
+    pub fn is_connected(&self) -> bool {
+        self.response.is_some()
+    }
+
+    /// Open the tip stream
+    async fn connect(&mut self) {
+        match self.client.get(self.url.clone()).send().await {
+            Ok(response) if response.status().is_success() => {
+                info!("Subscribed to tip stream at {}", self.url);
+                self.response = Some(response);
+                self.buffer.clear();
+                self.received_data = false;
+            }
+            Ok(response) => {
+                warn!("Tip stream at {} returned {}, falling back to polling", self.url, response.status());
+            }
+            Err(e) => {
+                warn!("Failed to open tip stream at {}: {}, falling back to polling", self.url, e);
+            }
+        }
+    }
+
+    /// Handle the stream closing or failing to open
+    ///
+    /// A stream that sent data is reopened right away. Otherwise the next
+    /// attempt is delayed, doubling the delay with every consecutive failure.
+    fn disconnected(&mut self) {
+        self.response = None;
+        if self.received_data {
+            self.failures = 0;
+            self.next_connect = None;
+            return;
+        }
+
+        self.failures += 1;
+        let delay = self.fallback.interval
+            .saturating_mul(1 << (self.failures - 1).min(16))
+            .min(MAX_RECONNECT_DELAY.max(self.fallback.interval));
+        debug!("Reopening tip stream at {} in {:?} (failure {})", self.url, delay, self.failures);
+        self.next_connect = Some(Instant::now() + delay);
+    }
+
+    /// Take the latest complete event from the buffer
+    ///
+    /// Events that arrived together are coalesced, since the synchronizer only
+    /// needs to know about the newest tip.
+    fn take_buffered_event(&mut self) -> Option<TipEvent> {
+        let end = self.buffer.iter().rposition(|byte| *byte == b'\n')?;
+        let lines: Vec<u8> = self.buffer.drain(..=end).collect();
+
+        let mut latest = None;
+        for line in lines.split(|byte| *byte == b'\n') {
+            let line = String::from_utf8_lossy(line);
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<TipEvent>(line) {
+                Ok(event) => latest = Some(event),
+                Err(e) => warn!("Ignoring invalid tip event '{}': {}", line, e),
+            }
+        }
+
+        latest
+    }
+}
+
+#[async_trait]
+impl BlockSource for StreamBlockSource {
+    async fn wait(&mut self) -> Option<TipEvent> {
+        if self.response.is_none() {
+            if self.next_connect.is_none_or(|at| Instant::now() >= at) {
+                self.connect().await;
+                if self.response.is_none() {
+                    self.disconnected();
+                }
+            }
+            if self.response.is_none() {
+                return self.fallback.wait().await;
+            }
+        }
+
+        loop {
+            if let Some(event) = self.take_buffered_event() {
+                debug!("Tip event at height {}", event.height);
+                return Some(event);
+            }
+
+            let response = self.response.as_mut()?;
+            match time::timeout(self.idle_timeout, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => {
+                    self.received_data = true;
+                    self.buffer.extend_from_slice(&chunk);
+                }
+                Ok(Ok(None)) => {
+                    warn!("Tip stream at {} closed, falling back to polling", self.url);
+                    self.disconnected();
+                    return None;
+                }
+                Ok(Err(e)) => {
+                    warn!("Tip stream at {} failed: {}, falling back to polling", self.url, e);
+                    self.disconnected();
+                    return None;
+                }
+                Err(_) => {
+                    debug!("No tip events for {:?}, polling metashrew", self.idle_timeout);
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "stream"
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Stub tip stream server
+    ///
+    /// Accepts a single connection and writes every line received on the
+    /// returned channel to it. Dropping the sender closes the stream.
+    pub(crate) async fn spawn_tip_stream() -> (String, mpsc::UnboundedSender<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/tips", listener.local_addr().unwrap());
+        let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            // Read the request headers
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                if read == 0 {
+                    return;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+
+            while let Some(line) = receiver.recv().await {
+                if socket.write_all(line.as_bytes()).await.is_err() {
+                    return;
+                }
+                let _ = socket.flush().await;
+            }
+        });
+
+        (url, sender)
+    }
+
+    #[tokio::test]
+    async fn test_polling_block_source() {
+        let mut source = PollingBlockSource::new(10);
+        assert_eq!(source.name(), "polling");
+
+        let start = Instant::now();
+        assert_eq!(source.wait().await, None);
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[tokio::test]
+    async fn test_stream_block_source_receives_events() {
+        let (url, sender) = spawn_tip_stream().await;
+        let mut source = StreamBlockSource::new(&url, 60_000).unwrap();
+
+        sender.send("{\"height\": 5, \"hash\": \"aa\"}\n".to_string()).unwrap();
+        let event = time::timeout(Duration::from_secs(5), source.wait()).await.unwrap();
+        assert_eq!(event, Some(TipEvent { height: 5, hash: "aa".to_string() }));
+        assert!(source.is_connected());
+
+        // Heartbeats and split writes are handled, and bursts are coalesced
+        sender.send("\n{\"height\": 6, \"hash\": \"bb\"}\n{\"height\": 7,".to_string()).unwrap();
+        sender.send(" \"hash\": \"cc\"}\n".to_string()).unwrap();
+        let mut latest = None;
+        while latest.as_ref().map(|event: &TipEvent| event.height) != Some(7) {
+            latest = time::timeout(Duration::from_secs(5), source.wait()).await.unwrap();
+            assert!(latest.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_block_source_falls_back_to_polling() {
+        let (url, sender) = spawn_tip_stream().await;
+        let mut source = StreamBlockSource::new(&url, 20).unwrap();
+
+        sender.send("{\"height\": 1, \"hash\": \"aa\"}\n".to_string()).unwrap();
+        assert!(source.wait().await.is_some());
+
+        // The stream drops: wake up right away so the tip is polled
+        drop(sender);
+        let event = time::timeout(Duration::from_secs(5), source.wait()).await.unwrap();
+        assert_eq!(event, None);
+        assert!(!source.is_connected());
+
+        // The server is gone: reconnecting fails and the source polls instead
+        let event = time::timeout(Duration::from_secs(5), source.wait()).await.unwrap();
+        assert_eq!(event, None);
+        assert!(!source.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_stream_block_source_backs_off_from_empty_streams() {
+        // A server that accepts every connection and closes it right away
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/tips", listener.local_addr().unwrap());
+        let connections = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let mut buffer = [0u8; 1024];
+                let _ = socket.read(&mut buffer).await;
+                let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n").await;
+            }
+        });
+
+        let mut source = StreamBlockSource::new(&url, 20).unwrap();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(500) {
+            assert_eq!(time::timeout(Duration::from_secs(5), source.wait()).await.unwrap(), None);
+        }
+
+        // Reconnects back off at 20, 40, 80, 160 and 320 ms instead of looping
+        let connections = connections.load(std::sync::atomic::Ordering::SeqCst);
+        assert!((2..=6).contains(&connections), "{} connections", connections);
+    }
+
+    #[tokio::test]
+    async fn test_stream_block_source_idle_timeout() {
+        let (url, _sender) = spawn_tip_stream().await;
+        let mut source = StreamBlockSource::new(&url, 60_000).unwrap();
+        source.set_idle_timeout(Duration::from_millis(50));
+
+        let event = time::timeout(Duration::from_secs(5), source.wait()).await.unwrap();
+        assert_eq!(event, None);
+        assert!(source.is_connected());
+    }
+}
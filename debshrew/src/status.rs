@@ -0,0 +1,200 @@
+//! Synchronizer status reporting
+//!
+//! This module defines the status snapshot published by the block
+//! synchronizer while it runs. The status lives behind a cloneable handle, so
+//! the HTTP server and other observers can read the live state without holding
+//! on to the synchronizer itself.
+
+use crate::error::Error;
+use debshrew_support::utils::now_ms;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// Last reorg handled by the synchronizer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReorgStatus {
+    /// The height the synchronizer was at when the reorg was detected
+    pub detected_height: u32,
+
+    /// The common ancestor the synchronizer rolled back to
+    pub common_ancestor: u32,
+
+    /// The time the reorg was handled, in milliseconds since the epoch
+    pub timestamp: u64,
+}
+
+/// Last error encountered by the synchronizer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorStatus {
+    /// The error message
+    pub message: String,
+
+    /// The time the error occurred, in milliseconds since the epoch
+    pub timestamp: u64,
+}
+
+/// Synchronizer status
+///
+/// A snapshot of the synchronizer's progress, as served on `/status`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// Whether the synchronizer is running
+    pub running: bool,
+
+    /// Whether processing was paused by an operator
+    pub paused: bool,
+
+    /// The last processed block height
+    pub current_height: u32,
+
+    /// The height metashrew reports as indexed, as of the last poll
+    pub indexed_height: Option<u32>,
+
+    /// The highest height whose block hash metashrew can serve, as of the last poll
+    pub available_height: Option<u32>,
+
+    /// The number of indexed blocks not processed yet
+    pub lag: Option<u32>,
+
+    /// The highest block sent to the tentative stream
+    pub emitted_height: Option<u32>,
+
+    /// The highest block sent to the finalized stream
+    pub finalized_height: Option<u32>,
+
+    /// The lowest block height held in the block cache
+    pub cache_lowest_height: Option<u32>,
+
+    /// The highest block height held in the block cache
+    pub cache_highest_height: Option<u32>,
+
+    /// The last reorg handled
+    pub last_reorg: Option<ReorgStatus>,
+
+    /// The last error encountered
+    pub last_error: Option<ErrorStatus>,
+
+    /// The block the synchronizer keeps failing at, if it is failing
+    pub failing_height: Option<u32>,
+
+    /// The number of consecutive failures at the failing block
+    pub consecutive_failures: u32,
+}
+
+/// Shared handle to the synchronizer status
+///
+/// The synchronizer updates the status as it goes; clones of the handle see
+/// every update.
+#[derive(Debug, Clone, Default)]
+pub struct StatusHandle {
+    /// The current status
+    status: Arc<RwLock<SyncStatus>>,
+}
+
+impl StatusHandle {
+    /// Create a new status handle
+    ///
+    /// # Returns
+    ///
+    /// A new status handle with an empty status
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a snapshot of the current status
+    ///
+    /// # Returns
+    ///
+    /// A copy of the current status
+    pub fn snapshot(&self) -> SyncStatus {
+        match self.status.read() {
+            Ok(status) => status.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Update the status
+    ///
+    /// The lag is recomputed after every update.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The function applying the update
+    pub fn update<F: FnOnce(&mut SyncStatus)>(&self, update: F) {
+        let mut status = match self.status.write() {
+            Ok(status) => status,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        update(&mut status);
+        status.lag = status
+            .indexed_height
+            .map(|height| height.saturating_sub(status.current_height));
+    }
+
+    /// Record a handled reorg
+    ///
+    /// # Arguments
+    ///
+    /// * `detected_height` - The height the reorg was detected at
+    /// * `common_ancestor` - The common ancestor the synchronizer rolled back to
+    pub fn record_reorg(&self, detected_height: u32, common_ancestor: u32) {
+        self.update(|status| {
+            status.last_reorg = Some(ReorgStatus {
+                detected_height,
+                common_ancestor,
+                timestamp: now_ms(),
+            });
+        });
+    }
+
+    /// Record an error
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error that occurred
+    pub fn record_error(&self, error: &Error) {
+        self.update(|status| {
+            status.last_error = Some(ErrorStatus {
+                message: error.to_string(),
+                timestamp: now_ms(),
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_handle_updates_are_shared() {
+        let handle = StatusHandle::new();
+        let reader = handle.clone();
+        assert_eq!(reader.snapshot(), SyncStatus::default());
+
+        handle.update(|status| {
+            status.current_height = 90;
+            status.indexed_height = Some(100);
+        });
+        let status = reader.snapshot();
+        assert_eq!(status.current_height, 90);
+        assert_eq!(status.lag, Some(10));
+
+        // The lag never goes negative when metashrew falls behind
+        handle.update(|status| status.indexed_height = Some(80));
+        assert_eq!(reader.snapshot().lag, Some(0));
+    }
+
+    #[test]
+    fn test_status_handle_records_reorg_and_error() {
+        let handle = StatusHandle::new();
+        handle.record_reorg(100, 97);
+        handle.record_error(&Error::Sink("sink unavailable".to_string()));
+
+        let status = handle.snapshot();
+        let reorg = status.last_reorg.unwrap();
+        assert_eq!((reorg.detected_height, reorg.common_ancestor), (100, 97));
+        assert_eq!(status.last_error.unwrap().message, "Sink error: sink unavailable");
+    }
+}
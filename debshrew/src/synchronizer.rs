@@ -3,39 +3,38 @@
 //! This module provides the block synchronizer, which is responsible for
 //! synchronizing with metashrew, processing blocks, and handling reorgs.
 
+use crate::admin::AdminHandle;
 use crate::block::BlockCache;
+use crate::checkpoint::{Checkpoint, CheckpointStore};
 use crate::WasmRuntime;
-use crate::client::MetashrewClient;
 use crate::error::{Error, Result};
+use crate::error_policy::{DeadLetter, DeadLetterQueue, RetryPolicy};
+use crate::metrics;
+use crate::observer::SyncObserver;
+use crate::runtime::ViewPrefetchCache;
 use crate::sink::CdcSink;
+use crate::source::BlockSource;
+use crate::status::StatusHandle;
+use crate::tip::{is_block_not_available, Tip, TipTracker};
+use crate::traits::{MetashrewClientLike, ViewCall, ViewProviderLike};
 use async_trait::async_trait;
-use debshrew_support::BlockMetadata;
+use debshrew_support::utils::now_ms;
+use debshrew_support::{BlockMetadata, CdcControl, CdcControlMessage, CdcMessage};
+use futures::StreamExt;
 use std::time::{SystemTime, UNIX_EPOCH};
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Notify};
+use tokio::task::{JoinHandle, JoinSet};
 use tokio::time;
 
-/// Maximum length for logged response bodies (in characters)
-const MAX_RESPONSE_LOG_LENGTH: usize = 1000;
-
-/// Truncate a response string for logging purposes
-fn truncate_response_for_logging(response: &str) -> String {
-    if response.len() <= MAX_RESPONSE_LOG_LENGTH {
-        response.to_string()
-    } else {
-        format!("{}... [truncated, total length: {} chars]",
-                &response[..MAX_RESPONSE_LOG_LENGTH],
-                response.len())
-    }
-}
-
 /// Block synchronizer
 ///
 /// The block synchronizer is responsible for synchronizing with metashrew,
 /// processing blocks, and handling reorgs.
-pub struct BlockSynchronizer<C: MetashrewClient> {
+pub struct BlockSynchronizer<C: MetashrewClientLike> {
     /// The metashrew client
     client: Arc<C>,
     
@@ -45,29 +44,171 @@ pub struct BlockSynchronizer<C: MetashrewClient> {
     /// The CDC sink
     sink: Arc<Box<dyn CdcSink>>,
     
+    /// The sink for the finalized stream, when it is separate from the main sink
+    finalized_sink: Option<Arc<Box<dyn CdcSink>>>,
+    
     /// The block cache
     cache: Arc<Mutex<BlockCache>>,
     
     /// The current block height
     current_height: u32,
     
+    /// The block height the synchronizer started from
+    starting_height: u32,
+    
     /// Whether the synchronizer is running
     running: bool,
     
+    /// Handle used to request a graceful stop from another task
+    stop_handle: StopHandle,
+    
     /// The polling interval in milliseconds
     polling_interval: u64,
+    
+    /// The source that wakes the synchronizer on new tips (None polls at the polling interval)
+    block_source: Option<Box<dyn BlockSource>>,
+    
+    /// The number of upcoming blocks to prefetch during catch-up
+    prefetch_depth: usize,
+    
+    /// Prefetched view results shared with the WASM runtime
+    view_cache: ViewPrefetchCache,
+    
+    /// The view prefetches in flight
+    prefetch_tasks: JoinSet<()>,
+    
+    /// The number of confirmations a block needs before it is emitted (0 emits immediately)
+    finality_depth: u32,
+    
+    /// The highest block whose CDC messages have been sent to the tentative stream
+    emitted_height: Option<u32>,
+    
+    /// The highest block whose CDC messages have been sent to the finalized stream
+    finalized_height: Option<u32>,
+    
+    /// The last block to emit before stopping (None runs indefinitely)
+    end_height: Option<u32>,
+    
+    /// The store the checkpoint is written to on exit
+    checkpoint_store: Option<CheckpointStore>,
+    
+    /// Tracks which heights metashrew has indexed and can serve hashes for
+    tip_tracker: TipTracker,
+    
+    /// The status published for the HTTP server
+    status: StatusHandle,
+    
+    /// How transient errors are retried
+    retry_policy: RetryPolicy,
+    
+    /// Where blocks the transform fails on are recorded (None halts on transform failures)
+    dead_letter_queue: Option<DeadLetterQueue>,
+    
+    /// The block the synchronizer keeps failing at, if it is failing
+    failing_height: Option<u32>,
+    
+    /// The number of consecutive failures at the failing block
+    consecutive_failures: u32,
+    
+    /// The observers notified of synchronizer events
+    observers: Vec<Arc<dyn SyncObserver>>,
+    
+    /// Whether block and reorg boundaries are marked with control messages
+    control_messages: bool,
+    
+    /// Handle used by operators to pause, resume and rewind the synchronizer
+    admin: AdminHandle,
+}
+
+/// Handle for stopping a running synchronizer
+///
+/// Requesting a stop lets the synchronizer finish the block it is working on,
+/// flush and close its sinks and write the checkpoint before `run` returns.
+/// The handle can be cloned and used from other tasks, such as a signal handler.
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    /// Whether a stop has been requested
+    stopped: Arc<AtomicBool>,
+    
+    /// Wakes the synchronizer while it waits for the next poll
+    notify: Arc<Notify>,
+}
+
+impl StopHandle {
+    /// Create a new stop handle
+    ///
+    /// # Returns
+    ///
+    /// A new stop handle
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Request a graceful stop
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+    
+    /// Check whether a stop has been requested
+    ///
+    /// # Returns
+    ///
+    /// True if a stop has been requested, false otherwise
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+    
+    /// Wait until a stop is requested
+    pub async fn stopped(&self) {
+        while !self.is_stopped() {
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// The CDC messages of a block, as sent to a stream
+struct EmittedBlock {
+    /// The block height
+    height: u32,
+    
+    /// The block hash, hex-encoded
+    hash: String,
+    
+    /// The CDC messages of the block
+    messages: Vec<CdcMessage>,
+}
+
+/// A sink send that is still in flight
+struct PendingSend {
+    /// The block that was processed
+    height: u32,
+    
+    /// The hash of the processed block, hex-encoded
+    hash: String,
+    
+    /// The CDC messages of the processed block, kept for observers
+    messages: Vec<CdcMessage>,
+    
+    /// The block sent to the tentative stream, if any
+    emitted: Option<u32>,
+    
+    /// The highest block sent to the finalized stream, if any
+    finalized: Option<u32>,
+    
+    /// The task sending the messages, with the results for the tentative and the finalized stream
+    handle: JoinHandle<(Result<()>, Result<()>)>,
 }
 
-impl<C: MetashrewClient> BlockSynchronizer<C> {
-    // Track the last time we logged a progress report
-    #[allow(dead_code)]
-    fn log_progress_report(&self, metashrew_height: u32, actual_block_count: u32) {
-        log::info!("Synchronization progress: current_height={}, metashrew_height={}, actual_block_count={}, progress={}%",
+impl<C: MetashrewClientLike + 'static> BlockSynchronizer<C> {
+    /// Log a synchronization progress report
+    fn log_progress_report(&self, tip: &Tip) {
+        log::info!("Synchronization progress: current_height={}, indexed_height={}, available_height={:?}, progress={}%",
             self.current_height,
-            metashrew_height,
-            actual_block_count,
-            if metashrew_height > 0 {
-                (self.current_height as f64 / metashrew_height as f64 * 100.0).round()
+            tip.indexed_height,
+            tip.available_height,
+            if tip.indexed_height > 0 {
+                (self.current_height as f64 / tip.indexed_height as f64 * 100.0).round()
             } else {
                 100.0
             }
@@ -77,7 +218,7 @@ impl<C: MetashrewClient> BlockSynchronizer<C> {
     ///
     /// # Arguments
     ///
-    /// * `client` - The metashrew client
+    /// * `client` - The metashrew client, which also serves the transform's view calls
     /// * `runtime` - The WASM runtime
     /// * `sink` - The CDC sink
     /// * `cache_size` - The block cache size
@@ -89,26 +230,45 @@ impl<C: MetashrewClient> BlockSynchronizer<C> {
     /// # Errors
     ///
     /// Returns an error if the block synchronizer cannot be created
-    pub fn new(client: C, runtime: WasmRuntime, sink: Box<dyn CdcSink>, cache_size: u32) -> Result<Self> {
+    pub fn new(client: C, mut runtime: WasmRuntime, sink: Box<dyn CdcSink>, cache_size: u32) -> Result<Self> {
         let cache = BlockCache::new(cache_size)?;
         
-        // Make sure the runtime has the correct metashrew URL
-        // This is a no-op if the runtime already has the correct URL
-        let metashrew_url = client.get_url().to_string();
-        if runtime.get_metashrew_url() != metashrew_url {
-            log::info!("Updating runtime metashrew URL to {}", metashrew_url);
-            // Since we can't update the URL directly, we'd need to create a new runtime
-            // with the correct URL in a real implementation
-        }
+        // Serve the transform's view calls from the same client as the blocks
+        let client = Arc::new(client);
+        debug!("Using metashrew client {}", client.get_identifier());
+        runtime.set_view_provider(client.clone() as Arc<dyn ViewProviderLike>);
+        
+        let view_cache = runtime.view_prefetch_cache();
         
         Ok(Self {
-            client: Arc::new(client),
+            client,
             runtime: Arc::new(Mutex::new(runtime)),
             sink: Arc::new(sink),
+            finalized_sink: None,
             cache: Arc::new(Mutex::new(cache)),
             current_height: 0,
+            starting_height: 0,
             running: false,
+            stop_handle: StopHandle::new(),
             polling_interval: 1000,
+            block_source: None,
+            prefetch_depth: 8,
+            view_cache,
+            prefetch_tasks: JoinSet::new(),
+            finality_depth: 0,
+            emitted_height: None,
+            finalized_height: None,
+            end_height: None,
+            checkpoint_store: None,
+            tip_tracker: TipTracker::new(),
+            status: StatusHandle::new(),
+            retry_policy: RetryPolicy::default(),
+            dead_letter_queue: None,
+            failing_height: None,
+            consecutive_failures: 0,
+            observers: Vec::new(),
+            control_messages: false,
+            admin: AdminHandle::new(),
         })
     }
     
@@ -121,6 +281,144 @@ impl<C: MetashrewClient> BlockSynchronizer<C> {
         self.polling_interval = interval;
     }
     
+    /// Set the block source
+    ///
+    /// The block source decides when metashrew is checked for a new tip. Without
+    /// one, the synchronizer polls at the polling interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The block source
+    pub fn set_block_source(&mut self, source: Box<dyn BlockSource>) {
+        info!("Using {} block source", source.name());
+        self.block_source = Some(source);
+    }
+    
+    /// Set the prefetch depth
+    ///
+    /// During catch-up, block hashes for up to this many upcoming blocks are
+    /// fetched concurrently ahead of the transform. A depth of 1 disables
+    /// prefetching.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of blocks to prefetch
+    pub fn set_prefetch_depth(&mut self, depth: usize) {
+        self.prefetch_depth = depth.max(1);
+    }
+    
+    /// Enable or disable view prefetching
+    ///
+    /// When enabled, the view calls made by the transform for a block are
+    /// replayed against the upcoming heights within the prefetch window, and the
+    /// results are served to the transform without another round trip.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to prefetch view results
+    pub fn set_prefetch_views(&mut self, enabled: bool) {
+        self.view_cache.set_enabled(enabled);
+    }
+    
+    /// Set the finality depth
+    ///
+    /// With a non-zero depth, the CDC messages for block H are held in the
+    /// block cache and only sent to the sink once the metashrew tip reaches
+    /// H + depth. Reorgs within that window are resolved internally, so the
+    /// sink never sees inverse messages for them. The block cache must be
+    /// larger than the finality depth.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of confirmations required before emitting a block
+    pub fn set_finality_depth(&mut self, depth: u32) {
+        self.finality_depth = depth;
+    }
+    
+    /// Set a separate sink for the finalized stream
+    ///
+    /// With a finalized sink attached, the main sink becomes a tentative stream:
+    /// it receives every block as soon as it is transformed, along with inverse
+    /// messages on reorg, while the finalized sink only receives blocks once
+    /// they pass the finality depth. Both streams are fed from the same
+    /// transform run.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - The sink for the finalized stream
+    pub fn set_finalized_sink(&mut self, sink: Box<dyn CdcSink>) {
+        self.finalized_sink = Some(Arc::new(sink));
+    }
+    
+    /// Set the end block height
+    ///
+    /// Once the CDC messages for this block have been emitted, the synchronizer
+    /// flushes and closes its sinks, writes the checkpoint and returns from
+    /// `run`.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The last block height to emit
+    pub fn set_end_height(&mut self, height: u32) {
+        self.end_height = Some(height);
+    }
+    
+    /// Set the checkpoint store
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The store the checkpoint is written to
+    pub fn set_checkpoint_store(&mut self, store: CheckpointStore) {
+        self.checkpoint_store = Some(store);
+    }
+    
+    /// Set the retry policy for transient errors
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The retry policy
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+    
+    /// Set the dead-letter queue
+    ///
+    /// With a dead-letter queue, a block the transform fails on is recorded
+    /// there and processed as if it produced no CDC messages, instead of
+    /// halting the synchronizer.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - The dead-letter queue
+    pub fn set_dead_letter_queue(&mut self, queue: DeadLetterQueue) {
+        self.dead_letter_queue = Some(queue);
+    }
+    
+    /// Enable or disable control messages
+    ///
+    /// When enabled, the messages of every block are framed by `BEGIN_BLOCK`
+    /// and `END_BLOCK` control messages, and the inverse messages of a reorg by
+    /// `REORG_BEGIN` and `REORG_END`, on every stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to send control messages
+    pub fn set_control_messages(&mut self, enabled: bool) {
+        self.control_messages = enabled;
+    }
+    
+    /// Register an observer
+    ///
+    /// Observers are notified of processed blocks, reorgs, errors and reaching
+    /// the metashrew tip, in the order they were registered.
+    ///
+    /// # Arguments
+    ///
+    /// * `observer` - The observer
+    pub fn add_observer(&mut self, observer: Arc<dyn SyncObserver>) {
+        self.observers.push(observer);
+    }
+    
     /// Set the starting block height
     ///
     /// # Arguments
@@ -128,11 +426,17 @@ impl<C: MetashrewClient> BlockSynchronizer<C> {
     /// * `height` - The starting block height
     pub fn set_starting_height(&mut self, height: u32) {
         self.current_height = height;
+        self.starting_height = height;
     }
     
     /// Run the block synchronizer
     ///
-    /// This method starts the block synchronizer and runs until stopped.
+    /// This method starts the block synchronizer and runs until stopped, either
+    /// through the stop handle or by reaching the end height. Stops take effect
+    /// between blocks, after which the sinks are flushed and closed and the
+    /// checkpoint is written. When the synchronizer halts on an error, the
+    /// sinks are still flushed and closed once, and a failure to do so is only
+    /// logged.
     ///
     /// # Returns
     ///
@@ -143,653 +447,2556 @@ impl<C: MetashrewClient> BlockSynchronizer<C> {
     /// Returns an error if the synchronizer encounters an error
     pub async fn run(&mut self) -> Result<()> {
         self.running = true;
+        self.status.update(|status| status.running = true);
         
         // We'll keep the current height as set by set_starting_height
         // This allows starting from genesis (height 0) or any other height
         info!("Starting at block height {}", self.current_height);
         
-        // Main synchronization loop
+        let result = match self.sync_loop().await {
+            Ok(()) => self.finish().await.inspect_err(|e| self.notify(|observer| observer.on_error(e))),
+            Err(e) => {
+                // Still try to flush what the sinks have buffered
+                if let Err(finish_error) = self.finish().await {
+                    error!("Failed to shut down synchronizer cleanly: {}", finish_error);
+                }
+                Err(e)
+            }
+        };
+        
+        if let Err(e) = &result {
+            self.status.record_error(e);
+        }
+        self.status.update(|status| status.running = false);
+        
+        result
+    }
+    
+    /// Run the synchronization loop until stopped or the end height is reached
+    async fn sync_loop(&mut self) -> Result<()> {
         while self.running {
-            // Poll metashrew for the latest height
-            let metashrew_height = self.client.get_height().await?;
-            log::info!("Metashrew reported height: {}", metashrew_height);
+            if self.stop_handle.is_stopped() {
+                info!("Stop requested, shutting down at block height {}", self.current_height);
+                break;
+            }
             
-            // Get the actual block count to avoid processing non-existent blocks
-            let actual_block_count = self.get_actual_block_count().await?;
-            log::info!("Actual block count: {}", actual_block_count);
+            // Stop once a bounded run has emitted its last block
+            if self.reached_end_height() {
+                info!("Reached end height, stopping at block {}", self.current_height);
+                break;
+            }
             
-            // Log a progress report
-            self.log_progress_report(metashrew_height, actual_block_count);
+            // Apply operator commands between blocks
+            if let Err(e) = self.apply_rewind().await {
+                self.handle_failure(e).await?;
+                continue;
+            }
+            if self.admin.is_paused() {
+                self.wait_while_paused().await;
+                continue;
+            }
             
-            // Check if there's a significant discrepancy between metashrew_height and actual_block_count
-            if metashrew_height > actual_block_count && actual_block_count <= self.current_height {
-                log::warn!("Significant discrepancy detected: metashrew_height={}, actual_block_count={}, current_height={}",
-                          metashrew_height, actual_block_count, self.current_height);
-                
-                // If we're stuck at the same height for multiple iterations, try incrementing by 1
-                // This allows us to make progress even when there's a discrepancy
-                let target_height = self.current_height + 1;
-                
-                if target_height <= metashrew_height {
-                    log::info!("Attempting to process next block at height {} despite discrepancy", target_height);
-                    
-                    // Process the next block
-                    self.process_block(target_height).await?;
-                    self.current_height = target_height;
-                    
-                    // Sleep for the polling interval and continue
-                    time::sleep(Duration::from_millis(self.polling_interval)).await;
+            match self.sync_step().await {
+                Ok(()) => self.clear_failures(),
+                Err(e) => {
+                    // Transient errors are retried after a backoff, anything else stops the loop
+                    self.handle_failure(e).await?;
                     continue;
                 }
             }
             
-            // Normal case: use the minimum of metashrew_height and actual_block_count
-            let target_height = std::cmp::min(metashrew_height, actual_block_count);
-            log::info!("Using target height: {} (min of {} and {})",
-                      target_height, metashrew_height, actual_block_count);
+            self.publish_status().await;
             
-            // Check if we need to process new blocks
-            // Special case: if current_height is 0 and we're starting from genesis, always process block 0
-            // regardless of target_height
-            if target_height > self.current_height || self.current_height == 0 {
-                info!("Processing blocks {} to {} (metashrew height: {}, actual block count: {})",
-                      self.current_height + 1, target_height, metashrew_height, actual_block_count);
-                
-                // Process new blocks
-                for height in (self.current_height + 1)..=target_height {
-                    self.process_block(height).await?;
-                    self.current_height = height;
+            // Wait for the next tip, unless a bounded run just emitted its last block or a command is pending
+            if !self.reached_end_height() && !self.admin.interrupts() {
+                self.wait_for_next_poll().await;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Run one synchronization step
+    ///
+    /// Polls the metashrew tip, then either processes new blocks or checks the
+    /// current block for a reorg.
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the step completed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if metashrew, the transform or a sink fails
+    async fn sync_step(&mut self) -> Result<()> {
+        // Poll metashrew for the highest block whose hash can be served
+        let tip = self.tip_tracker.poll(self.client.as_ref()).await?;
+        self.log_progress_report(&tip);
+        self.status.update(|status| {
+            status.indexed_height = Some(tip.indexed_height);
+            status.available_height = tip.available_height;
+        });
+        
+        let available_height = match tip.available_height {
+            Some(height) => height,
+            None => {
+                info!("No blocks available from metashrew yet (indexed height {})", tip.indexed_height);
+                return Ok(());
+            }
+        };
+        
+        // Never process past the end of a bounded run
+        let target_height = match self.end_height {
+            Some(end_height) => available_height.min(end_height),
+            None => available_height,
+        };
+        
+        // Check if we need to process new blocks
+        // Special case: if current_height is 0 and we're starting from genesis, always process block 0
+        // regardless of target_height
+        if target_height > self.current_height || self.current_height == 0 {
+            info!("Processing blocks {} to {} (indexed height: {}, available height: {})",
+                  self.current_height + 1, target_height, tip.indexed_height, available_height);
+            
+            // Process new blocks
+            let start_height = self.current_height;
+            self.catch_up(self.current_height + 1, target_height).await?;
+            
+            if self.current_height > start_height && self.current_height >= available_height {
+                let height = self.current_height;
+                self.notify(|observer| observer.on_caught_up(height));
+            }
+        } else if available_height < self.current_height {
+            // The indexer is behind us, e.g. after a restart; wait until it
+            // serves our current height again before comparing hashes
+            info!("Metashrew serves blocks up to {}, behind current height {}; waiting",
+                  available_height, self.current_height);
+        } else if self.current_height > 0 {
+            // Check for reorgs by comparing the hash of the current block
+            // Get the cached hash for the current height
+            let cached_hash = {
+                let cache = self.cache.lock().await;
+                if let Some(cached_block) = cache.get_block_at_height(self.current_height) {
+                    Some(cached_block.metadata.hash.clone())
+                } else {
+                    None
                 }
-            } else if self.current_height > 0 {
-                // Check for reorgs by comparing the hash of the current block
-                // Get the cached hash for the current height
-                let cached_hash = {
-                    let cache = self.cache.lock().await;
-                    if let Some(cached_block) = cache.get_block_at_height(self.current_height) {
-                        Some(cached_block.metadata.hash.clone())
-                    } else {
-                        None
-                    }
-                }; // Lock is released here
-                
-                // If we have a cached hash, compare it with the current hash from metashrew
-                if let Some(cached_hash) = cached_hash {
-                    // Get the current hash from metashrew
-                    match self.client.get_block_hash(self.current_height).await {
-                        Ok(current_hash) => {
-                            let current_hash_hex = hex::encode(&current_hash);
+            }; // Lock is released here
+            
+            // If we have a cached hash, compare it with the current hash from metashrew
+            if let Some(cached_hash) = cached_hash {
+                // Get the current hash from metashrew
+                match self.client.get_block_hash(self.current_height).await {
+                    Ok(current_hash) => {
+                        let current_hash_hex = hex::encode(&current_hash);
+                        
+                        // Compare with our cached hash
+                        if current_hash_hex != cached_hash {
+                            // Hash mismatch indicates a reorg
+                            warn!("Chain reorganization detected: hash mismatch at height {}. Cached: {}, Current: {}",
+                                  self.current_height, cached_hash, current_hash_hex);
                             
-                            // Compare with our cached hash
-                            if current_hash_hex != cached_hash {
-                                // Hash mismatch indicates a reorg
-                                warn!("Chain reorganization detected: hash mismatch at height {}. Cached: {}, Current: {}",
-                                      self.current_height, cached_hash, current_hash_hex);
-                                
-                                // Handle the reorg
-                                self.handle_reorg(self.current_height - 1).await?;
-                                
-                                // After handling reorg, we'll continue from the common ancestor
-                                // No need to update current_height here as handle_reorg already does that
-                            }
-                        },
-                        Err(e) => {
-                            // If we can't get the hash, it might be a deeper reorg
-                            warn!("Failed to get block hash at height {}: {}. Possible deep reorg.", self.current_height, e);
+                            // Handle the reorg
+                            self.handle_reorg(self.current_height - 1).await?;
                             
-                            // Try to find the highest block that exists in both chains
-                            let mut test_height = self.current_height - 1;
-                            while test_height > 0 {
-                                if let Ok(_) = self.client.get_block_hash(test_height).await {
-                                    // Found a block that exists, handle reorg from here
-                                    warn!("Found existing block at height {}. Handling reorg.", test_height);
-                                    self.handle_reorg(test_height).await?;
-                                    break;
-                                }
-                                test_height -= 1;
-                                if test_height == 0 {
-                                    // If we reach genesis, handle reorg from there
-                                    warn!("Deep reorg detected, rolling back to genesis.");
-                                    self.handle_reorg(0).await?;
-                                }
+                            // After handling reorg, we'll continue from the common ancestor
+                            // No need to update current_height here as handle_reorg already does that
+                        }
+                    },
+                    Err(e) => {
+                        // If we can't get the hash, it might be a deeper reorg
+                        warn!("Failed to get block hash at height {}: {}. Possible deep reorg.", self.current_height, e);
+                        
+                        // Try to find the highest block that exists in both chains
+                        let mut test_height = self.current_height - 1;
+                        while test_height > 0 {
+                            if let Ok(_) = self.client.get_block_hash(test_height).await {
+                                // Found a block that exists, handle reorg from here
+                                warn!("Found existing block at height {}. Handling reorg.", test_height);
+                                self.handle_reorg(test_height).await?;
+                                break;
+                            }
+                            test_height -= 1;
+                            if test_height == 0 {
+                                // If we reach genesis, handle reorg from there
+                                warn!("Deep reorg detected, rolling back to genesis.");
+                                self.handle_reorg(0).await?;
                             }
                         }
                     }
                 }
             }
-            
-            // Sleep for the polling interval
-            time::sleep(Duration::from_millis(self.polling_interval)).await;
         }
         
-        Ok(())
-    }
-    
-    /// Stop the block synchronizer
-    pub fn stop(&mut self) {
-        self.running = false;
+        // Send the blocks that became final, including any held back at the end
+        // of a bounded run or left behind by a failed finalized send
+        self.emit_finalized(available_height).await
     }
     
-    /// Process a block
-    ///
-    /// # Arguments
+    /// Apply a pending rewind requested through the admin handle
     ///
-    /// * `height` - The block height
+    /// A rewind to a block outside the block cache, or not below the current
+    /// height, is logged and dropped.
     ///
     /// # Returns
     ///
-    /// Ok(()) if the block was processed successfully
+    /// Ok(()) if there was no rewind pending or it was applied or dropped
     ///
     /// # Errors
     ///
-    /// Returns an error if the block cannot be processed
-    async fn process_block(&self, height: u32) -> Result<()> {
-        // Get the block hash
-        let hash = match self.client.get_block_hash(height).await {
-            Ok(hash) => hash,
-            Err(e) => {
-                // Check if the error is because the block hash is not found
-                if e.to_string().contains("Block hash not found") || e.to_string().contains("code: -32000") {
-                    // This is likely because we're trying to process a block that doesn't exist yet
-                    // Log this as info rather than error, and return without processing
-                    info!("Block at height {} not available yet, will retry later", height);
-                    return Ok(());
-                } else {
-                    // For other errors, propagate them
-                    return Err(e);
-                }
-            }
-        };
-        
-        // Create block metadata
-        let metadata = BlockMetadata {
-            height,
-            hash: hex::encode(&hash),
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis() as u64,
+    /// Returns an error if the rollback fails; the rewind stays pending
+    async fn apply_rewind(&mut self) -> Result<()> {
+        let height = match self.admin.pending_rewind() {
+            Some(height) => height,
+            None => return Ok(()),
         };
         
-        // Process the block with the transform module
-        let mut runtime = self.runtime.lock().await;
-        let transform_result = runtime.process_block(height, hash)?;
-        
-        // Add the block to the cache
-        let mut cache = self.cache.lock().await;
-        cache.add_block(metadata, transform_result.clone())?;
-        
-        // Send the CDC messages to the sink
-        self.sink.send(transform_result.cdc_messages).await?;
+        if let Err(e) = self.validate_rewind(height).await {
+            warn!("Admin: ignoring rewind: {}", e);
+            self.admin.clear_rewind(height);
+            return Ok(());
+        }
         
-        debug!("Processed block {}", height);
+        info!("Admin: rewinding from block height {} to {}", self.current_height, height);
+        self.roll_back_to(height).await?;
+        self.admin.clear_rewind(height);
+        self.publish_status().await;
+        info!("Admin: rewound to block height {}, processing forward", height);
         
         Ok(())
     }
     
-    /// Handle a chain reorganization
+    /// Check that the synchronizer can rewind to a block
     ///
     /// # Arguments
     ///
-    /// * `new_height` - The new block height
+    /// * `height` - The block height to rewind to
     ///
     /// # Returns
     ///
-    /// Ok(()) if the reorg was handled successfully
+    /// Ok(()) if the block is cached and below the current height
     ///
     /// # Errors
     ///
-    /// Returns an error if the reorg cannot be handled
-    async fn handle_reorg(&self, new_height: u32) -> Result<()> {
-        // Get the block hashes for the new chain
-        let mut new_hashes = Vec::new();
-        for height in 0..=new_height {
-            let hash = self.client.get_block_hash(height).await?;
-            new_hashes.push((height, hex::encode(&hash)));
+    /// Returns an error describing why the rewind is not possible
+    async fn validate_rewind(&self, height: u32) -> Result<()> {
+        if height >= self.current_height {
+            return Err(Error::BlockSynchronization(format!(
+                "Cannot rewind to height {}: the current height is {}", height, self.current_height
+            )));
         }
         
-        // Find the common ancestor
         let cache = self.cache.lock().await;
-        let common_ancestor = cache.find_common_ancestor(&new_hashes)
-            .ok_or_else(|| Error::ReorgHandling("No common ancestor found".to_string()))?;
-        
-        info!("Found common ancestor at height {}", common_ancestor);
-        
-        // Get the state snapshot at the common ancestor
-        let state_snapshot = cache.get_state_snapshot(common_ancestor)
-            .ok_or_else(|| Error::ReorgHandling(format!("State snapshot not found for height {}", common_ancestor)))?;
-        
-        // Release the cache lock
-        drop(cache);
-        
-        // Get the runtime lock
-        let mut runtime = self.runtime.lock().await;
-        
-        // Generate inverse CDC messages for the rolled back blocks
-        let mut inverse_messages = Vec::new();
-        
-        // Process blocks in reverse order from current_height down to common_ancestor + 1
-        for height in (common_ancestor + 1..=self.current_height).rev() {
-            info!("Generating inverse CDC messages for block {}", height);
-            
-            // Compute inverse messages for this block
-            let block_inverse = runtime.compute_inverse_messages(height)?;
-            inverse_messages.extend(block_inverse);
+        if cache.get_state_snapshot(height).is_none() {
+            return Err(Error::BlockSynchronization(format!(
+                "Cannot rewind to height {}: only blocks {:?} to {:?} are cached",
+                height, cache.lowest_height(), cache.highest_height()
+            )));
         }
         
-        // Reset the runtime state to the common ancestor
-        runtime.set_current_height(common_ancestor);
-        runtime.set_state(state_snapshot);
+        Ok(())
+    }
+    
+    /// Wait until processing is resumed, a rewind is requested or a stop is requested
+    async fn wait_while_paused(&mut self) {
+        info!("Admin: paused at block height {}", self.current_height);
+        self.publish_status().await;
         
-        // Send the inverse CDC messages to the sink
-        if !inverse_messages.is_empty() {
-            info!("Sending {} inverse CDC messages to sink", inverse_messages.len());
-            self.sink.send(inverse_messages).await?;
+        while self.admin.is_paused() && self.admin.pending_rewind().is_none() && !self.stop_handle.is_stopped() {
+            tokio::select! {
+                _ = self.admin.changed() => {}
+                _ = self.stop_handle.stopped() => {}
+            }
         }
         
-        // Roll back the cache
-        let mut cache = self.cache.lock().await;
-        cache.rollback(common_ancestor)?;
-        
-        // Release the runtime lock
-        drop(runtime);
-        
-        // Process the new chain
-        for height in (common_ancestor + 1)..=new_height {
-            // Release the cache lock
-            drop(cache);
-            
-            // Process the block
-            self.process_block(height).await?;
-            
-            // Reacquire the cache lock
-            cache = self.cache.lock().await;
+        if !self.admin.is_paused() {
+            info!("Admin: resumed at block height {}", self.current_height);
+            self.publish_status().await;
         }
-        
-        Ok(())
     }
     
-    /// Get the current block height
+    /// Handle a failed synchronization step according to the error policy
     ///
-    /// # Returns
+    /// Transient errors are retried with exponential backoff until the retry
+    /// limit is reached. Any other error, such as a transform failure that was
+    /// not dead-lettered, halts the synchronizer.
     ///
-    /// The current block height
-    pub fn get_current_height(&self) -> u32 {
-        self.current_height
-    }
-    
-    /// Get the block cache
+    /// # Arguments
+    ///
+    /// * `error` - The error the step failed with
     ///
     /// # Returns
     ///
-    /// The block cache
-    pub async fn get_cache(&self) -> Arc<Mutex<BlockCache>> {
-        self.cache.clone()
+    /// Ok(()) once the backoff has elapsed and the step should be retried
+    ///
+    /// # Errors
+    ///
+    /// Returns the error if it is not transient or retries are exhausted
+    async fn handle_failure(&mut self, error: Error) -> Result<()> {
+        // Failures are counted per block, so progress between failures resets the count
+        let failing_height = self.current_height + 1;
+        if self.failing_height == Some(failing_height) {
+            self.consecutive_failures += 1;
+        } else {
+            self.failing_height = Some(failing_height);
+            self.consecutive_failures = 1;
+        }
+        
+        let failures = self.consecutive_failures;
+        let transient = error.is_transient();
+        self.status.record_error(&error);
+        self.notify(|observer| observer.on_error(&error));
+        self.status.update(|status| {
+            status.failing_height = Some(failing_height);
+            status.consecutive_failures = failures;
+        });
+        metrics::record_failure(transient, failures);
+        
+        if !transient {
+            error!("Halting at block {}: {}", failing_height, error);
+            return Err(error);
+        }
+        
+        if !self.retry_policy.should_retry(failures) {
+            error!("Giving up at block {} after {} consecutive failures: {}", failing_height, failures, error);
+            return Err(error);
+        }
+        
+        let backoff = self.retry_policy.backoff(failures);
+        warn!("Transient error at block {} (failure {}), retrying in {:?}: {}", failing_height, failures, backoff, error);
+        
+        tokio::select! {
+            _ = time::sleep(backoff) => {}
+            _ = self.stop_handle.stopped() => {}
+        }
+        
+        Ok(())
     }
     
-    /// Get the WASM runtime
+    /// Reset the failure count after a successful step
+    fn clear_failures(&mut self) {
+        if self.failing_height.take().is_some() {
+            info!("Recovered after {} consecutive failures", self.consecutive_failures);
+            self.consecutive_failures = 0;
+            self.status.update(|status| {
+                status.failing_height = None;
+                status.consecutive_failures = 0;
+            });
+            metrics::record_recovery();
+        }
+    }
+    
+    /// Call a hook on every registered observer
+    fn notify<F: Fn(&dyn SyncObserver)>(&self, hook: F) {
+        for observer in &self.observers {
+            hook(observer.as_ref());
+        }
+    }
+    
+    /// Copy a block's CDC messages for the observers, if there are any
+    fn observed_messages(&self, messages: &[CdcMessage]) -> Vec<CdcMessage> {
+        if self.observers.is_empty() {
+            Vec::new()
+        } else {
+            messages.to_vec()
+        }
+    }
+    
+    /// Publish the current heights and cache range to the status handle
+    async fn publish_status(&self) {
+        let (cache_lowest_height, cache_highest_height) = {
+            let cache = self.cache.lock().await;
+            (cache.lowest_height(), cache.highest_height())
+        };
+        
+        self.status.update(|status| {
+            status.paused = self.admin.is_paused();
+            status.current_height = self.current_height;
+            status.emitted_height = self.emitted_height;
+            status.finalized_height = self.finalized_height;
+            status.cache_lowest_height = cache_lowest_height;
+            status.cache_highest_height = cache_highest_height;
+        });
+        
+        let indexed_height = self.tip_tracker.last_tip().map(|tip| tip.indexed_height);
+        metrics::record_progress(self.current_height, indexed_height);
+    }
+    
+    /// Check whether a bounded run has emitted its last block
+    ///
+    /// A run that starts at or past its end height, e.g. when resuming from a
+    /// checkpoint, has nothing to emit and is done at once.
+    fn reached_end_height(&self) -> bool {
+        match (self.end_height, self.checkpoint_height()) {
+            (Some(end_height), Some(height)) => height >= end_height,
+            (Some(end_height), None) => self.starting_height >= end_height,
+            (None, _) => false,
+        }
+    }
+    
+    /// Wait until the block source signals a possible new tip, waking early if a stop is requested
+    async fn wait_for_next_poll(&mut self) {
+        match &mut self.block_source {
+            Some(source) => {
+                tokio::select! {
+                    event = source.wait() => {
+                        if let Some(event) = event {
+                            debug!("Woken by tip event at height {}", event.height);
+                        }
+                    }
+                    _ = self.stop_handle.stopped() => {}
+                    _ = self.admin.changed() => {}
+                }
+            }
+            None => {
+                tokio::select! {
+                    _ = time::sleep(Duration::from_millis(self.polling_interval)) => {}
+                    _ = self.stop_handle.stopped() => {}
+                    _ = self.admin.changed() => {}
+                }
+            }
+        }
+    }
+    
+    /// Stop the block synchronizer
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.stop_handle.stop();
+    }
+    
+    /// Get a handle to the live synchronizer status
     ///
     /// # Returns
     ///
-    /// The WASM runtime
-    pub async fn get_runtime(&self) -> Arc<Mutex<WasmRuntime>> {
-        self.runtime.clone()
+    /// A status handle shared with this synchronizer
+    pub fn status_handle(&self) -> StatusHandle {
+        self.status.clone()
     }
     
-    /// Get the CDC sink
+    /// Get a handle for pausing, resuming and rewinding the synchronizer from another task
     ///
     /// # Returns
     ///
-    /// The CDC sink
-    pub fn get_sink(&self) -> Arc<Box<dyn CdcSink>> {
-        self.sink.clone()
+    /// An admin handle shared with this synchronizer
+    pub fn admin_handle(&self) -> AdminHandle {
+        self.admin.clone()
     }
     
-    /// Get the metashrew client
+    /// Get a handle for stopping the synchronizer from another task
     ///
     /// # Returns
     ///
-    /// The metashrew client
-    pub fn get_client(&self) -> Arc<C> {
-        self.client.clone()
+    /// A stop handle shared with this synchronizer
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop_handle.clone()
     }
-}
-
-/// Synchronizer trait
-///
-/// This trait defines the interface for block synchronizers.
-#[async_trait]
-pub trait Synchronizer: Send + Sync {
-    /// Run the synchronizer
+    
+    /// Flush and close the sinks and write the checkpoint
     ///
     /// # Returns
     ///
-    /// Ok(()) if the synchronizer ran successfully
+    /// Ok(()) if the synchronizer was shut down cleanly
     ///
     /// # Errors
     ///
-    /// Returns an error if the synchronizer encounters an error
-    async fn run(&mut self) -> Result<()>;
-    
-    /// Stop the synchronizer
-    fn stop(&mut self);
+    /// Returns an error if a sink cannot be flushed or closed, or the
+    /// checkpoint cannot be written
+    pub async fn finish(&mut self) -> Result<()> {
+        self.cancel_prefetches().await;
+        self.sink.flush().await?;
+        self.sink.close().await?;
+        
+        if let Some(finalized_sink) = &self.finalized_sink {
+            finalized_sink.flush().await?;
+            finalized_sink.close().await?;
+        }
+        
+        self.write_checkpoint().await
+    }
     
-    /// Get the current block height
+    /// Get the last block whose CDC messages were fully emitted
+    ///
+    /// With a finality depth this is the last finalized block, otherwise the
+    /// last block sent to the sink.
     ///
     /// # Returns
     ///
-    /// The current block height
-    fn get_current_height(&self) -> u32;
-}
-
-#[async_trait]
-impl<C: MetashrewClient> Synchronizer for BlockSynchronizer<C> {
-    async fn run(&mut self) -> Result<()> {
-        self.run().await
-    }
-    
-    fn stop(&mut self) {
-        self.stop();
+    /// The checkpoint height, or None if nothing has been emitted yet
+    pub fn checkpoint_height(&self) -> Option<u32> {
+        if self.finality_depth > 0 {
+            self.finalized_height
+        } else {
+            self.emitted_height
+        }
     }
     
-    fn get_current_height(&self) -> u32 {
-        self.get_current_height()
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::client::MockMetashrewClient;
-    use crate::sink::{ConsoleSink, FileSink, NullSink};
-    use debshrew_runtime::transform::MockTransform;
-    use debshrew_support::{CdcHeader, CdcMessage, CdcOperation, CdcPayload};
-    use std::sync::Arc;
-    use tempfile::tempdir;
-    use tokio::runtime::Runtime;
-
-    #[test]
-    fn test_block_synchronizer() {
-        // Create a mock metashrew client
-        let mut client = MockMetashrewClient::new();
-        client.set_height(10);
-        
-        for i in 0..=10 {
-            client.set_block_hash(i, vec![i as u8]);
-        }
-        
-        // Create a mock transform
-        let _transform = MockTransform::default();
-        
-        // Create a WASM runtime for testing
-        // Use the client's URL for the metashrew URL
-        let runtime = WasmRuntime::for_testing().unwrap();
+    /// Write the checkpoint for the last fully emitted block
+    ///
+    /// This is a no-op if no checkpoint store is configured or nothing has been
+    /// emitted yet.
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the checkpoint was written successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint cannot be written
+    pub async fn write_checkpoint(&self) -> Result<()> {
+        let (store, height) = match (&self.checkpoint_store, self.checkpoint_height()) {
+            (Some(store), Some(height)) => (store, height),
+            _ => return Ok(()),
+        };
         
-        // Create a null sink
-        let sink = Box::new(NullSink::new());
+        let hash = self.cache.lock().await.get_block_hash(height)
+            .ok_or_else(|| Error::BlockSynchronization(format!("Block {} not found in cache for checkpoint", height)))?;
         
-        // Create a block synchronizer
-        let mut synchronizer = BlockSynchronizer::new(client, runtime, sink, 6).unwrap();
+        let checkpoint = Checkpoint {
+            height,
+            hash,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
         
-        // Set the starting height
-        synchronizer.set_starting_height(5);
+        store.save(&checkpoint)?;
+        info!("Wrote checkpoint at height {} to {}", height, store.path().display());
         
-        // Set a short polling interval
-        synchronizer.set_polling_interval(10);
+        Ok(())
+    }
+    
+    /// Process a block
+    ///
+    /// The block is only marked as processed once its hash was fetched, the
+    /// transform ran and its CDC messages were handed to the sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the block was processed successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block cannot be processed
+    async fn process_block(&mut self, height: u32) -> Result<()> {
+        // Get the block hash; a missing hash is an error, the block must not be skipped
+        let hash = self.client.get_block_hash(height).await?;
+        let hash_hex = hex::encode(&hash);
         
-        // Create a runtime for async tests
-        let rt = Runtime::new().unwrap();
+        let messages = self.transform_block(height, hash).await?;
+        let observed = self.observed_messages(&messages);
         
-        // Run the synchronizer for a short time
-        let handle = rt.spawn(async move {
-            let _ = synchronizer.run().await;
-        });
+        // Send the CDC messages to the tentative stream, if there is one
+        if self.emits_tentative() {
+            let block = EmittedBlock {
+                height,
+                hash: hash_hex.clone(),
+                messages,
+            };
+            send_blocks(self.sink.as_ref().as_ref(), "tentative", vec![block], self.control_messages).await?;
+            self.emitted_height = Some(height);
+        }
         
-        // Wait for a short time
-        std::thread::sleep(Duration::from_millis(100));
+        self.current_height = height;
+        self.notify(|observer| observer.on_block_processed(height, &hash_hex, &observed));
+        debug!("Processed block {}", height);
         
-        // Abort the task
-        handle.abort();
+        Ok(())
     }
     
-    #[test]
-    fn test_block_synchronizer_with_different_sinks() {
-        // Create a mock metashrew client
-        let mut client = MockMetashrewClient::new();
-        client.set_height(10);
+    /// Run the transform for a block and add the result to the cache
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height
+    /// * `hash` - The block hash
+    ///
+    /// # Returns
+    ///
+    /// The CDC messages generated for the block
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transform fails
+    async fn transform_block(&self, height: u32, hash: Vec<u8>) -> Result<Vec<CdcMessage>> {
+        // Create block metadata
+        let metadata = BlockMetadata {
+            height,
+            hash: hex::encode(&hash),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
         
-        for i in 0..=10 {
-            client.set_block_hash(i, vec![i as u8]);
-        }
+        // Process the block with the transform module
+        let start = Instant::now();
+        let mut runtime = self.runtime.lock().await;
+        let transform_result = match runtime.process_block(height, hash) {
+            Ok(result) => result,
+            Err(Error::Transform(message)) => {
+                let message = format!("Block {} ({}) failed to transform: {}", height, metadata.hash, message);
+                let queue = match &self.dead_letter_queue {
+                    Some(queue) => queue,
+                    None => return Err(Error::Transform(message)),
+                };
+                
+                // Move past the block without emitting anything for it
+                error!("{}; writing it to the dead-letter file {}", message, queue.path().display());
+                queue.push(&DeadLetter {
+                    height,
+                    hash: metadata.hash.clone(),
+                    error: message,
+                    timestamp: metadata.timestamp,
+                })?;
+                metrics::record_dead_letter();
+                
+                runtime.skip_block(height)
+            }
+            Err(e) => return Err(e),
+        };
+        drop(runtime);
+        metrics::record_transform(start.elapsed(), &transform_result.cdc_messages);
         
-        // We don't need this runtime since we create a new one for each test
-        // let runtime = WasmRuntime::from_bytes(&[0]).unwrap();
+        // Add the block to the cache
+        let mut cache = self.cache.lock().await;
+        cache.add_block(metadata, transform_result.clone())?;
         
-        // Create a tokio runtime for async tests
-        let rt = Runtime::new().unwrap();
+        Ok(transform_result.cdc_messages)
+    }
+    
+    /// Process a contiguous range of blocks through the prefetch pipeline
+    ///
+    /// Block hashes are fetched in batches of `prefetch_depth` blocks, one
+    /// batch ahead of the transform, and the sink send for each block
+    /// overlaps with the transform of the next one. Output order stays strict:
+    /// the send for a block is awaited before the send for the following block
+    /// starts, and `current_height` only advances once a block's CDC messages
+    /// have been accepted by the sink. With a finality depth, only the blocks
+    /// that are at least that deep below `end_height` are sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_height` - The first block to process
+    /// * `end_height` - The last block to process (inclusive)
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the range was processed, or stopped early at a block that is
+    /// not available yet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a block hash cannot be fetched, the transform fails,
+    /// or the sink rejects a block
+    async fn catch_up(&mut self, start_height: u32, end_height: u32) -> Result<()> {
+        let client = self.client.clone();
+        let batch_size = self.prefetch_depth as u32;
+        let mut hashes = futures::stream::iter((start_height..=end_height).step_by(self.prefetch_depth))
+            .map(move |batch_start| {
+                let client = client.clone();
+                let batch_end = batch_start.saturating_add(batch_size - 1).min(end_height);
+                async move {
+                    match client.get_block_hashes(batch_start..=batch_end).await {
+                        Ok(hashes) => (batch_start..=batch_end).zip(hashes.into_iter().map(Ok)).collect(),
+                        Err(e) if is_block_not_available(&e) => {
+                            // The batch reaches past the available blocks; fetch
+                            // one at a time to stop exactly at the first missing one
+                            let mut hashes = Vec::new();
+                            for height in batch_start..=batch_end {
+                                let hash = client.get_block_hash(height).await;
+                                let missing = hash.is_err();
+                                hashes.push((height, hash));
+                                if missing {
+                                    break;
+                                }
+                            }
+                            hashes
+                        }
+                        Err(e) => vec![(batch_start, Err(e))],
+                    }
+                }
+            })
+            .buffered(2)
+            .flat_map(futures::stream::iter);
         
-        // Create a new runtime for each test to avoid cloning
+        let mut pending: Option<PendingSend> = None;
+        let mut next_view_prefetch = start_height;
+        let mut result = Ok(());
         
-        // Test with ConsoleSink
-        {
-            let console_sink = Box::new(ConsoleSink::new(false));
-            let test_runtime = WasmRuntime::for_testing().unwrap();
-            let synchronizer = BlockSynchronizer::new(client.clone(), test_runtime, console_sink, 6).unwrap();
+        while let Some((height, hash)) = hashes.next().await {
+            if self.stop_handle.is_stopped() {
+                info!("Stop requested, pausing catch-up before block {}", height);
+                break;
+            }
+            if self.admin.interrupts() {
+                info!("Admin: command pending, pausing catch-up before block {}", height);
+                break;
+            }
             
-            // Verify the sink type
-            let sink = synchronizer.get_sink();
-            rt.block_on(async {
-                // Send a test message to verify the sink works
-                let message = create_test_message();
-                let result = sink.send(vec![message]).await;
-                assert!(result.is_ok());
-                
-                // Flush and close the sink
-                assert!(sink.flush().await.is_ok());
-                assert!(sink.close().await.is_ok());
-            });
-        }
-        
-        // Test with FileSink
-        {
-            // Create a temporary directory for the file
-            let dir = tempdir().unwrap();
-            let file_path = dir.path().join("test.json");
+            let hash = match hash {
+                Ok(hash) => hash,
+                Err(e) if is_block_not_available(&e) => {
+                    info!("Block at height {} not available yet, will retry later", height);
+                    break;
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
             
-            let file_sink = Box::new(FileSink::new(file_path.to_str().unwrap(), false, 1000).unwrap());
-            let test_runtime = WasmRuntime::for_testing().unwrap();
-            let synchronizer = BlockSynchronizer::new(client.clone(), test_runtime, file_sink, 6).unwrap();
+            let hash_hex = hex::encode(&hash);
+            let messages = match self.transform_block(height, hash).await {
+                Ok(messages) => messages,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            let observed = self.observed_messages(&messages);
             
-            // Verify the sink type
-            let sink = synchronizer.get_sink();
-            rt.block_on(async {
-                // Send a test message to verify the sink works
-                let message = create_test_message();
-                let result = sink.send(vec![message]).await;
-                assert!(result.is_ok());
-                
-                // Flush and close the sink
-                assert!(sink.flush().await.is_ok());
-                assert!(sink.close().await.is_ok());
-            });
-        }
-        
-        // Test with a custom sink implementation
-        {
-            // Create a custom sink that counts messages
-            #[derive(Clone)]
-            struct CountingSink {
-                count: Arc<tokio::sync::Mutex<usize>>,
+            if self.view_cache.is_enabled() {
+                self.prefetch_views(height, end_height, &mut next_view_prefetch);
             }
             
-            impl CountingSink {
-                fn new() -> Self {
-                    Self {
-                        count: Arc::new(tokio::sync::Mutex::new(0)),
-                    }
-                }
-                
-                async fn get_count(&self) -> usize {
-                    let count = self.count.lock().await;
-                    *count
-                }
+            // Wait for the previous block to reach the sink before handing over this one
+            if let Err(e) = self.complete_pending_send(&mut pending).await {
+                result = Err(e);
+                break;
             }
             
-            #[async_trait]
-            impl CdcSink for CountingSink {
-                async fn send(&self, messages: Vec<CdcMessage>) -> Result<()> {
-                    let mut count = self.count.lock().await;
-                    *count += messages.len();
-                    Ok(())
-                }
-                
-                async fn flush(&self) -> Result<()> {
-                    Ok(())
-                }
-                
-                async fn close(&self) -> Result<()> {
-                    Ok(())
-                }
-            }
+            let tentative = if self.emits_tentative() {
+                Some(EmittedBlock {
+                    height,
+                    hash: hash_hex.clone(),
+                    messages,
+                })
+            } else {
+                None
+            };
+            let finalized = match self.finalized_target(end_height) {
+                Some(finalized_height) => self.take_finalized(height.min(finalized_height)).await,
+                None => None,
+            };
             
-            let counting_sink = CountingSink::new();
-            let counting_sink_clone = counting_sink.clone();
-            let test_runtime = WasmRuntime::for_testing().unwrap();
-            let synchronizer = BlockSynchronizer::new(client.clone(), test_runtime, Box::new(counting_sink), 6).unwrap();
+            if tentative.is_none() && finalized.is_none() {
+                self.current_height = height;
+                self.publish_status().await;
+                self.notify(|observer| observer.on_block_processed(height, &hash_hex, &observed));
+                debug!("Processed block {}", height);
+                continue;
+            }
             
-            // Verify the sink works
-            rt.block_on(async {
-                // Get the sink from the synchronizer
-                let sink = synchronizer.get_sink();
-                
-                // Send test messages
-                let messages = vec![
-                    create_test_message(),
-                    create_test_message(),
-                    create_test_message(),
-                ];
-                
-                let result = sink.send(messages).await;
-                assert!(result.is_ok());
-                
-                // Verify the count
-                assert_eq!(counting_sink_clone.get_count().await, 3);
+            let sink = self.sink.clone();
+            let finalized_sink = self.finalized_output();
+            let control_messages = self.control_messages;
+            pending = Some(PendingSend {
+                height,
+                hash: hash_hex,
+                messages: observed,
+                emitted: tentative.as_ref().map(|_| height),
+                finalized: finalized.as_ref().map(|(finalized_height, _)| *finalized_height),
+                handle: tokio::spawn(async move {
+                    if let Some(block) = tentative {
+                        if let Err(e) = send_blocks(sink.as_ref().as_ref(), "tentative", vec![block], control_messages).await {
+                            return (Err(e), Ok(()));
+                        }
+                    }
+                    let finalized = match finalized {
+                        Some((_, blocks)) => send_blocks(finalized_sink.as_ref().as_ref(), "finalized", blocks, control_messages).await,
+                        None => Ok(()),
+                    };
+                    (Ok(()), finalized)
+                }),
             });
         }
+        
+        // Never leave a block half-emitted, even when stopping on an error
+        let drained = self.complete_pending_send(&mut pending).await;
+        result.and(drained)
     }
     
-    // Helper function to create a test CDC message
-    fn create_test_message() -> CdcMessage {
-        CdcMessage {
-            header: CdcHeader {
-                source: "test".to_string(),
-                timestamp: SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_millis() as u64,
-                block_height: 123,
-                block_hash: "000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d".to_string(),
-                transaction_id: None,
-            },
-            payload: CdcPayload {
-                operation: CdcOperation::Create,
-                table: "test_table".to_string(),
-                key: "test_key".to_string(),
-                before: None,
-                after: Some(serde_json::json!({
-                    "field1": "value1",
-                    "field2": 42
-                })),
-            },
+    /// Wait for an in-flight sink send and mark its block as processed
+    ///
+    /// Each stream's progress is tracked on its own. Once the tentative stream
+    /// has accepted the block, it is processed even if the finalized send
+    /// failed; the finalized blocks are then sent again by `emit_finalized`,
+    /// without repeating the block on the tentative stream.
+    async fn complete_pending_send(&mut self, pending: &mut Option<PendingSend>) -> Result<()> {
+        let mut result = Ok(());
+        if let Some(send) = pending.take() {
+            let (tentative, finalized) = send.handle.await
+                .map_err(|e| Error::Sink(format!("Sink task for block {} failed: {}", send.height, e)))?;
+            tentative?;
+            
+            self.current_height = send.height;
+            if send.emitted.is_some() {
+                self.emitted_height = send.emitted;
+            }
+            match finalized {
+                Ok(()) if send.finalized.is_some() => self.finalized_height = send.finalized,
+                Ok(()) => {}
+                Err(e) => result = Err(e),
+            }
+            self.publish_status().await;
+            self.notify(|observer| observer.on_block_processed(send.height, &send.hash, &send.messages));
+            debug!("Processed block {}", send.height);
         }
+        
+        result
+    }
+    
+    /// Check whether blocks are sent to the tentative stream as soon as they are transformed
+    ///
+    /// This is the case without a finality depth, or when a separate finalized
+    /// sink is attached.
+    fn emits_tentative(&self) -> bool {
+        self.finality_depth == 0 || self.finalized_sink.is_some()
+    }
+    
+    /// Get the sink that receives the finalized stream
+    fn finalized_output(&self) -> Arc<Box<dyn CdcSink>> {
+        self.finalized_sink.clone().unwrap_or_else(|| self.sink.clone())
+    }
+    
+    /// Get the highest block height that is final at a given tip
+    ///
+    /// # Arguments
+    ///
+    /// * `tip` - The metashrew tip height
+    ///
+    /// # Returns
+    ///
+    /// The highest final block height, or None if there is no finalized stream
+    /// or no block is deep enough yet
+    fn finalized_target(&self, tip: u32) -> Option<u32> {
+        if self.finality_depth == 0 {
+            return None;
+        }
+        
+        tip.checked_sub(self.finality_depth)
+    }
+    
+    /// Collect the cached blocks that are final but not yet emitted
+    ///
+    /// # Arguments
+    ///
+    /// * `finalized_height` - The highest block height considered final
+    ///
+    /// # Returns
+    ///
+    /// The highest block covered and the blocks to emit, or None if there is
+    /// nothing new to emit
+    async fn take_finalized(&self, finalized_height: u32) -> Option<(u32, Vec<EmittedBlock>)> {
+        let start_height = self.finalized_height.map_or(0, |height| height + 1);
+        
+        let cache = self.cache.lock().await;
+        let end_height = cache.highest_height()?.min(finalized_height);
+        if end_height < start_height {
+            return None;
+        }
+        
+        let blocks = (start_height..=end_height)
+            .filter_map(|height| cache.get_block_at_height(height))
+            .map(|block| EmittedBlock {
+                height: block.metadata.height,
+                hash: block.metadata.hash.clone(),
+                messages: block.cdc_messages.clone(),
+            })
+            .collect();
+        
+        Some((end_height, blocks))
     }
-}
-impl<C: MetashrewClient> BlockSynchronizer<C> {
-    // Add this method after the existing methods
     
-    /// Get the actual block count using the Bitcoin-style API
+    /// Send the CDC messages of every block that has reached the finality depth
+    ///
+    /// # Arguments
     ///
-    /// This is a workaround for the discrepancy between metashrew_height and the actual block count
+    /// * `tip` - The current metashrew tip height
     ///
     /// # Returns
     ///
-    /// The actual block count
+    /// Ok(()) if the finalized blocks were emitted successfully
     ///
     /// # Errors
     ///
-    /// Returns an error if the block count cannot be retrieved
-    async fn get_actual_block_count(&self) -> Result<u32> {
-        log::info!("Getting actual block count from {}", self.client.get_url());
-        log::debug!("Current height before getting block count: {}", self.current_height);
-        
-        // Create a JSON-RPC request to get the block count
-        let request = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "getblockcount",
-            "params": [],
-            "id": 1
-        });
+    /// Returns an error if the sink rejects the messages
+    async fn emit_finalized(&mut self, tip: u32) -> Result<()> {
+        let finalized_height = match self.finalized_target(tip) {
+            Some(height) => height,
+            None => return Ok(()),
+        };
+        
+        if let Some((height, blocks)) = self.take_finalized(finalized_height).await {
+            debug!("Emitting finalized blocks up to {}", height);
+            send_blocks(self.finalized_output().as_ref().as_ref(), "finalized", blocks, self.control_messages).await?;
+            self.finalized_height = Some(height);
+        }
+        
+        Ok(())
+    }
+    
+    /// Prefetch the view calls recorded for a block against upcoming heights
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block that was just transformed
+    /// * `end_height` - The last block of the current catch-up range
+    /// * `next_height` - The next height that has not been prefetched yet
+    fn prefetch_views(&mut self, height: u32, end_height: u32, next_height: &mut u32) {
+        let calls = self.view_cache.take_recorded();
+        self.view_cache.evict_below(height + 1);
+        
+        *next_height = (*next_height).max(height + 1);
         
-        log::debug!("Sending getblockcount request: {}", request.to_string());
+        // At most one prefetch per block in the window is in flight
+        while self.prefetch_tasks.try_join_next().is_some() {}
+        if calls.is_empty() || self.prefetch_tasks.len() >= self.prefetch_depth {
+            return;
+        }
+        
+        // Send the calls for every height up to the horizon in one batch
+        let horizon = end_height.min(height.saturating_add(self.prefetch_depth as u32));
+        let mut batch = Vec::new();
+        while *next_height <= horizon {
+            for (view_name, params) in &calls {
+                batch.push(ViewCall::new(view_name, params, Some(*next_height)));
+            }
+            *next_height += 1;
+        }
+        if batch.is_empty() {
+            return;
+        }
         
-        // Send the request
-        let client = reqwest::Client::new();
-        let response = match client.post(self.client.get_url().clone())
-            .header("Content-Type", "application/json")
-            .body(request.to_string())
-            .send()
-            .await {
-                Ok(resp) => resp,
+        let client = self.client.clone();
+        let view_cache = self.view_cache.clone();
+        self.prefetch_tasks.spawn(async move {
+            let results = match client.call_views(&batch).await {
+                Ok(results) => results,
                 Err(e) => {
-                    log::error!("Failed to send getblockcount request: {}", e);
-                    // If we can't get the actual block count, return a safe default of 1
-                    // This ensures we can still process blocks even if the getblockcount method fails
-                    log::warn!("Using default block count of 1");
-                    return Ok(1);
+                    debug!("Prefetch of {} view calls failed: {}", batch.len(), e);
+                    return;
                 }
             };
-        
-        // Parse the response
-        let response_text = match response.text().await {
-            Ok(text) => text,
-            Err(e) => {
-                log::error!("Failed to get response text: {}", e);
-                log::warn!("Using default block count of 1");
-                return Ok(1);
+            
+            for (call, result) in batch.iter().zip(results) {
+                let prefetch_height = call.height.unwrap_or_default();
+                match result {
+                    Ok(result) => view_cache.insert(&call.view_name, &call.params, prefetch_height, result),
+                    Err(e) => debug!("Prefetch of view '{}' at height {} failed: {}", call.view_name, prefetch_height, e),
+                }
             }
+        });
+    }
+    
+    /// Abort the view prefetches in flight and drop their results
+    ///
+    /// Aborted tasks are awaited, so none of them stores a result afterwards.
+    async fn cancel_prefetches(&mut self) {
+        self.prefetch_tasks.abort_all();
+        while self.prefetch_tasks.join_next().await.is_some() {}
+        self.view_cache.clear();
+    }
+    
+    /// Handle a chain reorganization
+    ///
+    /// # Arguments
+    ///
+    /// * `new_height` - The new block height
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the reorg was handled successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reorg cannot be handled
+    async fn handle_reorg(&mut self, new_height: u32) -> Result<()> {
+        // Get the block hashes of the new chain; only cached heights can be a common ancestor
+        let lowest_cached = self.cache.lock().await.lowest_height().unwrap_or(0);
+        let new_hashes: Vec<(u32, String)> = if lowest_cached <= new_height {
+            let hashes = self.client.get_block_hashes(lowest_cached..=new_height).await?;
+            (lowest_cached..=new_height).zip(hashes.iter().map(hex::encode)).collect()
+        } else {
+            Vec::new()
         };
         
-        log::debug!("Received getblockcount response: {}", truncate_response_for_logging(&response_text));
+        // Find the common ancestor
+        let cache = self.cache.lock().await;
+        let common_ancestor = cache.find_common_ancestor(&new_hashes)
+            .ok_or_else(|| Error::ReorgHandling("No common ancestor found".to_string()))?;
         
-        let json_response: serde_json::Value = match serde_json::from_str(&response_text) {
-            Ok(json) => json,
-            Err(e) => {
-                log::error!("Failed to parse response as JSON: {}", e);
-                log::warn!("Using default block count of 1");
-                return Ok(1);
-            }
-        };
+        info!("Found common ancestor at height {}", common_ancestor);
+        self.status.record_reorg(self.current_height, common_ancestor);
+        metrics::record_reorg(self.current_height.saturating_sub(common_ancestor));
         
-        // Extract the result
-        let result = match json_response.get("result") {
-            Some(r) => r,
-            None => {
-                log::error!("No result in getblockcount response");
-                log::warn!("Using default block count of 1");
-                return Ok(1);
-            }
-        };
+        // Release the cache lock
+        drop(cache);
         
-        // Convert to u32
-        let block_count = match result.as_u64() {
-            Some(count) => count as u32,
-            None => {
-                log::error!("Invalid block count: {:?}", result);
-                log::warn!("Using default block count of 1");
-                return Ok(1);
+        let old_tip = self.current_height;
+        self.roll_back_to(common_ancestor).await?;
+        
+        self.notify(|observer| observer.on_reorg(common_ancestor, old_tip, new_height));
+        
+        // Process the new chain
+        for height in (common_ancestor + 1)..=new_height {
+            self.process_block(height).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Roll the synchronizer back to a cached block
+    ///
+    /// Inverse CDC messages for every emitted block above the target are sent
+    /// to their streams, then the runtime state and the block cache are reset
+    /// to the target block. Nothing is reset if a sink rejects the inverse
+    /// messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block to roll back to
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the synchronizer was rolled back successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block is not cached, the inverse messages cannot
+    /// be computed, or a sink rejects them
+    async fn roll_back_to(&mut self, height: u32) -> Result<()> {
+        // Prefetched results may belong to the chain being rolled back
+        self.cancel_prefetches().await;
+        
+        // Get the state snapshot at the target block
+        let state_snapshot = self.cache.lock().await.get_state_snapshot(height)
+            .ok_or_else(|| Error::ReorgHandling(format!("State snapshot not found for height {}", height)))?;
+        
+        // Hold the runtime lock until the state is reset
+        let mut runtime = self.runtime.lock().await;
+        
+        // Generate inverse CDC messages for the rolled back blocks, per stream
+        let mut inverse_messages = Vec::new();
+        let mut finalized_inverse_messages = Vec::new();
+        
+        // Process blocks in reverse order from current_height down to height + 1
+        for rolled_back in (height + 1..=self.current_height).rev() {
+            let tentative = self.emitted_height.is_some_and(|emitted| rolled_back <= emitted);
+            let finalized = self.finalized_height.is_some_and(|finalized| rolled_back <= finalized);
+            
+            // Blocks still waiting for finality never reached a sink
+            if !tentative && !finalized {
+                debug!("Block {} was not emitted yet, no inverse CDC messages needed", rolled_back);
+                continue;
+            }
+            
+            info!("Generating inverse CDC messages for block {}", rolled_back);
+            
+            // Compute inverse messages for this block
+            let block_inverse = runtime.compute_inverse_messages(rolled_back)?;
+            if finalized {
+                warn!("Rolling back finalized block {}, deeper than the finality depth of {}", rolled_back, self.finality_depth);
+                finalized_inverse_messages.extend(block_inverse.iter().cloned());
+            }
+            if tentative {
+                inverse_messages.extend(block_inverse);
             }
+        }
+        
+        // Send the inverse CDC messages to the sinks
+        let tentative_depth = self.emitted_height.map_or(0, |emitted| emitted.saturating_sub(height));
+        let finalized_depth = self.finalized_height.map_or(0, |finalized| finalized.saturating_sub(height));
+        let frame = |depth| {
+            (self.control_messages && depth > 0).then_some(ReorgFrame {
+                fork_height: height,
+                depth,
+            })
         };
+        send_reorg(self.sink.as_ref().as_ref(), "tentative", frame(tentative_depth), inverse_messages).await?;
+        send_reorg(self.finalized_output().as_ref().as_ref(), "finalized", frame(finalized_depth), finalized_inverse_messages).await?;
+        self.emitted_height = self.emitted_height.map(|emitted| emitted.min(height));
+        self.finalized_height = self.finalized_height.map(|finalized| finalized.min(height));
         
-        log::info!("Actual block count: {}", block_count);
-        Ok(block_count)
+        // Reset the runtime state to the target block
+        runtime.set_current_height(height);
+        runtime.set_state(state_snapshot);
+        drop(runtime);
+        
+        // Roll back the cache
+        self.cache.lock().await.rollback(height)?;
+        self.current_height = height;
+        
+        Ok(())
+    }
+    
+    /// Get the current block height
+    ///
+    /// # Returns
+    ///
+    /// The current block height
+    pub fn get_current_height(&self) -> u32 {
+        self.current_height
+    }
+    
+    /// Get the block cache
+    ///
+    /// # Returns
+    ///
+    /// The block cache
+    pub async fn get_cache(&self) -> Arc<Mutex<BlockCache>> {
+        self.cache.clone()
+    }
+    
+    /// Get the WASM runtime
+    ///
+    /// # Returns
+    ///
+    /// The WASM runtime
+    pub async fn get_runtime(&self) -> Arc<Mutex<WasmRuntime>> {
+        self.runtime.clone()
+    }
+    
+    /// Get the CDC sink
+    ///
+    /// # Returns
+    ///
+    /// The CDC sink
+    pub fn get_sink(&self) -> Arc<Box<dyn CdcSink>> {
+        self.sink.clone()
+    }
+    
+    /// Get the finalized sink
+    ///
+    /// # Returns
+    ///
+    /// The sink for the finalized stream, if a separate one is attached
+    pub fn get_finalized_sink(&self) -> Option<Arc<Box<dyn CdcSink>>> {
+        self.finalized_sink.clone()
+    }
+    
+    /// Get the metashrew client
+    ///
+    /// # Returns
+    ///
+    /// The metashrew client
+    pub fn get_client(&self) -> Arc<C> {
+        self.client.clone()
+    }
+}
+
+/// The fork point and depth of a reorg, as announced by control messages
+struct ReorgFrame {
+    /// The common ancestor the stream is rolled back to
+    fork_height: u32,
+    
+    /// The number of emitted blocks rolled back
+    depth: u32,
+}
+
+/// Build a control message
+fn control_message(control: CdcControl) -> CdcControlMessage {
+    CdcControlMessage {
+        source: "debshrew".to_string(),
+        timestamp: now_ms(),
+        control,
+    }
+}
+
+/// Send blocks to a stream
+///
+/// Without control messages the CDC messages of every block are sent in a
+/// single batch; with them, each block is framed by `BEGIN_BLOCK` and
+/// `END_BLOCK`.
+///
+/// # Arguments
+///
+/// * `sink` - The sink of the stream
+/// * `stream` - The stream the blocks belong to (`tentative` or `finalized`)
+/// * `blocks` - The blocks to send, in order
+/// * `control_messages` - Whether to frame each block with control messages
+///
+/// # Returns
+///
+/// Ok(()) if the blocks were sent successfully
+///
+/// # Errors
+///
+/// Returns an error if the sink rejects a message
+async fn send_blocks(sink: &dyn CdcSink, stream: &'static str, blocks: Vec<EmittedBlock>, control_messages: bool) -> Result<()> {
+    if !control_messages {
+        let messages = blocks.into_iter().flat_map(|block| block.messages).collect();
+        return metrics::send_to_sink(sink, stream, messages).await;
+    }
+    
+    for block in blocks {
+        let message_count = block.messages.len() as u32;
+        sink.send_control(control_message(CdcControl::BeginBlock {
+            height: block.height,
+            hash: block.hash.clone(),
+            message_count,
+        })).await?;
+        if !block.messages.is_empty() {
+            metrics::send_to_sink(sink, stream, block.messages).await?;
+        }
+        sink.send_control(control_message(CdcControl::EndBlock {
+            height: block.height,
+            hash: block.hash,
+            message_count,
+        })).await?;
+    }
+    
+    Ok(())
+}
+
+/// Send the inverse messages of a reorg to a stream
+///
+/// # Arguments
+///
+/// * `sink` - The sink of the stream
+/// * `stream` - The stream the messages belong to (`tentative` or `finalized`)
+/// * `frame` - The reorg to frame the messages with, if control messages are enabled
+/// * `messages` - The inverse CDC messages
+///
+/// # Returns
+///
+/// Ok(()) if the messages were sent successfully
+///
+/// # Errors
+///
+/// Returns an error if the sink rejects a message
+async fn send_reorg(sink: &dyn CdcSink, stream: &'static str, frame: Option<ReorgFrame>, messages: Vec<CdcMessage>) -> Result<()> {
+    if let Some(frame) = &frame {
+        sink.send_control(control_message(CdcControl::ReorgBegin {
+            fork_height: frame.fork_height,
+            depth: frame.depth,
+        })).await?;
+    }
+    if !messages.is_empty() {
+        info!("Sending {} inverse CDC messages to the {} stream", messages.len(), stream);
+        metrics::send_to_sink(sink, stream, messages).await?;
+    }
+    if let Some(frame) = frame {
+        sink.send_control(control_message(CdcControl::ReorgEnd {
+            fork_height: frame.fork_height,
+            depth: frame.depth,
+        })).await?;
+    }
+    
+    Ok(())
+}
+
+/// Synchronizer trait
+///
+/// This trait defines the interface for block synchronizers.
+#[async_trait]
+pub trait Synchronizer: Send + Sync {
+    /// Run the synchronizer
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the synchronizer ran successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the synchronizer encounters an error
+    async fn run(&mut self) -> Result<()>;
+    
+    /// Stop the synchronizer
+    fn stop(&mut self);
+    
+    /// Get the current block height
+    ///
+    /// # Returns
+    ///
+    /// The current block height
+    fn get_current_height(&self) -> u32;
+}
+
+#[async_trait]
+impl<C: MetashrewClientLike + 'static> Synchronizer for BlockSynchronizer<C> {
+    async fn run(&mut self) -> Result<()> {
+        self.run().await
+    }
+    
+    fn stop(&mut self) {
+        self.stop();
+    }
+    
+    fn get_current_height(&self) -> u32 {
+        self.get_current_height()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::MemoryMetashrewAdapter;
+    use crate::client::MockMetashrewClient;
+    use crate::sink::{ConsoleSink, FileSink, NullSink};
+    use debshrew_runtime::transform::MockTransform;
+    use debshrew_support::{CdcHeader, CdcMessage, CdcOperation, CdcPayload};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn test_block_synchronizer() {
+        // Create a mock metashrew client
+        let mut client = MockMetashrewClient::new();
+        client.set_height(10);
+        
+        for i in 0..=10 {
+            client.set_block_hash(i, vec![i as u8]);
+        }
+        
+        // Create a mock transform
+        let _transform = MockTransform::default();
+        
+        // Create a WASM runtime for testing
+        // Use the client's URL for the metashrew URL
+        let runtime = WasmRuntime::for_testing().unwrap();
+        
+        // Create a null sink
+        let sink = Box::new(NullSink::new());
+        
+        // Create a block synchronizer
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, sink, 6).unwrap();
+        
+        // Set the starting height
+        synchronizer.set_starting_height(5);
+        
+        // Set a short polling interval
+        synchronizer.set_polling_interval(10);
+        
+        // Create a runtime for async tests
+        let rt = Runtime::new().unwrap();
+        
+        // Run the synchronizer for a short time
+        let handle = rt.spawn(async move {
+            let _ = synchronizer.run().await;
+        });
+        
+        // Wait for a short time
+        std::thread::sleep(Duration::from_millis(100));
+        
+        // Abort the task
+        handle.abort();
+    }
+    
+    #[test]
+    fn test_block_synchronizer_with_different_sinks() {
+        // Create a mock metashrew client
+        let mut client = MockMetashrewClient::new();
+        client.set_height(10);
+        
+        for i in 0..=10 {
+            client.set_block_hash(i, vec![i as u8]);
+        }
+        
+        // We don't need this runtime since we create a new one for each test
+        // let runtime = WasmRuntime::from_bytes(&[0]).unwrap();
+        
+        // Create a tokio runtime for async tests
+        let rt = Runtime::new().unwrap();
+        
+        // Create a new runtime for each test to avoid cloning
+        
+        // Test with ConsoleSink
+        {
+            let console_sink = Box::new(ConsoleSink::new(false));
+            let test_runtime = WasmRuntime::for_testing().unwrap();
+            let synchronizer = BlockSynchronizer::new(client.clone(), test_runtime, console_sink, 6).unwrap();
+            
+            // Verify the sink type
+            let sink = synchronizer.get_sink();
+            rt.block_on(async {
+                // Send a test message to verify the sink works
+                let message = create_test_message();
+                let result = sink.send(vec![message]).await;
+                assert!(result.is_ok());
+                
+                // Flush and close the sink
+                assert!(sink.flush().await.is_ok());
+                assert!(sink.close().await.is_ok());
+            });
+        }
+        
+        // Test with FileSink
+        {
+            // Create a temporary directory for the file
+            let dir = tempdir().unwrap();
+            let file_path = dir.path().join("test.json");
+            
+            let file_sink = Box::new(FileSink::new(file_path.to_str().unwrap(), false, 1000).unwrap());
+            let test_runtime = WasmRuntime::for_testing().unwrap();
+            let synchronizer = BlockSynchronizer::new(client.clone(), test_runtime, file_sink, 6).unwrap();
+            
+            // Verify the sink type
+            let sink = synchronizer.get_sink();
+            rt.block_on(async {
+                // Send a test message to verify the sink works
+                let message = create_test_message();
+                let result = sink.send(vec![message]).await;
+                assert!(result.is_ok());
+                
+                // Flush and close the sink
+                assert!(sink.flush().await.is_ok());
+                assert!(sink.close().await.is_ok());
+            });
+        }
+        
+        // Test with a custom sink implementation
+        {
+            // Create a custom sink that counts messages
+            #[derive(Clone)]
+            struct CountingSink {
+                count: Arc<tokio::sync::Mutex<usize>>,
+            }
+            
+            impl CountingSink {
+                fn new() -> Self {
+                    Self {
+                        count: Arc::new(tokio::sync::Mutex::new(0)),
+                    }
+                }
+                
+                async fn get_count(&self) -> usize {
+                    let count = self.count.lock().await;
+                    *count
+                }
+            }
+            
+            #[async_trait]
+            impl CdcSink for CountingSink {
+                async fn send(&self, messages: Vec<CdcMessage>) -> Result<()> {
+                    let mut count = self.count.lock().await;
+                    *count += messages.len();
+                    Ok(())
+                }
+                
+                async fn flush(&self) -> Result<()> {
+                    Ok(())
+                }
+                
+                async fn close(&self) -> Result<()> {
+                    Ok(())
+                }
+            }
+            
+            let counting_sink = CountingSink::new();
+            let counting_sink_clone = counting_sink.clone();
+            let test_runtime = WasmRuntime::for_testing().unwrap();
+            let synchronizer = BlockSynchronizer::new(client.clone(), test_runtime, Box::new(counting_sink), 6).unwrap();
+            
+            // Verify the sink works
+            rt.block_on(async {
+                // Get the sink from the synchronizer
+                let sink = synchronizer.get_sink();
+                
+                // Send test messages
+                let messages = vec![
+                    create_test_message(),
+                    create_test_message(),
+                    create_test_message(),
+                ];
+                
+                let result = sink.send(messages).await;
+                assert!(result.is_ok());
+                
+                // Verify the count
+                assert_eq!(counting_sink_clone.get_count().await, 3);
+            });
+        }
+    }
+    
+    /// Sink that counts calls and fails the test if two sends ever overlap
+    #[derive(Clone, Default)]
+    struct SerialSink {
+        sends: Arc<std::sync::atomic::AtomicUsize>,
+        in_flight: Arc<std::sync::atomic::AtomicBool>,
+        flushes: Arc<std::sync::atomic::AtomicUsize>,
+        closes: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl CdcSink for SerialSink {
+        async fn send(&self, _messages: Vec<CdcMessage>) -> Result<()> {
+            use std::sync::atomic::Ordering;
+            assert!(!self.in_flight.swap(true, Ordering::SeqCst), "sink sends overlapped");
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            self.in_flight.store(false, Ordering::SeqCst);
+            self.sends.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn flush(&self) -> Result<()> {
+            self.flushes.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn close(&self) -> Result<()> {
+            self.closes.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// A sink that fails a number of sends before accepting messages
+    #[derive(Default)]
+    struct FlakySink {
+        failures_left: Arc<std::sync::atomic::AtomicUsize>,
+        sends: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl CdcSink for FlakySink {
+        async fn send(&self, _messages: Vec<CdcMessage>) -> Result<()> {
+            use std::sync::atomic::Ordering;
+            let failing = self.failures_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+                .is_ok();
+            if failing {
+                return Err(Error::Sink("broker unavailable".to_string()));
+            }
+            self.sends.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn flush(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn close(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A runtime whose transform traps at a given height
+    fn create_trapping_runtime(trap_height: u32) -> WasmRuntime {
+        let wasm = wat::parse_str(format!(
+            r#"
+            (module
+                (import "env" "__height" (func $height (result i32)))
+                (func (export "process_block") (result i32)
+                    call $height
+                    i32.const {}
+                    i32.eq
+                    if
+                        unreachable
+                    end
+                    i32.const 0
+                )
+                (func (export "rollback") (result i32)
+                    i32.const 0
+                )
+                (memory (export "memory") 1)
+            )
+            "#,
+            trap_height
+        ))
+        .unwrap();
+        WasmRuntime::from_bytes(&wasm, "http://localhost:18888").unwrap()
+    }
+
+    /// Sink that rejects every message for good
+    struct RejectingSink;
+
+    #[async_trait]
+    impl CdcSink for RejectingSink {
+        async fn send(&self, _messages: Vec<CdcMessage>) -> Result<()> {
+            Err(Error::SinkRejected("duplicate key value violates unique constraint".to_string()))
+        }
+
+        async fn flush(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn close(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn fast_retry_policy(max_retries: Option<u32>) -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            max_retries,
+        }
+    }
+
+    /// Sink that records the data and control messages it receives, in order
+    #[derive(Clone, Default)]
+    struct RecordingSink {
+        records: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl RecordingSink {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.records.lock().unwrap())
+        }
+    }
+
+    #[async_trait]
+    impl CdcSink for RecordingSink {
+        async fn send(&self, messages: Vec<CdcMessage>) -> Result<()> {
+            self.records.lock().unwrap().push(format!("data {}", messages.len()));
+            Ok(())
+        }
+
+        async fn send_control(&self, message: CdcControlMessage) -> Result<()> {
+            let record = match message.control {
+                CdcControl::BeginBlock { height, message_count, .. } => format!("begin block {} {}", height, message_count),
+                CdcControl::EndBlock { height, message_count, .. } => format!("end block {} {}", height, message_count),
+                CdcControl::ReorgBegin { fork_height, depth } => format!("begin reorg {} {}", fork_height, depth),
+                CdcControl::ReorgEnd { fork_height, depth } => format!("end reorg {} {}", fork_height, depth),
+            };
+            self.records.lock().unwrap().push(record);
+            Ok(())
+        }
+
+        async fn flush(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn close(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Observer that records every event it is notified of
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl RecordingObserver {
+        fn events(&self) -> Vec<String> {
+            self.events.lock().unwrap().clone()
+        }
+
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    impl SyncObserver for RecordingObserver {
+        fn on_block_processed(&self, height: u32, hash: &str, messages: &[CdcMessage]) {
+            self.record(format!("block {} {} {}", height, &hash[..4], messages.len()));
+        }
+
+        fn on_reorg(&self, fork_height: u32, old_tip: u32, new_tip: u32) {
+            self.record(format!("reorg {} {} {}", fork_height, old_tip, new_tip));
+        }
+
+        fn on_error(&self, error: &Error) {
+            self.record(format!("error {}", error));
+        }
+
+        fn on_caught_up(&self, height: u32) {
+            self.record(format!("caught up {}", height));
+        }
+    }
+
+    /// Client that counts single and batched block hash requests
+    #[derive(Debug)]
+    struct BatchCountingClient {
+        inner: MockMetashrewClient,
+        single: Arc<std::sync::atomic::AtomicUsize>,
+        batched: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl crate::traits::BlockProviderLike for BatchCountingClient {
+        async fn get_height(&self) -> Result<u32> {
+            crate::traits::BlockProviderLike::get_height(&self.inner).await
+        }
+
+        async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+            self.single.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            crate::traits::BlockProviderLike::get_block_hash(&self.inner, height).await
+        }
+
+        async fn get_block_hashes(&self, heights: std::ops::RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+            self.batched.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            crate::traits::BlockProviderLike::get_block_hashes(&self.inner, heights).await
+        }
+    }
+
+    #[async_trait]
+    impl ViewProviderLike for BatchCountingClient {
+        async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+            ViewProviderLike::call_view(&self.inner, view_name, params, height).await
+        }
+    }
+
+    #[async_trait]
+    impl MetashrewClientLike for BatchCountingClient {
+        fn get_identifier(&self) -> String {
+            "batch-counting".to_string()
+        }
+    }
+
+    fn create_client_with_hashes(tip: u32, last_hash: u32) -> MockMetashrewClient {
+        let mut client = MockMetashrewClient::new();
+        client.set_height(tip);
+        for i in 0..=last_hash {
+            client.set_block_hash(i, vec![i as u8; 32]);
+        }
+        client
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_catch_up_pipeline_keeps_sends_serial() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_prefetch_depth(4);
+
+        synchronizer.catch_up(1, 20).await.unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 20);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 20);
+
+        let cache = synchronizer.get_cache().await;
+        let cache = cache.lock().await;
+        assert_eq!(cache.highest_height(), Some(20));
+        assert_eq!(cache.get_block_hash(20), Some(hex::encode(vec![20u8; 32])));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_catch_up_and_reorgs_batch_block_hash_requests() {
+        let single = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let batched = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let client = BatchCountingClient {
+            inner: create_client_with_hashes(20, 20),
+            single: single.clone(),
+            batched: batched.clone(),
+        };
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(NullSink::new()), 6).unwrap();
+        synchronizer.set_prefetch_depth(8);
+
+        // Blocks 1 to 20 in batches of 8
+        synchronizer.catch_up(1, 20).await.unwrap();
+        assert_eq!(synchronizer.get_current_height(), 20);
+        assert_eq!(batched.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert_eq!(single.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        // The common ancestor search asks for the cached range in one batch
+        synchronizer.handle_reorg(20).await.unwrap();
+        assert_eq!(batched.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rollback_cancels_view_prefetches() {
+        let client = create_client_with_hashes(20, 20);
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(NullSink::new()), 6).unwrap();
+        synchronizer.set_prefetch_views(true);
+        synchronizer.catch_up(1, 10).await.unwrap();
+
+        // A prefetch for the chain being rolled back is still in flight
+        let view_cache = synchronizer.view_cache.clone();
+        synchronizer.prefetch_tasks.spawn(async move {
+            time::sleep(Duration::from_millis(50)).await;
+            view_cache.insert("view", &[], 9, vec![1]);
+        });
+        synchronizer.view_cache.insert("view", &[], 10, vec![1]);
+
+        synchronizer.roll_back_to(8).await.unwrap();
+        assert!(synchronizer.prefetch_tasks.is_empty());
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(synchronizer.view_cache.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_catch_up_stops_at_unavailable_block() {
+        // Metashrew reports height 20 but only has hashes up to 12
+        let client = create_client_with_hashes(20, 12);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_prefetch_depth(8);
+
+        synchronizer.catch_up(1, 20).await.unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 12);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 12);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_finality_depth_holds_recent_blocks() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_finality_depth(3);
+
+        synchronizer.catch_up(1, 20).await.unwrap();
+
+        // Every block is transformed, but only blocks 1..=17 have 3 confirmations
+        assert_eq!(synchronizer.get_current_height(), 20);
+        assert_eq!(synchronizer.finalized_height, Some(17));
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 17);
+
+        // The held blocks are still in the cache
+        let cache = synchronizer.get_cache().await;
+        assert_eq!(cache.lock().await.lowest_height(), Some(15));
+
+        // Nothing new is final until the tip moves
+        synchronizer.emit_finalized(20).await.unwrap();
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 17);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_finality_depth_reorg_within_window_emits_nothing() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_finality_depth(3);
+        synchronizer.catch_up(1, 20).await.unwrap();
+
+        // Pretend blocks 19 and 20 were processed on a fork that metashrew has since abandoned
+        {
+            let cache = synchronizer.get_cache().await;
+            let mut cache = cache.lock().await;
+            let snapshot = cache.rollback(18).unwrap();
+            for height in 19..=20 {
+                let metadata = BlockMetadata {
+                    height,
+                    hash: "fork".to_string(),
+                    timestamp: 0,
+                };
+                let result = debshrew_runtime::TransformResult {
+                    cdc_messages: Vec::new(),
+                    state_snapshot: snapshot.clone(),
+                };
+                cache.add_block(metadata, result).unwrap();
+            }
+        }
+
+        synchronizer.handle_reorg(20).await.unwrap();
+
+        // The fork never reached the sink, so no inverse messages are sent
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 17);
+        assert_eq!(synchronizer.finalized_height, Some(17));
+        let last_reorg = synchronizer.status_handle().snapshot().last_reorg.unwrap();
+        assert_eq!(last_reorg.common_ancestor, 18);
+
+        let cache = synchronizer.get_cache().await;
+        let cache = cache.lock().await;
+        assert_eq!(cache.get_block_hash(20), Some(hex::encode(vec![20u8; 32])));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dual_tentative_and_finalized_sinks() {
+        let client = create_client_with_hashes(20, 20);
+        let tentative = SerialSink::default();
+        let tentative_sends = tentative.sends.clone();
+        let finalized = SerialSink::default();
+        let finalized_sends = finalized.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(tentative), 6).unwrap();
+        synchronizer.set_finality_depth(3);
+        synchronizer.set_finalized_sink(Box::new(finalized));
+
+        synchronizer.catch_up(1, 20).await.unwrap();
+
+        // The tentative stream sees every block, the finalized stream only the deep ones
+        assert_eq!(tentative_sends.load(std::sync::atomic::Ordering::SeqCst), 20);
+        assert_eq!(finalized_sends.load(std::sync::atomic::Ordering::SeqCst), 17);
+        assert_eq!(synchronizer.emitted_height, Some(20));
+        assert_eq!(synchronizer.finalized_height, Some(17));
+
+        // A reorg inside the window is replayed on the tentative stream only
+        {
+            let cache = synchronizer.get_cache().await;
+            let mut cache = cache.lock().await;
+            let snapshot = cache.rollback(18).unwrap();
+            for height in 19..=20 {
+                let metadata = BlockMetadata {
+                    height,
+                    hash: "fork".to_string(),
+                    timestamp: 0,
+                };
+                let result = debshrew_runtime::TransformResult {
+                    cdc_messages: Vec::new(),
+                    state_snapshot: snapshot.clone(),
+                };
+                cache.add_block(metadata, result).unwrap();
+            }
+        }
+
+        synchronizer.handle_reorg(20).await.unwrap();
+
+        assert_eq!(tentative_sends.load(std::sync::atomic::Ordering::SeqCst), 22);
+        assert_eq!(finalized_sends.load(std::sync::atomic::Ordering::SeqCst), 17);
+        assert_eq!(synchronizer.emitted_height, Some(20));
+        assert_eq!(synchronizer.finalized_height, Some(17));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_control_messages_frame_blocks_and_reorgs() {
+        let client = create_client_with_hashes(20, 20);
+        let tentative = RecordingSink::default();
+        let finalized = RecordingSink::default();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(tentative.clone()), 6).unwrap();
+        synchronizer.set_finality_depth(3);
+        synchronizer.set_finalized_sink(Box::new(finalized.clone()));
+        synchronizer.set_control_messages(true);
+
+        synchronizer.catch_up(1, 5).await.unwrap();
+
+        // Every block is framed, even when it produced no CDC messages
+        let expected: Vec<String> = (1..=5)
+            .flat_map(|height| vec![format!("begin block {} 0", height), format!("end block {} 0", height)])
+            .collect();
+        assert_eq!(tentative.take(), expected);
+        assert_eq!(finalized.take(), expected[..4].to_vec());
+
+        // Pretend block 5 was processed on a fork that metashrew has since abandoned
+        {
+            let cache = synchronizer.get_cache().await;
+            let mut cache = cache.lock().await;
+            let snapshot = cache.rollback(4).unwrap();
+            let metadata = BlockMetadata {
+                height: 5,
+                hash: "fork".to_string(),
+                timestamp: 0,
+            };
+            let result = debshrew_runtime::TransformResult {
+                cdc_messages: Vec::new(),
+                state_snapshot: snapshot,
+            };
+            cache.add_block(metadata, result).unwrap();
+        }
+
+        synchronizer.handle_reorg(5).await.unwrap();
+
+        // The reorg is framed on the stream that saw the rolled back block, followed by the replacement
+        assert_eq!(tentative.take(), vec![
+            "begin reorg 4 1".to_string(),
+            "end reorg 4 1".to_string(),
+            "begin block 5 0".to_string(),
+            "end block 5 0".to_string(),
+        ]);
+        assert!(finalized.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_admin_rewind_rolls_back_and_reprocesses() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = RecordingSink::default();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink.clone()), 6).unwrap();
+        synchronizer.set_control_messages(true);
+        synchronizer.catch_up(1, 10).await.unwrap();
+        sink.take();
+
+        // A rewind below the block cache is dropped
+        let admin = synchronizer.admin_handle();
+        admin.request_rewind(2);
+        synchronizer.apply_rewind().await.unwrap();
+        assert_eq!(admin.pending_rewind(), None);
+        assert_eq!(synchronizer.get_current_height(), 10);
+        assert!(sink.take().is_empty());
+
+        admin.request_rewind(7);
+        synchronizer.apply_rewind().await.unwrap();
+        assert_eq!(admin.pending_rewind(), None);
+        assert_eq!(synchronizer.get_current_height(), 7);
+        assert_eq!(sink.take(), vec!["begin reorg 7 3".to_string(), "end reorg 7 3".to_string()]);
+
+        synchronizer.catch_up(8, 10).await.unwrap();
+        assert_eq!(synchronizer.get_current_height(), 10);
+        assert_eq!(sink.take().len(), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_admin_pause_holds_processing_until_resumed() {
+        let client = create_client_with_hashes(20, 20);
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(NullSink::new()), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_end_height(3);
+
+        let admin = synchronizer.admin_handle();
+        let status = synchronizer.status_handle();
+        admin.pause();
+
+        let task = tokio::spawn(async move {
+            let result = synchronizer.run().await;
+            (synchronizer, result)
+        });
+
+        // Wait for the synchronizer to report that it is paused
+        while !status.snapshot().paused {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(status.snapshot().current_height, 0);
+
+        admin.resume();
+        let (synchronizer, result) = tokio::time::timeout(Duration::from_secs(30), task)
+            .await
+            .expect("run did not resume after the resume request")
+            .unwrap();
+        result.unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 3);
+        assert!(!status.snapshot().paused);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_stops_at_end_height() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let dir = tempdir().unwrap();
+        let store = CheckpointStore::new(dir.path().join("checkpoint.json"));
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_end_height(10);
+        synchronizer.set_checkpoint_store(store.clone());
+        let status = synchronizer.status_handle();
+
+        tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run did not stop at the end height")
+            .unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 10);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 10);
+
+        // The published status reflects the live synchronizer
+        let status = status.snapshot();
+        assert!(!status.running);
+        assert_eq!(status.current_height, 10);
+        assert_eq!(status.indexed_height, Some(20));
+        assert_eq!(status.lag, Some(10));
+        assert_eq!(status.emitted_height, Some(10));
+        assert_eq!((status.cache_lowest_height, status.cache_highest_height), (Some(5), Some(10)));
+        assert!(status.last_error.is_none());
+
+        let checkpoint = store.load().unwrap().unwrap();
+        assert_eq!(checkpoint.height, 10);
+        assert_eq!(checkpoint.hash, hex::encode(vec![10u8; 32]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_starting_at_end_height_stops_at_once() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_starting_height(10);
+        synchronizer.set_end_height(10);
+
+        tokio::time::timeout(Duration::from_secs(5), synchronizer.run())
+            .await
+            .expect("run did not stop at the end height")
+            .unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 10);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_resuming_past_end_height_keeps_checkpoint() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let dir = tempdir().unwrap();
+        let store = CheckpointStore::new(dir.path().join("checkpoint.json"));
+        let checkpoint = Checkpoint {
+            height: 15,
+            hash: hex::encode(vec![15u8; 32]),
+            timestamp: 0,
+        };
+        store.save(&checkpoint).unwrap();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_starting_height(store.load().unwrap().unwrap().height);
+        synchronizer.set_checkpoint_store(store.clone());
+        synchronizer.set_end_height(10);
+
+        tokio::time::timeout(Duration::from_secs(5), synchronizer.run())
+            .await
+            .expect("run did not stop past the end height")
+            .unwrap();
+
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(store.load().unwrap().unwrap().height, 15);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stop_handle_shuts_down_gracefully() {
+        let client = create_client_with_hashes(20, 20);
+        let sink = SerialSink::default();
+        let probe = sink.clone();
+
+        let dir = tempdir().unwrap();
+        let store = CheckpointStore::new(dir.path().join("checkpoint.json"));
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        // A long polling interval, so the stop has to interrupt the wait
+        synchronizer.set_polling_interval(60_000);
+        synchronizer.set_checkpoint_store(store.clone());
+
+        let stop_handle = synchronizer.stop_handle();
+        let task = tokio::spawn(async move {
+            let result = synchronizer.run().await;
+            (synchronizer, result)
+        });
+
+        // Wait for the first block to be emitted
+        while probe.sends.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        stop_handle.stop();
+
+        let (synchronizer, result) = tokio::time::timeout(Duration::from_secs(10), task)
+            .await
+            .expect("run did not stop after the stop request")
+            .unwrap();
+        result.unwrap();
+
+        assert_eq!(probe.flushes.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(probe.closes.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let checkpoint = store.load().unwrap().unwrap();
+        assert_eq!(Some(checkpoint.height), synchronizer.checkpoint_height());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_waits_for_lagging_indexer() {
+        // Metashrew reports 20 blocks indexed, but only serves hashes up to 12
+        let adapter = MemoryMetashrewAdapter::new();
+        adapter.set_height(20);
+        for height in 0..=12 {
+            adapter.set_block_hash(height, vec![height as u8; 32]);
+        }
+
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(adapter.clone(), runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(5);
+        synchronizer.set_end_height(20);
+
+        let task = tokio::spawn(async move {
+            let result = synchronizer.run().await;
+            (synchronizer, result)
+        });
+
+        // Blocks are processed up to the last available hash, and no further
+        while sends.load(std::sync::atomic::Ordering::SeqCst) < 12 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 12);
+
+        // The indexer catches up
+        for height in 13..=20 {
+            adapter.set_block_hash(height, vec![height as u8; 32]);
+        }
+
+        let (synchronizer, result) = tokio::time::timeout(Duration::from_secs(10), task)
+            .await
+            .expect("run did not reach the end height")
+            .unwrap();
+        result.unwrap();
+
+        // Every block was emitted exactly once
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 20);
+        assert_eq!(synchronizer.get_current_height(), 20);
+        let cache = synchronizer.get_cache().await;
+        assert_eq!(cache.lock().await.get_block_hash(20), Some(hex::encode(vec![20u8; 32])));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_process_block_does_not_skip_missing_hash() {
+        let adapter = MemoryMetashrewAdapter::new();
+        adapter.set_height(5);
+        for height in 0..=4 {
+            adapter.set_block_hash(height, vec![height as u8; 32]);
+        }
+
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(adapter, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_starting_height(4);
+
+        // A missing hash is an error, and the block is not marked as processed
+        assert!(synchronizer.process_block(5).await.is_err());
+        assert_eq!(synchronizer.get_current_height(), 4);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stream_block_source_wakes_synchronizer() {
+        let adapter = MemoryMetashrewAdapter::new();
+        adapter.set_height(5);
+        for height in 0..=5 {
+            adapter.set_block_hash(height, vec![height as u8; 32]);
+        }
+
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let (url, tips) = crate::source::tests::spawn_tip_stream().await;
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(adapter.clone(), runtime, Box::new(sink), 6).unwrap();
+        // Polling alone would take a minute to notice the new block
+        synchronizer.set_polling_interval(60_000);
+        synchronizer.set_block_source(Box::new(crate::source::StreamBlockSource::new(&url, 60_000).unwrap()));
+        synchronizer.set_end_height(6);
+
+        let task = tokio::spawn(async move {
+            let result = synchronizer.run().await;
+            (synchronizer, result)
+        });
+
+        while sends.load(std::sync::atomic::Ordering::SeqCst) < 5 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // A new block is indexed and announced on the stream
+        adapter.set_block_hash(6, vec![6u8; 32]);
+        adapter.set_height(6);
+        tips.send(format!("{{\"height\": 6, \"hash\": \"{}\"}}\n", hex::encode(vec![6u8; 32]))).unwrap();
+
+        let (synchronizer, result) = tokio::time::timeout(Duration::from_secs(10), task)
+            .await
+            .expect("synchronizer was not woken by the tip event")
+            .unwrap();
+        result.unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 6);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 6);
+    }
+
+    // Helper function to create a test CDC message
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_transient_sink_failures_are_retried() {
+        let client = create_client_with_hashes(10, 10);
+        let sink = FlakySink::default();
+        sink.failures_left.store(3, std::sync::atomic::Ordering::SeqCst);
+        let sends = sink.sends.clone();
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_end_height(5);
+        synchronizer.set_retry_policy(fast_retry_policy(Some(5)));
+        let status = synchronizer.status_handle();
+
+        tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run did not recover from the sink failures")
+            .unwrap();
+
+        // Every block reached the sink exactly once, and the cache holds each block once
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 5);
+        assert_eq!(synchronizer.get_cache().await.lock().await.len(), 5);
+
+        let status = status.snapshot();
+        assert_eq!(status.failing_height, None);
+        assert_eq!(status.consecutive_failures, 0);
+        assert!(status.last_error.unwrap().message.contains("broker unavailable"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failed_finalized_send_is_retried_on_its_own() {
+        let client = create_client_with_hashes(20, 20);
+        let tentative = SerialSink::default();
+        let tentative_sends = tentative.sends.clone();
+        let finalized = FlakySink::default();
+        finalized.failures_left.store(2, std::sync::atomic::Ordering::SeqCst);
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(tentative), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_retry_policy(fast_retry_policy(Some(5)));
+        synchronizer.set_finality_depth(3);
+        synchronizer.set_finalized_sink(Box::new(finalized));
+        synchronizer.set_end_height(10);
+
+        tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run did not recover from the finalized sink failures")
+            .unwrap();
+
+        // Every block reached the tentative stream exactly once
+        assert_eq!(tentative_sends.load(std::sync::atomic::Ordering::SeqCst), 10);
+        assert_eq!(synchronizer.emitted_height, Some(10));
+        assert_eq!(synchronizer.finalized_height, Some(10));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rejected_messages_halt_without_retrying() {
+        let client = create_client_with_hashes(10, 10);
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(RejectingSink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_retry_policy(fast_retry_policy(None));
+        let status = synchronizer.status_handle();
+
+        let result = tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run retried messages the sink rejected");
+        assert!(matches!(result, Err(Error::SinkRejected(_))));
+        assert_eq!(status.snapshot().consecutive_failures, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_transient_failures_halt_after_max_retries() {
+        let client = create_client_with_hashes(10, 10);
+        let sink = FlakySink::default();
+        sink.failures_left.store(usize::MAX, std::sync::atomic::Ordering::SeqCst);
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_retry_policy(fast_retry_policy(Some(2)));
+        let status = synchronizer.status_handle();
+
+        let result = tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run kept retrying past the retry limit");
+        assert!(matches!(result, Err(Error::Sink(_))));
+
+        // The repeated failures at block 1 are visible in the status
+        let status = status.snapshot();
+        assert_eq!(status.failing_height, Some(1));
+        assert_eq!(status.consecutive_failures, 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_transform_failure_halts_with_context() {
+        let client = create_client_with_hashes(10, 10);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+        let closes = sink.closes.clone();
+
+        let mut synchronizer = BlockSynchronizer::new(client, create_trapping_runtime(4), Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_retry_policy(fast_retry_policy(None));
+
+        let result = tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run retried a deterministic transform failure");
+
+        // The transform failure is not retried, and the blocks before it were emitted
+        match result {
+            Err(Error::Transform(message)) => {
+                assert!(message.contains("Block 4"), "{}", message);
+                assert!(message.contains(&hex::encode(vec![4u8; 32])), "{}", message);
+            }
+            other => panic!("Expected a transform error, got {:?}", other.err()),
+        }
+        assert_eq!(synchronizer.get_current_height(), 3);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // The sink is closed once on the way out
+        assert_eq!(closes.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_transform_failure_is_dead_lettered() {
+        let client = create_client_with_hashes(10, 10);
+        let sink = SerialSink::default();
+        let sends = sink.sends.clone();
+
+        let dir = tempdir().unwrap();
+        let queue = DeadLetterQueue::new(dir.path().join("dead-letters.jsonl"));
+
+        let mut synchronizer = BlockSynchronizer::new(client, create_trapping_runtime(4), Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_end_height(6);
+        synchronizer.set_dead_letter_queue(queue.clone());
+
+        tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run did not move past the failing block")
+            .unwrap();
+
+        assert_eq!(synchronizer.get_current_height(), 6);
+        assert_eq!(sends.load(std::sync::atomic::Ordering::SeqCst), 6);
+
+        let letters = queue.load().unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].height, 4);
+        assert_eq!(letters[0].hash, hex::encode(vec![4u8; 32]));
+        assert!(letters[0].error.contains("unreachable"), "{}", letters[0].error);
+
+        // The dead-lettered block is cached without CDC messages
+        let cache = synchronizer.get_cache().await;
+        assert_eq!(cache.lock().await.get_cdc_messages(4), Some(Vec::new()));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reorg_across_dead_lettered_block() {
+        let client = create_client_with_hashes(10, 10);
+        let dir = tempdir().unwrap();
+        let queue = DeadLetterQueue::new(dir.path().join("dead-letters.jsonl"));
+
+        let mut synchronizer = BlockSynchronizer::new(client, create_trapping_runtime(4), Box::new(NullSink::new()), 6).unwrap();
+        synchronizer.set_dead_letter_queue(queue.clone());
+        synchronizer.catch_up(1, 6).await.unwrap();
+
+        // Pretend blocks 3 to 6 were processed on a fork that metashrew has since abandoned
+        {
+            let cache = synchronizer.get_cache().await;
+            let mut cache = cache.lock().await;
+            let snapshot = cache.rollback(2).unwrap();
+            for height in 3..=6 {
+                let metadata = BlockMetadata {
+                    height,
+                    hash: "fork".to_string(),
+                    timestamp: 0,
+                };
+                let result = debshrew_runtime::TransformResult {
+                    cdc_messages: Vec::new(),
+                    state_snapshot: snapshot.clone(),
+                };
+                cache.add_block(metadata, result).unwrap();
+            }
+        }
+
+        // Rolling back the dead-lettered block has nothing to invert
+        synchronizer.handle_reorg(6).await.unwrap();
+        assert_eq!(synchronizer.get_current_height(), 6);
+        assert_eq!(queue.load().unwrap().len(), 2);
+
+        // The same holds for an admin rewind across it
+        let admin = synchronizer.admin_handle();
+        admin.request_rewind(3);
+        synchronizer.apply_rewind().await.unwrap();
+        assert_eq!(synchronizer.get_current_height(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_observers_are_notified_of_blocks_errors_and_catch_up() {
+        let client = create_client_with_hashes(3, 3);
+        let sink = FlakySink::default();
+        sink.failures_left.store(1, std::sync::atomic::Ordering::SeqCst);
+
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(sink), 6).unwrap();
+        synchronizer.set_polling_interval(1);
+        synchronizer.set_end_height(3);
+        synchronizer.set_retry_policy(fast_retry_policy(None));
+
+        let observer = Arc::new(RecordingObserver::default());
+        synchronizer.add_observer(observer.clone());
+
+        tokio::time::timeout(Duration::from_secs(30), synchronizer.run())
+            .await
+            .expect("run did not reach the end height")
+            .unwrap();
+
+        // The failed send is reported, then every block once it reached the sink
+        assert_eq!(observer.events(), vec![
+            "error Sink error: broker unavailable".to_string(),
+            "block 1 0101 0".to_string(),
+            "block 2 0202 0".to_string(),
+            "block 3 0303 0".to_string(),
+            "caught up 3".to_string(),
+        ]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_observers_are_notified_of_reorgs() {
+        let client = create_client_with_hashes(20, 20);
+        let runtime = WasmRuntime::for_testing().unwrap();
+        let mut synchronizer = BlockSynchronizer::new(client, runtime, Box::new(NullSink::new()), 6).unwrap();
+        synchronizer.set_finality_depth(3);
+        synchronizer.catch_up(1, 20).await.unwrap();
+
+        let observer = Arc::new(RecordingObserver::default());
+        synchronizer.add_observer(observer.clone());
+
+        // Pretend block 20 was processed on a fork that metashrew has since abandoned
+        {
+            let cache = synchronizer.get_cache().await;
+            let mut cache = cache.lock().await;
+            let snapshot = cache.rollback(19).unwrap();
+            let metadata = BlockMetadata {
+                height: 20,
+                hash: "fork".to_string(),
+                timestamp: 0,
+            };
+            let result = debshrew_runtime::TransformResult {
+                cdc_messages: Vec::new(),
+                state_snapshot: snapshot,
+            };
+            cache.add_block(metadata, result).unwrap();
+        }
+
+        synchronizer.handle_reorg(20).await.unwrap();
+
+        // The rollback is reported before the replacement block
+        assert_eq!(observer.events(), vec!["reorg 19 20 20".to_string(), "block 20 1414 0".to_string()]);
+    }
+
+    fn create_test_message() -> CdcMessage {
+        CdcMessage {
+            header: CdcHeader {
+                source: "test".to_string(),
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64,
+                block_height: 123,
+                block_hash: "000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d".to_string(),
+                transaction_id: None,
+                inverse: false,
+            },
+            payload: CdcPayload {
+                operation: CdcOperation::Create,
+                table: "test_table".to_string(),
+                key: "test_key".to_string(),
+                before: None,
+                after: Some(serde_json::json!({
+                    "field1": "value1",
+                    "field2": 42
+                })),
+            },
+        }
     }
 }
@@ -131,6 +131,7 @@ impl TestUtils {
                     block_height: height as u32,
                     block_hash: format!("{:02x}", hash_byte),
                     transaction_id: None,
+                    inverse: false,
                 },
                 payload: crate::CdcPayload {
                     operation: crate::CdcOperation::Create,
@@ -11,7 +11,14 @@ use super::{TestConfig, TestUtils};
 use crate::adapters::MemoryMetashrewAdapter;
 use crate::error::Result;
 use crate::traits::{MetashrewClientLike, BlockchainSimulatorLike, BlockProviderLike, ViewProviderLike};
-use crate::{CdcMessage, CdcOperation};
+use crate::{CdcControl, CdcControlMessage, CdcMessage, CdcOperation};
+use crate::error_policy::RetryPolicy;
+use crate::runtime::WasmRuntime;
+use crate::sink::CdcSink;
+use crate::synchronizer::BlockSynchronizer;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// Simple CDC processor that tracks block processing and generates messages
 pub struct SimpleCdcProcessor {
@@ -64,6 +71,7 @@ impl SimpleCdcProcessor {
                 block_height: height,
                 block_hash: format!("{:02x}", hash),
                 transaction_id: None,
+                inverse: false,
             },
             payload: crate::CdcPayload {
                 operation: CdcOperation::Create,
@@ -110,6 +118,7 @@ impl SimpleCdcProcessor {
                                 block_height: target_height, // Target height for rollback
                                 block_hash: original_message.header.block_hash.clone(),
                                 transaction_id: None,
+                                inverse: false,
                             },
                             payload: crate::CdcPayload {
                                 operation: CdcOperation::Delete,
@@ -433,4 +442,139 @@ async fn test_comprehensive_reorg_all_verifications() -> Result<()> {
     println!("   - Adapter state consistency verified");
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Sink that records block and reorg boundaries
+#[derive(Clone, Default)]
+struct BoundaryRecordingSink {
+    records: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl CdcSink for BoundaryRecordingSink {
+    async fn send(&self, _messages: Vec<CdcMessage>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send_control(&self, message: CdcControlMessage) -> Result<()> {
+        let record = match message.control {
+            CdcControl::BeginBlock { height, .. } => format!("block {}", height),
+            CdcControl::ReorgBegin { fork_height, depth } => format!("reorg {} {}", fork_height, depth),
+            _ => return Ok(()),
+        };
+        self.records.lock().unwrap().push(record);
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A transform that reads the `tip` view for every block, and traps if the view call fails
+fn create_view_reading_runtime() -> Result<WasmRuntime> {
+    let wasm = wat::parse_str(
+        r#"
+        (module
+            (import "env" "__view" (func $view (param i32 i32) (result i32)))
+            (func (export "process_block") (result i32)
+                i32.const 0
+                i32.const 16
+                call $view
+                i32.const 0
+                i32.lt_s
+                if
+                    unreachable
+                end
+                i32.const 0
+            )
+            (func (export "rollback") (result i32)
+                i32.const 0
+            )
+            (memory (export "memory") 1)
+            (data (i32.const 0) "\03\00\00\00tip")
+            (data (i32.const 16) "\00\00\00\00")
+        )
+        "#,
+    )
+    .map_err(|e| crate::error::Error::Transform(e.to_string()))?;
+    WasmRuntime::from_bytes(&wasm, "http://localhost:18888")
+}
+
+/// Test a full synchronizer run over a simulated chain that reorgs while it is followed
+#[tokio::test(flavor = "multi_thread")]
+async fn test_synchronizer_run_through_simulated_reorg() -> Result<()> {
+    let mut chain = MemoryMetashrewAdapter::with_identifier("simulated-chain");
+    chain.set_block_hash(0, vec![0; 32]);
+    for height in 1..=10 {
+        chain.advance_block(Some(format!("initial_block_{}", height).as_bytes()))?;
+    }
+
+    // The transform's view calls are served by the same adapter as the blocks
+    for height in 1..=12 {
+        chain.set_view_result("tip", &[], Some(height), height.to_le_bytes().to_vec());
+    }
+
+    let sink = BoundaryRecordingSink::default();
+    let records = sink.records.clone();
+
+    let mut synchronizer = BlockSynchronizer::new(chain.clone(), create_view_reading_runtime()?, Box::new(sink), 6)?;
+    synchronizer.set_polling_interval(5);
+    synchronizer.set_control_messages(true);
+    synchronizer.set_retry_policy(RetryPolicy {
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(5),
+        max_retries: Some(2),
+    });
+    let status = synchronizer.status_handle();
+    let stop_handle = synchronizer.stop_handle();
+
+    let task = tokio::spawn(async move { synchronizer.run().await });
+
+    while status.snapshot().current_height < 10 {
+        assert!(!task.is_finished(), "synchronizer stopped before reaching the tip");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    // Replace blocks 8 to 10
+    let new_blocks = TestUtils::create_test_blocks(3, "reorg");
+    let (new_tip, _) = chain.simulate_reorg(7, new_blocks)?;
+    assert_eq!(new_tip, 10);
+
+    let wait = async {
+        while status.snapshot().last_reorg.is_none() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(30), wait)
+        .await
+        .expect("synchronizer did not detect the reorg");
+    assert_eq!(status.snapshot().last_reorg.unwrap().common_ancestor, 7);
+
+    // The new chain keeps growing
+    chain.advance_block(Some(b"block_11"))?;
+    chain.advance_block(Some(b"block_12"))?;
+
+    let wait = async {
+        while status.snapshot().current_height < 12 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(30), wait)
+        .await
+        .expect("synchronizer did not follow the new chain");
+    stop_handle.stop();
+    task.await.unwrap()?;
+
+    // Blocks 1 to 10, the rollback of 8 to 10, then the new blocks 8 to 12
+    let mut expected: Vec<String> = (1..=10).map(|height| format!("block {}", height)).collect();
+    expected.push("reorg 7 3".to_string());
+    expected.extend((8..=12).map(|height| format!("block {}", height)));
+    assert_eq!(*records.lock().unwrap(), expected);
+
+    Ok(())
+}
@@ -0,0 +1,291 @@
+//! Metashrew tip tracking
+//!
+//! This module provides the tip tracker, which determines how far the
+//! synchronizer may safely go. Metashrew can report an indexed height before the
+//! block hashes up to that height can be served, for example while the indexer
+//! is still catching up. The tracker reports both heights, so that blocks are
+//! only processed once their hash is actually available.
+
+use crate::traits::BlockProviderLike;
+use crate::error::{Error, Result};
+use log::{debug, warn};
+
+/// Messages that say a block is not indexed yet
+///
+/// Metashrew reports these with the generic JSON-RPC server error code, which
+/// it uses for real failures as well, so only the message tells them apart.
+const NOT_AVAILABLE_MESSAGES: [&str; 2] = ["Block hash not found", "not yet indexed"];
+
+/// Check whether an error means the block is not available from metashrew yet
+///
+/// # Arguments
+///
+/// * `error` - The error returned by a block hash request
+///
+/// # Returns
+///
+/// True if the block is not available yet, false for any other failure
+pub fn is_block_not_available(error: &Error) -> bool {
+    is_not_available_message(&error.to_string())
+}
+
+/// Check whether an error message means the block is not available yet
+///
+/// # Arguments
+///
+/// * `message` - The error message
+///
+/// # Returns
+///
+/// True if the message says the block is not indexed yet
+pub(crate) fn is_not_available_message(message: &str) -> bool {
+    NOT_AVAILABLE_MESSAGES.iter().any(|pattern| message.contains(pattern))
+}
+
+/// Metashrew tip
+///
+/// A snapshot of what metashrew can serve at the time of a poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tip {
+    /// The height metashrew reports as indexed
+    pub indexed_height: u32,
+
+    /// The highest height whose block hash is available, or None if there is none
+    pub available_height: Option<u32>,
+}
+
+impl Tip {
+    /// Check whether block hashes lag behind the indexed height
+    ///
+    /// # Returns
+    ///
+    /// True if some indexed blocks do not have their hash available yet
+    pub fn is_lagging(&self) -> bool {
+        self.available_height != Some(self.indexed_height)
+    }
+}
+
+/// Tip tracker
+///
+/// The tip tracker polls metashrew for its indexed height and then finds the
+/// highest height whose block hash is available. Hashes are assumed to become
+/// available in height order, so the search is a binary search between the
+/// last known available height and the indexed height.
+#[derive(Debug, Default)]
+pub struct TipTracker {
+    /// The most recently observed tip
+    last_tip: Option<Tip>,
+}
+
+impl TipTracker {
+    /// Create a new tip tracker
+    ///
+    /// # Returns
+    ///
+    /// A new tip tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the most recently observed tip
+    ///
+    /// # Returns
+    ///
+    /// The tip from the last successful poll, or None if there was none
+    pub fn last_tip(&self) -> Option<Tip> {
+        self.last_tip
+    }
+
+    /// Poll metashrew for its current tip
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The metashrew client
+    ///
+    /// # Returns
+    ///
+    /// The current tip
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the height cannot be fetched, or a block hash request
+    /// fails for any reason other than the block not being available yet
+    pub async fn poll<C: BlockProviderLike + ?Sized>(&mut self, client: &C) -> Result<Tip> {
+        let indexed_height = client.get_height().await?;
+
+        let available_height = if Self::is_available(client, indexed_height).await? {
+            Some(indexed_height)
+        } else {
+            self.search_available(client, indexed_height).await?
+        };
+
+        let tip = Tip {
+            indexed_height,
+            available_height,
+        };
+
+        if tip.is_lagging() {
+            warn!(
+                "Metashrew reports height {} but block hashes are only available up to {:?}",
+                indexed_height, available_height
+            );
+        } else {
+            debug!("Metashrew tip at height {}", indexed_height);
+        }
+
+        self.last_tip = Some(tip);
+        Ok(tip)
+    }
+
+    /// Find the highest available height below an unavailable one
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The metashrew client
+    /// * `unavailable_height` - A height whose block hash is not available
+    ///
+    /// # Returns
+    ///
+    /// The highest available height, or None if no block hash is available
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a block hash request fails unexpectedly
+    async fn search_available<C: BlockProviderLike + ?Sized>(
+        &self,
+        client: &C,
+        unavailable_height: u32,
+    ) -> Result<Option<u32>> {
+        // Start from the last available height if it is still below the tip and available
+        let known = self
+            .last_tip
+            .and_then(|tip| tip.available_height)
+            .filter(|height| *height < unavailable_height);
+
+        let mut low = match known {
+            Some(height) if Self::is_available(client, height).await? => height,
+            _ => {
+                if unavailable_height == 0 || !Self::is_available(client, 0).await? {
+                    return Ok(None);
+                }
+                0
+            }
+        };
+        let mut high = unavailable_height;
+
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            if Self::is_available(client, middle).await? {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        Ok(Some(low))
+    }
+
+    /// Check whether the block hash at a height is available
+    async fn is_available<C: BlockProviderLike + ?Sized>(client: &C, height: u32) -> Result<bool> {
+        match client.get_block_hash(height).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_block_not_available(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::MemoryMetashrewAdapter;
+
+    fn create_adapter(indexed_height: u32, last_hash: Option<u32>) -> MemoryMetashrewAdapter {
+        let adapter = MemoryMetashrewAdapter::new();
+        adapter.set_height(indexed_height);
+        if let Some(last_hash) = last_hash {
+            for height in 0..=last_hash {
+                adapter.set_block_hash(height, vec![height as u8; 32]);
+            }
+        }
+        adapter
+    }
+
+    #[tokio::test]
+    async fn test_tip_tracker_in_sync() {
+        let adapter = create_adapter(100, Some(100));
+        let mut tracker = TipTracker::new();
+
+        let tip = tracker.poll(&adapter).await.unwrap();
+        assert_eq!(tip.indexed_height, 100);
+        assert_eq!(tip.available_height, Some(100));
+        assert!(!tip.is_lagging());
+        assert_eq!(tracker.last_tip(), Some(tip));
+    }
+
+    #[tokio::test]
+    async fn test_tip_tracker_lagging_indexer() {
+        // Metashrew reports 100 blocks indexed, but hashes are only served up to 63
+        let adapter = create_adapter(100, Some(63));
+        let mut tracker = TipTracker::new();
+
+        let tip = tracker.poll(&adapter).await.unwrap();
+        assert_eq!(tip.indexed_height, 100);
+        assert_eq!(tip.available_height, Some(63));
+        assert!(tip.is_lagging());
+
+        // Once more hashes become available, the search resumes from the last tip
+        for height in 64..=80 {
+            adapter.set_block_hash(height, vec![height as u8; 32]);
+        }
+        let tip = tracker.poll(&adapter).await.unwrap();
+        assert_eq!(tip.available_height, Some(80));
+
+        // And catches up completely
+        for height in 81..=100 {
+            adapter.set_block_hash(height, vec![height as u8; 32]);
+        }
+        let tip = tracker.poll(&adapter).await.unwrap();
+        assert_eq!(tip.available_height, Some(100));
+        assert!(!tip.is_lagging());
+    }
+
+    #[tokio::test]
+    async fn test_tip_tracker_no_blocks_available() {
+        let adapter = create_adapter(10, None);
+        let mut tracker = TipTracker::new();
+
+        let tip = tracker.poll(&adapter).await.unwrap();
+        assert_eq!(tip.available_height, None);
+        assert!(tip.is_lagging());
+    }
+
+    #[tokio::test]
+    async fn test_tip_tracker_indexed_height_drops() {
+        let adapter = create_adapter(50, Some(50));
+        let mut tracker = TipTracker::new();
+        assert_eq!(tracker.poll(&adapter).await.unwrap().available_height, Some(50));
+
+        // The indexer restarts and rebuilds from an earlier height
+        adapter.clear();
+        adapter.set_height(30);
+        for height in 0..=20 {
+            adapter.set_block_hash(height, vec![height as u8; 32]);
+        }
+
+        let tip = tracker.poll(&adapter).await.unwrap();
+        assert_eq!(tip.indexed_height, 30);
+        assert_eq!(tip.available_height, Some(20));
+    }
+
+    #[test]
+    fn test_block_not_available_errors() {
+        let error = |message: &str| Error::MetashrewClient(message.to_string());
+        assert!(is_block_not_available(&error("Block hash not found for height 10")));
+        assert!(is_block_not_available(&error("JSON-RPC error: block not yet indexed (code: -32000)")));
+
+        // The generic server error code alone is a real failure
+        assert!(!is_block_not_available(&error("JSON-RPC error: database is corrupt (code: -32000)")));
+        assert!(!is_block_not_available(&error("Failed to send request: connection refused")));
+    }
+}
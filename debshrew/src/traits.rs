@@ -23,6 +23,8 @@
 use crate::error::Result;
 use async_trait::async_trait;
 use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 /// Trait for providing block data and metadata
 ///
@@ -55,6 +57,67 @@ pub trait BlockProviderLike: Send + Sync + Debug {
     ///
     /// Returns an error if the request fails or block doesn't exist
     async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>>;
+    
+    /// Get the block hashes for a range of heights
+    ///
+    /// The default implementation requests one height at a time. Clients that
+    /// can batch requests should override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `heights` - The block heights
+    ///
+    /// # Returns
+    ///
+    /// The block hashes as bytes, in height order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hash of any of the heights cannot be fetched
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        let mut hashes = Vec::new();
+        for height in heights {
+            hashes.push(self.get_block_hash(height).await?);
+        }
+        Ok(hashes)
+    }
+}
+
+/// A view function call
+///
+/// Used to send several view calls at once with
+/// [`ViewProviderLike::call_views`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ViewCall {
+    /// The name of the view function
+    pub view_name: String,
+    
+    /// The parameters to pass to the view function
+    pub params: Vec<u8>,
+    
+    /// The block height to query at (optional)
+    pub height: Option<u32>,
+}
+
+impl ViewCall {
+    /// Create a new view call
+    ///
+    /// # Arguments
+    ///
+    /// * `view_name` - The name of the view function
+    /// * `params` - The parameters to pass to the view function
+    /// * `height` - The block height to query at (optional)
+    ///
+    /// # Returns
+    ///
+    /// A new view call
+    pub fn new(view_name: &str, params: &[u8], height: Option<u32>) -> Self {
+        Self {
+            view_name: view_name.to_string(),
+            params: params.to_vec(),
+            height,
+        }
+    }
 }
 
 /// Trait for executing view functions
@@ -79,6 +142,31 @@ pub trait ViewProviderLike: Send + Sync + Debug {
     ///
     /// Returns an error if the request fails
     async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>>;
+    
+    /// Call several view functions
+    ///
+    /// The default implementation makes one call at a time. Clients that can
+    /// batch requests should override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `calls` - The view calls
+    ///
+    /// # Returns
+    ///
+    /// The result of each call, in the order of the calls
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the calls cannot be sent at all; failures of
+    /// individual calls are returned in their results
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        let mut results = Vec::with_capacity(calls.len());
+        for call in calls {
+            results.push(self.call_view(&call.view_name, &call.params, call.height).await);
+        }
+        Ok(results)
+    }
 }
 
 /// Generic trait for metashrew client backends
@@ -125,6 +213,45 @@ pub trait MetashrewClientLike: BlockProviderLike + ViewProviderLike + Send + Syn
     }
 }
 
+// Shared clients, including trait objects, can be used wherever a client is expected
+
+#[async_trait]
+impl<T: BlockProviderLike + ?Sized> BlockProviderLike for Arc<T> {
+    async fn get_height(&self) -> Result<u32> {
+        (**self).get_height().await
+    }
+    
+    async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>> {
+        (**self).get_block_hash(height).await
+    }
+    
+    async fn get_block_hashes(&self, heights: RangeInclusive<u32>) -> Result<Vec<Vec<u8>>> {
+        (**self).get_block_hashes(heights).await
+    }
+}
+
+#[async_trait]
+impl<T: ViewProviderLike + ?Sized> ViewProviderLike for Arc<T> {
+    async fn call_view(&self, view_name: &str, params: &[u8], height: Option<u32>) -> Result<Vec<u8>> {
+        (**self).call_view(view_name, params, height).await
+    }
+    
+    async fn call_views(&self, calls: &[ViewCall]) -> Result<Vec<Result<Vec<u8>>>> {
+        (**self).call_views(calls).await
+    }
+}
+
+#[async_trait]
+impl<T: MetashrewClientLike + ?Sized> MetashrewClientLike for Arc<T> {
+    fn get_identifier(&self) -> String {
+        (**self).get_identifier()
+    }
+    
+    async fn is_healthy(&self) -> bool {
+        (**self).is_healthy().await
+    }
+}
+
 /// Trait for blockchain simulation in tests
 ///
 /// This trait provides additional functionality needed for testing,
@@ -46,6 +46,7 @@ impl DebTransform for LoggingTransform {
                 block_height: height,
                 block_hash: hex::encode(&block_hash),
                 transaction_id: Some(format!("tx-{}", self.blocks_processed)),
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,
@@ -93,6 +94,7 @@ impl DebTransform for LoggingTransform {
                 block_height: height,
                 block_hash: "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
                 transaction_id: Some(format!("tx-{}", self.blocks_processed)),
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Delete,
@@ -22,6 +22,7 @@ impl DebTransform for MinimalTransform {
                 block_height: height,
                 block_hash: hex::encode(&hash),
                 transaction_id: None,
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,
@@ -51,6 +51,7 @@ impl DebTransform for ViewTestTransform {
                 block_height: height,
                 block_hash: hex::encode(&hash),
                 transaction_id: None,
+                inverse: false,
             },
             payload: CdcPayload {
                 operation: CdcOperation::Create,